@@ -0,0 +1,153 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::io::Cursor;
+use xbf_rs::{
+    XbfMetadata, XbfMetadataUpcast, XbfNumericVec, XbfPrimitive, XbfPrimitiveMetadata, XbfStruct,
+    XbfStructMetadata, XbfType, XbfTypeRef, XbfVec, XbfVecMetadata,
+};
+
+const ENTITIES: usize = 10_000;
+const SAMPLES: usize = 60_000;
+
+fn position_metadata() -> XbfStructMetadata {
+    XbfStructMetadata::new(
+        "position".to_string(),
+        vec![
+            ("x".to_string(), XbfPrimitiveMetadata::F32.into()),
+            ("y".to_string(), XbfPrimitiveMetadata::F32.into()),
+            ("z".to_string(), XbfPrimitiveMetadata::F32.into()),
+        ],
+    )
+}
+
+fn entity_metadata(position: &XbfStructMetadata) -> XbfStructMetadata {
+    XbfStructMetadata::new(
+        "entity".to_string(),
+        vec![
+            ("id".to_string(), XbfPrimitiveMetadata::U64.into()),
+            ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+            ("position".to_string(), position.to_base_metadata()),
+        ],
+    )
+}
+
+fn entity(entity: &XbfStructMetadata, position: &XbfStructMetadata, id: u64) -> XbfType {
+    let position = XbfStruct::new(
+        position.clone(),
+        vec![
+            XbfPrimitive::F32(id as f32).into(),
+            XbfPrimitive::F32(0.5).into(),
+            XbfPrimitive::F32(-1.0).into(),
+        ],
+    )
+    .unwrap();
+    XbfStruct::new(
+        entity.clone(),
+        vec![
+            XbfPrimitive::U64(id).into(),
+            XbfPrimitive::String(format!("entity {id}")).into(),
+            position.into(),
+        ],
+    )
+    .unwrap()
+    .into()
+}
+
+fn decode_vec_of_nested_structs(c: &mut Criterion) {
+    let position = position_metadata();
+    let entity_metadata = entity_metadata(&position);
+    let metadata = XbfVecMetadata::new(entity_metadata.to_base_metadata());
+    let entities = (0..ENTITIES as u64)
+        .map(|id| entity(&entity_metadata, &position, id))
+        .collect();
+    let vec = XbfVec::new(metadata.clone(), entities).unwrap();
+    let mut bytes = vec![];
+    vec.serialize_vec_type(&mut bytes).unwrap();
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(ENTITIES as u64));
+    group.bench_function("vec_of_nested_structs", |b| {
+        b.iter(|| {
+            let mut reader = Cursor::new(black_box(bytes.as_slice()));
+            XbfVec::deserialize_vec_type(&metadata, &mut reader).unwrap()
+        })
+    });
+    group.bench_function("vec_of_nested_structs_borrowed", |b| {
+        let metadata = metadata.to_base_metadata();
+        b.iter(|| {
+            let mut input = black_box(bytes.as_slice());
+            XbfTypeRef::deserialize_base_type(&metadata, &mut input).unwrap()
+        })
+    });
+    group.finish();
+}
+
+/// Copies every name and field list of struct metadata, which is what cloning it cost before
+/// metadata was shared.
+fn deep_clone(metadata: &XbfStructMetadata) -> XbfStructMetadata {
+    let fields = metadata
+        .fields()
+        .iter()
+        .map(|(name, x)| {
+            let x = match x {
+                XbfMetadata::Struct(x) => deep_clone(x).into(),
+                x => x.clone(),
+            };
+            (name.as_str().to_string(), x)
+        })
+        .collect();
+    XbfStructMetadata::new(metadata.name().to_string(), fields)
+}
+
+fn clone_metadata_per_element(c: &mut Criterion) {
+    let entity_metadata = entity_metadata(&position_metadata());
+
+    let mut group = c.benchmark_group("clone_metadata");
+    group.throughput(Throughput::Elements(ENTITIES as u64));
+    group.bench_function("shared", |b| {
+        b.iter(|| {
+            (0..ENTITIES)
+                .map(|_| black_box(&entity_metadata).clone())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("deep_copy_baseline", |b| {
+        b.iter(|| {
+            (0..ENTITIES)
+                .map(|_| deep_clone(black_box(&entity_metadata)))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+fn decode_vec_of_floats(c: &mut Criterion) {
+    let samples: Vec<f32> = (0..SAMPLES).map(|x| x as f32 * 0.25).collect();
+    let numeric = XbfNumericVec::new(samples);
+    let metadata = numeric.get_metadata();
+    let mut bytes = vec![];
+    numeric.serialize_numeric_vec_type(&mut bytes).unwrap();
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(SAMPLES as u64));
+    group.bench_function("vec_of_floats", |b| {
+        b.iter(|| {
+            let mut reader = Cursor::new(black_box(bytes.as_slice()));
+            XbfVec::deserialize_vec_type(&metadata, &mut reader).unwrap()
+        })
+    });
+    group.bench_function("numeric_vec_of_floats", |b| {
+        b.iter(|| {
+            let mut reader = Cursor::new(black_box(bytes.as_slice()));
+            XbfNumericVec::<f32>::deserialize_numeric_vec_type(&mut reader).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    decode_vec_of_nested_structs,
+    clone_metadata_per_element,
+    decode_vec_of_floats
+);
+criterion_main!(benches);
@@ -0,0 +1,478 @@
+use crate::{decoder::Progress, Result, XbfConfig, XbfMetadata, XbfType};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The fewest bytes room is made for whenever an [`XbfAsyncReader`] reads.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// Buffers a tokio [`AsyncRead`] so values can be deserialized from it asynchronously.
+///
+/// Bytes are kept in the buffer until a whole value has been read from them, so a
+/// [`deserialize_async`](XbfType::deserialize_async) future that is dropped before it finishes,
+/// such as when it loses a `tokio::select!`, loses no data. The next call for the same metadata
+/// picks up where it left off, while any other call starts over from the first byte of the
+/// unfinished value. After an error, where the next value starts is unspecified.
+///
+/// Values are decoded as their bytes arrive and the reader remembers how far it got, so no byte is
+/// decoded twice however slowly a value arrives.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::XbfAsyncReader;
+/// use xbf_rs::XbfAsyncWriter;
+/// use xbf_rs::XbfMetadata;
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfType;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (client, server) = tokio::io::duplex(64);
+/// let mut writer = XbfAsyncWriter::new(client);
+/// let mut reader = XbfAsyncReader::new(server);
+///
+/// let value = XbfType::from(XbfPrimitive::String("hello".to_string()));
+/// XbfMetadata::from(&value).serialize_async(&mut writer).await.unwrap();
+/// value.serialize_async(&mut writer).await.unwrap();
+///
+/// let metadata = XbfMetadata::deserialize_async(&mut reader).await.unwrap();
+/// let read = XbfType::deserialize_async(&metadata, &mut reader).await.unwrap();
+///
+/// assert_eq!(read, value);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct XbfAsyncReader<R> {
+    reader: R,
+    config: XbfConfig,
+    buf: Vec<u8>,
+    /// The number of bytes at the start of the buffer that belong to values already read.
+    pos: usize,
+    bytes_read: u64,
+    /// The header or value partway through being decoded, and how many bytes after `pos` it has
+    /// taken in.
+    partial: Option<(Target, Progress, usize)>,
+}
+
+/// What an [`XbfAsyncReader`] is partway through decoding.
+#[derive(Debug, PartialEq)]
+enum Target {
+    Metadata,
+    Value(XbfMetadata),
+}
+
+impl<R: AsyncRead + Unpin> XbfAsyncReader<R> {
+    /// Creates a new [`XbfAsyncReader`].
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, &XbfConfig::default())
+    }
+
+    /// Creates a new [`XbfAsyncReader`] that deserializes using the given [`XbfConfig`].
+    pub fn with_config(reader: R, config: &XbfConfig) -> Self {
+        Self {
+            reader,
+            config: *config,
+            buf: vec![],
+            pos: 0,
+            bytes_read: 0,
+            partial: None,
+        }
+    }
+
+    /// Decodes one header or value from the buffer, reading more whenever it runs out.
+    async fn decode<T>(
+        &mut self,
+        target: Target,
+        advance: impl Fn(&mut Progress, &mut &[u8], &XbfConfig) -> Result<Option<T>>,
+    ) -> Result<T> {
+        if self.partial.as_ref().is_some_and(|(x, ..)| *x != target) {
+            self.partial = None;
+        }
+        let (_, progress, fed) = self
+            .partial
+            .get_or_insert_with(|| (target, Progress::default(), 0));
+        loop {
+            let mut input = &self.buf[self.pos + *fed..];
+            let len = input.len();
+            let result = advance(progress, &mut input, &self.config);
+            *fed += len - input.len();
+            let offset = self.bytes_read + *fed as u64;
+            match result {
+                Ok(Some(x)) => {
+                    self.pos += *fed;
+                    self.bytes_read = offset;
+                    self.partial = None;
+                    return Ok(x);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.partial = None;
+                    return Err(e.at_offset(offset));
+                }
+            }
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+            self.buf.reserve(READ_CHUNK);
+            if self.reader.read_buf(&mut self.buf).await? == 0 {
+                let err = progress.unexpected_eof().at_offset(offset);
+                self.partial = None;
+                return Err(err);
+            }
+        }
+    }
+}
+
+impl<R> XbfAsyncReader<R> {
+    /// Returns the bytes that have been read from the underlying reader but not yet decoded.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// Any bytes in [`Self::buffer`] are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Buffers a tokio [`AsyncWrite`] so values can be serialized to it asynchronously.
+///
+/// A value is serialized into the buffer in full the first time a
+/// [`serialize_async`](XbfType::serialize_async) future is polled, and the future then writes
+/// out the buffer. If the future is dropped before it finishes, the rest of the value stays in
+/// the buffer and is written out ahead of anything else by the next call, so values are never
+/// cut short or interleaved. Use [`Self::flush`] to write out the buffer without writing another
+/// value.
+///
+/// See [`XbfAsyncReader`] for an example.
+#[derive(Debug)]
+pub struct XbfAsyncWriter<W> {
+    writer: W,
+    config: XbfConfig,
+    buf: Vec<u8>,
+    /// The number of bytes at the start of the buffer that have already been written.
+    pos: usize,
+}
+
+impl<W: AsyncWrite + Unpin> XbfAsyncWriter<W> {
+    /// Creates a new [`XbfAsyncWriter`].
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, &XbfConfig::default())
+    }
+
+    /// Creates a new [`XbfAsyncWriter`] that serializes using the given [`XbfConfig`].
+    pub fn with_config(writer: W, config: &XbfConfig) -> Self {
+        Self {
+            writer,
+            config: *config,
+            buf: vec![],
+            pos: 0,
+        }
+    }
+
+    /// Writes out the buffer, then flushes the underlying writer.
+    ///
+    /// This is cancellation safe, anything not yet written stays in the buffer.
+    pub async fn flush(&mut self) -> Result<()> {
+        self.write_buffer().await?;
+        Ok(self.writer.flush().await?)
+    }
+
+    /// Serializes one value to the end of the buffer, leaving the buffer as it was on failure.
+    fn encode(
+        &mut self,
+        encode: impl FnOnce(&mut Vec<u8>, &XbfConfig) -> Result<()>,
+    ) -> Result<()> {
+        let len = self.buf.len();
+        encode(&mut self.buf, &self.config).inspect_err(|_| self.buf.truncate(len))
+    }
+
+    async fn write_buffer(&mut self) -> Result<()> {
+        while self.pos < self.buf.len() {
+            let written = self.writer.write(&self.buf[self.pos..]).await?;
+            if written == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+            }
+            self.pos += written;
+        }
+        self.buf.clear();
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<W> XbfAsyncWriter<W> {
+    /// Returns the bytes that have been serialized but not yet written to the underlying writer.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns the underlying writer.
+    ///
+    /// Any bytes in [`Self::buffer`] are lost.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl XbfMetadata {
+    /// Serialize any XBF metadata to an [`XbfAsyncWriter`], using its [`XbfConfig`].
+    ///
+    /// This is cancellation safe, see [`XbfAsyncWriter`] for details.
+    pub async fn serialize_async<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut XbfAsyncWriter<W>,
+    ) -> Result<()> {
+        writer.encode(|buf, config| self.serialize_base_metadata_with_config(buf, config))?;
+        writer.write_buffer().await
+    }
+
+    /// Deserialize any XBF metadata from an [`XbfAsyncReader`], using its [`XbfConfig`].
+    ///
+    /// This is cancellation safe, see [`XbfAsyncReader`] for details.
+    pub async fn deserialize_async<R: AsyncRead + Unpin>(
+        reader: &mut XbfAsyncReader<R>,
+    ) -> Result<XbfMetadata> {
+        reader.decode(Target::Metadata, Progress::metadata).await
+    }
+}
+
+impl XbfType {
+    /// Serialize any XBF type to an [`XbfAsyncWriter`], using its [`XbfConfig`].
+    ///
+    /// This is cancellation safe, see [`XbfAsyncWriter`] for details.
+    pub async fn serialize_async<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut XbfAsyncWriter<W>,
+    ) -> Result<()> {
+        writer.encode(|buf, config| self.serialize_base_type_with_config(buf, config))?;
+        writer.write_buffer().await
+    }
+
+    /// Deserialize any XBF type described by the given metadata from an [`XbfAsyncReader`],
+    /// using its [`XbfConfig`].
+    ///
+    /// This is cancellation safe, see [`XbfAsyncReader`] for details.
+    pub async fn deserialize_async<R: AsyncRead + Unpin>(
+        metadata: &XbfMetadata,
+        reader: &mut XbfAsyncReader<R>,
+    ) -> Result<XbfType> {
+        reader
+            .decode(
+                Target::Value(metadata.clone()),
+                |progress, input, config| progress.value(input, metadata, config),
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        LengthEncoding, XbfError, XbfPrimitive, XbfPrimitiveMetadata, XbfStruct, XbfStructMetadata,
+        XbfVec, XbfVecMetadata,
+    };
+    use tokio::io::duplex;
+    use tokio_test::task;
+
+    fn test_value(len: usize) -> XbfType {
+        let metadata = XbfStructMetadata::new(
+            "test".to_string(),
+            vec![
+                ("id".to_string(), XbfPrimitiveMetadata::U64.into()),
+                (
+                    "data".to_string(),
+                    XbfVecMetadata::new(XbfPrimitiveMetadata::String.into()).into(),
+                ),
+            ],
+        );
+        let data = XbfVec::new_unchecked(
+            XbfVecMetadata::new(XbfPrimitiveMetadata::String.into()),
+            (0..len)
+                .map(|i| XbfPrimitive::String(i.to_string()).into())
+                .collect(),
+        );
+        XbfStruct::new(metadata, vec![XbfPrimitive::U64(7).into(), data.into()])
+            .unwrap()
+            .into()
+    }
+
+    #[tokio::test]
+    async fn round_trip_over_duplex_works() {
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::Varint);
+        let (client, server) = duplex(16);
+        let values: Vec<_> = (0..20).map(test_value).collect();
+        let metadata = XbfMetadata::from(&values[0]);
+
+        let write = async {
+            let mut writer = XbfAsyncWriter::with_config(client, &config);
+            metadata.serialize_async(&mut writer).await.unwrap();
+            for value in &values {
+                value.serialize_async(&mut writer).await.unwrap();
+            }
+            writer.flush().await.unwrap();
+        };
+        let read = async {
+            let mut reader = XbfAsyncReader::with_config(server, &config);
+            let metadata = XbfMetadata::deserialize_async(&mut reader).await.unwrap();
+            let mut read = vec![];
+            for _ in 0..values.len() {
+                read.push(
+                    XbfType::deserialize_async(&metadata, &mut reader)
+                        .await
+                        .unwrap(),
+                );
+            }
+            assert!(reader.buffer().is_empty());
+            read
+        };
+        let ((), read) = tokio::join!(write, read);
+
+        assert_eq!(read, values);
+    }
+
+    #[tokio::test]
+    async fn cancelled_read_loses_no_data() {
+        let (mut client, server) = duplex(1024);
+        let value = test_value(10);
+        let metadata = XbfMetadata::from(&value);
+        let mut bytes = vec![];
+        value.serialize_base_type(&mut bytes).unwrap();
+        let (first, second) = bytes.split_at(bytes.len() / 2);
+        let mut reader = XbfAsyncReader::new(server);
+
+        client.write_all(first).await.unwrap();
+        let mut read = task::spawn(XbfType::deserialize_async(&metadata, &mut reader));
+        assert!(read.poll().is_pending());
+        drop(read);
+        assert_eq!(reader.buffer(), first);
+
+        client.write_all(second).await.unwrap();
+        let read = XbfType::deserialize_async(&metadata, &mut reader)
+            .await
+            .unwrap();
+
+        assert_eq!(read, value);
+    }
+
+    #[tokio::test]
+    async fn large_value_arriving_slowly_is_read() {
+        let (client, server) = duplex(64);
+        let value = test_value(60_000);
+        let metadata = XbfMetadata::from(&value);
+
+        let write = async {
+            let mut writer = XbfAsyncWriter::new(client);
+            value.serialize_async(&mut writer).await.unwrap();
+        };
+        let read = async {
+            let mut reader = XbfAsyncReader::new(server);
+            XbfType::deserialize_async(&metadata, &mut reader)
+                .await
+                .unwrap()
+        };
+        let ((), read) = tokio::join!(write, read);
+
+        assert_eq!(read, value);
+    }
+
+    #[tokio::test]
+    async fn cancelled_read_is_started_over_for_other_metadata() {
+        let (mut client, server) = duplex(1024);
+        let mut reader = XbfAsyncReader::new(server);
+        let string = XbfMetadata::from(XbfPrimitiveMetadata::String);
+
+        client.write_all(&[4, 0, b'a']).await.unwrap();
+        let mut read = task::spawn(XbfType::deserialize_async(&string, &mut reader));
+        assert!(read.poll().is_pending());
+        drop(read);
+
+        let read = XbfType::deserialize_async(&XbfPrimitiveMetadata::U16.into(), &mut reader)
+            .await
+            .unwrap();
+
+        assert_eq!(read, XbfPrimitive::U16(4).into());
+        assert_eq!(reader.buffer(), b"a");
+    }
+
+    #[tokio::test]
+    async fn cancelled_write_is_finished_by_the_next_one() {
+        let (client, mut server) = duplex(8);
+        let values = [test_value(10), test_value(3)];
+        let mut writer = XbfAsyncWriter::new(client);
+
+        let mut write = task::spawn(values[0].serialize_async(&mut writer));
+        assert!(write.poll().is_pending());
+        drop(write);
+        assert!(!writer.buffer().is_empty());
+
+        let write = async {
+            values[1].serialize_async(&mut writer).await.unwrap();
+            drop(writer);
+        };
+        let read = async {
+            let mut bytes = vec![];
+            server.read_to_end(&mut bytes).await.unwrap();
+            bytes
+        };
+        let ((), bytes) = tokio::join!(write, read);
+
+        let mut expected = vec![];
+        values[0].serialize_base_type(&mut expected).unwrap();
+        values[1].serialize_base_type(&mut expected).unwrap();
+        assert_eq!(bytes, expected);
+    }
+
+    #[tokio::test]
+    async fn truncated_value_fails() {
+        let (mut client, server) = duplex(1024);
+        client.write_all(&[3, 0, b'a']).await.unwrap();
+        drop(client);
+        let mut reader = XbfAsyncReader::new(server);
+
+        let err = XbfType::deserialize_async(&XbfPrimitiveMetadata::String.into(), &mut reader)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(err.offset(), Some(3));
+
+        let (mut client, server) = duplex(1024);
+        let value = test_value(3);
+        let mut bytes = vec![];
+        value.serialize_base_type(&mut bytes).unwrap();
+        client.write_all(&bytes[..bytes.len() - 1]).await.unwrap();
+        drop(client);
+        let mut reader = XbfAsyncReader::new(server);
+
+        let err = XbfType::deserialize_async(&XbfMetadata::from(&value), &mut reader)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(err.path(), Some("root.data[2]"));
+        assert_eq!(err.offset(), Some(bytes.len() as u64 - 1));
+    }
+
+    #[tokio::test]
+    async fn failed_serialize_leaves_buffer_unchanged() {
+        let (client, _server) = duplex(1024);
+        let mut writer = XbfAsyncWriter::new(client);
+        let value = XbfType::from(XbfPrimitive::Bytes(vec![0; u16::MAX as usize + 1]));
+
+        assert!(value.serialize_async(&mut writer).await.is_err());
+        assert!(writer.buffer().is_empty());
+    }
+}
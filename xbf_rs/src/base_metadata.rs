@@ -1,41 +1,392 @@
 use crate::{
+    util::{ReadContext, ReadSkip, SeekSkip, Skip},
+    xbf_array::{XbfArrayMetadata, ARRAY_METADATA_DISCRIMINANT},
+    xbf_enum::{XbfEnumMetadata, ENUM_METADATA_DISCRIMINANT},
+    xbf_map::{XbfMapMetadata, MAP_METADATA_DISCRIMINANT},
+    xbf_optional::{XbfOptionalMetadata, OPTIONAL_METADATA_DISCRIMINANT},
     xbf_primitive::XbfPrimitiveMetadata,
     xbf_struct::{XbfStructMetadata, STRUCT_METADATA_DISCRIMINANT},
     xbf_vec::{XbfVecMetadata, VEC_METADATA_DISCRIMINANT},
-    XbfType,
+    LengthEncoding, Result, XbfConfig, XbfError, XbfType,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt::Display,
+    io::{Read, Seek, Write},
 };
-use byteorder::ReadBytesExt;
-use std::io::{self, Read, Write};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum XbfMetadata {
     Primitive(XbfPrimitiveMetadata),
     Vec(XbfVecMetadata),
     Struct(XbfStructMetadata),
+    Optional(XbfOptionalMetadata),
+    Enum(XbfEnumMetadata),
+    Map(XbfMapMetadata),
+    Array(XbfArrayMetadata),
 }
 
 impl XbfMetadata {
-    pub fn serialize_base_metadata(&self, writer: &mut impl Write) -> io::Result<()> {
+    pub fn serialize_base_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_base_metadata_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize any XBF metadata using the given [`XbfConfig`].
+    pub fn serialize_base_metadata_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
         match self {
             XbfMetadata::Primitive(x) => x.serialize_primitive_metadata(writer),
-            XbfMetadata::Vec(x) => x.serialize_vec_metadata(writer),
-            XbfMetadata::Struct(x) => x.serialize_struct_metadata(writer),
+            XbfMetadata::Vec(x) => x.serialize_vec_metadata_with_config(writer, config),
+            XbfMetadata::Struct(x) => x.serialize_struct_metadata_with_config(writer, config),
+            XbfMetadata::Optional(x) => x.serialize_optional_metadata_with_config(writer, config),
+            XbfMetadata::Enum(x) => x.serialize_enum_metadata_with_config(writer, config),
+            XbfMetadata::Map(x) => x.serialize_map_metadata_with_config(writer, config),
+            XbfMetadata::Array(x) => x.serialize_array_metadata_with_config(writer, config),
         }
     }
 
-    pub fn deserialize_base_metadata(reader: &mut impl Read) -> io::Result<XbfMetadata> {
+    pub fn deserialize_base_metadata(reader: &mut impl Read) -> Result<XbfMetadata> {
+        Self::deserialize_base_metadata_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize any XBF metadata using the given [`XbfConfig`].
+    pub fn deserialize_base_metadata_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfMetadata> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_base_metadata_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_base_metadata_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfMetadata> {
         let discriminant = reader.read_u8()?;
         if let Ok(x) = XbfPrimitiveMetadata::try_from(discriminant) {
             Ok(XbfMetadata::Primitive(x))
         } else if discriminant == VEC_METADATA_DISCRIMINANT {
-            Ok(XbfVecMetadata::deserialize_vec_metadata(reader)?.to_base_metadata())
+            Ok(XbfVecMetadata::deserialize_vec_metadata_with_context(reader)?.to_base_metadata())
         } else if discriminant == STRUCT_METADATA_DISCRIMINANT {
-            Ok(XbfStructMetadata::deserialize_struct_metadata(reader)?.to_base_metadata())
+            Ok(
+                XbfStructMetadata::deserialize_struct_metadata_with_context(reader)?
+                    .to_base_metadata(),
+            )
+        } else if discriminant == OPTIONAL_METADATA_DISCRIMINANT {
+            Ok(
+                XbfOptionalMetadata::deserialize_optional_metadata_with_context(reader)?
+                    .to_base_metadata(),
+            )
+        } else if discriminant == ENUM_METADATA_DISCRIMINANT {
+            Ok(XbfEnumMetadata::deserialize_enum_metadata_with_context(reader)?.to_base_metadata())
+        } else if discriminant == MAP_METADATA_DISCRIMINANT {
+            Ok(XbfMapMetadata::deserialize_map_metadata_with_context(reader)?.to_base_metadata())
+        } else if discriminant == ARRAY_METADATA_DISCRIMINANT {
+            Ok(
+                XbfArrayMetadata::deserialize_array_metadata_with_context(reader)?
+                    .to_base_metadata(),
+            )
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Unknown metadata discriminant {}", discriminant),
-            ))
+            Err(XbfError::UnknownDiscriminant(discriminant))
+        }
+    }
+
+    /// Returns the number of bytes [`Self::serialize_base_metadata`] would write, without
+    /// serializing.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_base_metadata_with_config`] would write
+    /// using the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        match self {
+            XbfMetadata::Primitive(x) => x.encoded_len(),
+            XbfMetadata::Vec(x) => x.encoded_len_with_config(config),
+            XbfMetadata::Struct(x) => x.encoded_len_with_config(config),
+            XbfMetadata::Optional(x) => x.encoded_len_with_config(config),
+            XbfMetadata::Enum(x) => x.encoded_len_with_config(config),
+            XbfMetadata::Map(x) => x.encoded_len_with_config(config),
+            XbfMetadata::Array(x) => x.encoded_len_with_config(config),
+        }
+    }
+
+    /// Returns the encoded size of every value described by this metadata, or `None` if values
+    /// can differ in size.
+    ///
+    /// Vectors, maps, strings and bytes carry their length so never have a fixed size, while
+    /// structs and arrays do when everything inside them does. An optional or enum only does when
+    /// its values can't differ in size, such as an enum whose variants all carry the same size.
+    /// Lengths don't come into it, so the result is the same for every [`XbfConfig`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfStructMetadata;
+    ///
+    /// let position: XbfMetadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 3).into();
+    /// assert_eq!(position.fixed_size(), Some(12));
+    ///
+    /// let player: XbfMetadata = XbfStructMetadata::new(
+    ///     "player".to_string(),
+    ///     vec![
+    ///         ("position".to_string(), position),
+    ///         ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+    ///     ],
+    /// )
+    /// .into();
+    /// assert_eq!(player.fixed_size(), None);
+    /// ```
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            XbfMetadata::Primitive(x) => x.fixed_size(),
+            XbfMetadata::Vec(_) | XbfMetadata::Map(_) => None,
+            XbfMetadata::Struct(x) => x
+                .fields
+                .iter()
+                .try_fold(0usize, |acc, (_, x)| acc.checked_add(x.fixed_size()?)),
+            XbfMetadata::Optional(x) => match x.inner_type.fixed_size() {
+                Some(0) => Some(1),
+                _ => None,
+            },
+            XbfMetadata::Enum(x) => {
+                let (_, first) = x.variants.first()?;
+                let size = first.fixed_size()?;
+                x.variants
+                    .iter()
+                    .all(|(_, x)| x.fixed_size() == Some(size))
+                    .then_some(2 + size)
+            }
+            XbfMetadata::Array(x) => x.inner_type.fixed_size()?.checked_mul(x.len()),
+        }
+    }
+
+    /// Moves the reader past exactly one value described by this metadata, without building it.
+    ///
+    /// Only as much of the value is read as is needed to find where it ends, so the contents of
+    /// strings and chars are not checked to be valid and maps are not checked for duplicate keys.
+    /// If the reader also implements [`Seek`], use [`Self::skip_value_seek`] to avoid reading
+    /// the skipped bytes at all.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the reader, returns an error if a length, presence byte or enum
+    /// variant index is invalid.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfVecMetadata;
+    /// use byteorder::ReadBytesExt;
+    ///
+    /// let metadata: XbfMetadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U32.into()).into();
+    /// let mut data = vec![];
+    /// data.extend_from_slice(&2u16.to_le_bytes());
+    /// data.extend_from_slice(&[0; 8]);
+    /// data.push(42);
+    /// let mut reader = std::io::Cursor::new(data);
+    ///
+    /// metadata.skip_value(&mut reader).unwrap();
+    ///
+    /// assert_eq!(reader.read_u8().unwrap(), 42);
+    /// ```
+    pub fn skip_value(&self, reader: &mut impl Read) -> Result<()> {
+        self.skip_value_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Skip a value using the given [`XbfConfig`].
+    ///
+    /// See [`Self::skip_value`] for more details.
+    pub fn skip_value_with_config(&self, reader: &mut impl Read, config: &XbfConfig) -> Result<()> {
+        ReadContext::decode(ReadSkip(reader), config, |reader| {
+            self.skip_value_with_context(reader)
+        })
+    }
+
+    /// Moves the reader past exactly one value described by this metadata, seeking over anything
+    /// whose length is already known instead of reading it.
+    ///
+    /// See [`Self::skip_value`] for more details.
+    pub fn skip_value_seek(&self, reader: &mut (impl Read + Seek)) -> Result<()> {
+        self.skip_value_seek_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Skip a value by seeking using the given [`XbfConfig`].
+    ///
+    /// See [`Self::skip_value`] for more details.
+    pub fn skip_value_seek_with_config(
+        &self,
+        reader: &mut (impl Read + Seek),
+        config: &XbfConfig,
+    ) -> Result<()> {
+        ReadContext::decode(SeekSkip(reader), config, |reader| {
+            self.skip_value_with_context(reader)
+        })
+    }
+
+    /// Checks that this metadata, and all metadata nested inside it, can be sent and read back
+    /// without ambiguity.
+    ///
+    /// Every field of a struct and every variant of an enum must have a name that is not empty
+    /// and not shared with another field or variant of the same type, an enum can have at most
+    /// `u16::MAX + 1` variants, and every name must fit in the length encoding of
+    /// [`XbfConfig::default`]. Use [`Self::validate_with_config`] to check names against the
+    /// length encoding you send with instead. Metadata built with `new` is not checked, while deserialized metadata always has its
+    /// fields and variants checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XbfError::InvalidMetadata`] describing the first problem found, wrapped in
+    /// [`XbfError::At`] if it was found inside a nested type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::InvalidMetadataError;
+    /// use xbf_rs::XbfError;
+    /// use xbf_rs::XbfMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfStructMetadata;
+    /// use xbf_rs::XbfVecMetadata;
+    ///
+    /// let point = XbfStructMetadata::new(
+    ///     "point".to_string(),
+    ///     vec![
+    ///         ("x".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("x".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///     ],
+    /// );
+    /// let metadata: XbfMetadata = XbfStructMetadata::new(
+    ///     "path".to_string(),
+    ///     vec![("points".to_string(), XbfVecMetadata::new(point.into()).into())],
+    /// )
+    /// .into();
+    ///
+    /// let err = metadata.validate().unwrap_err();
+    ///
+    /// assert!(matches!(
+    ///     err.root_cause(),
+    ///     XbfError::InvalidMetadata(InvalidMetadataError::DuplicateName { index: 1, .. })
+    /// ));
+    /// assert_eq!(err.path(), Some("root.points[]"));
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        self.validate_with_config(&XbfConfig::default())
+    }
+
+    /// Validate metadata using the given [`XbfConfig`], which decides how long names can be.
+    ///
+    /// See [`Self::validate`] for more details.
+    pub fn validate_with_config(&self, config: &XbfConfig) -> Result<()> {
+        let validate_named = |name: &str, entries: &[(String, XbfMetadata)], separator, kind| {
+            check_name_len(name, config)?;
+            check_names(entries)?;
+            entries.iter().enumerate().try_for_each(|(i, (name, x))| {
+                check_name_len(name, config).map_err(|e| {
+                    XbfError::from(e).within(format_args!("{separator}<{kind} {i}>"))
+                })?;
+                x.validate_with_config(config)
+                    .map_err(|e| e.within(format_args!("{separator}{name}")))
+            })
+        };
+        match self {
+            XbfMetadata::Primitive(_) => Ok(()),
+            XbfMetadata::Vec(x) => x
+                .inner_type
+                .validate_with_config(config)
+                .map_err(|e| e.within("[]")),
+            XbfMetadata::Struct(x) => validate_named(x.name(), &x.fields, ".", "field"),
+            XbfMetadata::Optional(x) => x.inner_type.validate_with_config(config),
+            XbfMetadata::Enum(x) => {
+                check_variant_count(&x.variants)?;
+                validate_named(x.name(), &x.variants, "::", "variant")
+            }
+            XbfMetadata::Map(x) => x
+                .value_type
+                .validate_with_config(config)
+                .map_err(|e| e.within("[]")),
+            XbfMetadata::Array(x) => x
+                .inner_type
+                .validate_with_config(config)
+                .map_err(|e| e.within("[]")),
+        }
+    }
+
+    pub(crate) fn skip_value_with_context(
+        &self,
+        reader: &mut ReadContext<impl Skip>,
+    ) -> Result<()> {
+        match self {
+            XbfMetadata::Primitive(x) => match x.fixed_size() {
+                Some(size) => reader.skip(size as u64),
+                None => {
+                    let len = reader.read_bytes_len()?;
+                    reader.skip(len as u64)
+                }
+            },
+            XbfMetadata::Vec(x) => reader.nested(|reader| {
+                let len = reader.read_elements_len()?;
+                if let Some(size) = x.inner_type.fixed_size() {
+                    return reader.skip((size as u64).saturating_mul(len as u64));
+                }
+                (0..len).try_for_each(|i| {
+                    x.inner_type
+                        .skip_value_with_context(reader)
+                        .map_err(|e| e.within(format_args!("[{i}]")))
+                })
+            }),
+            XbfMetadata::Struct(x) => reader.nested(|reader| {
+                x.fields.iter().try_for_each(|(name, field_type)| {
+                    field_type
+                        .skip_value_with_context(reader)
+                        .map_err(|e| e.within(format_args!(".{name}")))
+                })
+            }),
+            XbfMetadata::Optional(x) => reader.nested(|reader| match reader.read_u8()? {
+                0 => Ok(()),
+                1 => x.inner_type.skip_value_with_context(reader),
+                x => Err(XbfError::InvalidPresenceByte(x)),
+            }),
+            XbfMetadata::Enum(x) => reader.nested(|reader| {
+                let variant = reader.read_u16::<LittleEndian>()?;
+                let (variant_name, variant_type) = x
+                    .variants
+                    .get(variant as usize)
+                    .ok_or(XbfError::UnknownVariant(variant))?;
+                variant_type
+                    .skip_value_with_context(reader)
+                    .map_err(|e| e.within(format_args!("::{variant_name}")))
+            }),
+            XbfMetadata::Map(x) => reader.nested(|reader| {
+                let len = reader.read_elements_len()?;
+                let key_type = XbfMetadata::Primitive(x.key_type);
+                (0..len).try_for_each(|i| {
+                    key_type
+                        .skip_value_with_context(reader)
+                        .and_then(|_| x.value_type.skip_value_with_context(reader))
+                        .map_err(|e| e.within(format_args!("[{i}]")))
+                })
+            }),
+            XbfMetadata::Array(x) => reader.nested(|reader| {
+                if let Some(size) = x.inner_type.fixed_size() {
+                    return reader.skip((size as u64).saturating_mul(x.len() as u64));
+                }
+                (0..x.len()).try_for_each(|i| {
+                    x.inner_type
+                        .skip_value_with_context(reader)
+                        .map_err(|e| e.within(format_args!("[{i}]")))
+                })
+            }),
         }
     }
 }
@@ -76,16 +427,171 @@ impl From<&XbfStructMetadata> for XbfMetadata {
     }
 }
 
+impl From<XbfOptionalMetadata> for XbfMetadata {
+    fn from(value: XbfOptionalMetadata) -> Self {
+        XbfMetadata::Optional(value)
+    }
+}
+
+impl From<&XbfOptionalMetadata> for XbfMetadata {
+    fn from(value: &XbfOptionalMetadata) -> Self {
+        XbfMetadata::Optional(value.clone())
+    }
+}
+
+impl From<XbfEnumMetadata> for XbfMetadata {
+    fn from(value: XbfEnumMetadata) -> Self {
+        XbfMetadata::Enum(value)
+    }
+}
+
+impl From<&XbfEnumMetadata> for XbfMetadata {
+    fn from(value: &XbfEnumMetadata) -> Self {
+        XbfMetadata::Enum(value.clone())
+    }
+}
+
+impl From<XbfMapMetadata> for XbfMetadata {
+    fn from(value: XbfMapMetadata) -> Self {
+        XbfMetadata::Map(value)
+    }
+}
+
+impl From<&XbfMapMetadata> for XbfMetadata {
+    fn from(value: &XbfMapMetadata) -> Self {
+        XbfMetadata::Map(value.clone())
+    }
+}
+
+impl From<XbfArrayMetadata> for XbfMetadata {
+    fn from(value: XbfArrayMetadata) -> Self {
+        XbfMetadata::Array(value)
+    }
+}
+
+impl From<&XbfArrayMetadata> for XbfMetadata {
+    fn from(value: &XbfArrayMetadata) -> Self {
+        XbfMetadata::Array(value.clone())
+    }
+}
+
 impl From<&XbfType> for XbfMetadata {
     fn from(value: &XbfType) -> Self {
         match value {
             XbfType::Primitive(v) => XbfPrimitiveMetadata::from(v).to_base_metadata(),
             XbfType::Vec(v) => XbfVecMetadata::from(v).to_base_metadata(),
             XbfType::Struct(v) => XbfStructMetadata::from(v).to_base_metadata(),
+            XbfType::Optional(v) => XbfOptionalMetadata::from(v).to_base_metadata(),
+            XbfType::Enum(v) => XbfEnumMetadata::from(v).to_base_metadata(),
+            XbfType::Map(v) => XbfMapMetadata::from(v).to_base_metadata(),
+            XbfType::Array(v) => XbfArrayMetadata::from(v).to_base_metadata(),
+        }
+    }
+}
+
+/// Checks that the fields of a struct or the variants of an enum have distinct, non-empty names.
+pub(crate) fn check_names(
+    entries: &[(String, XbfMetadata)],
+) -> std::result::Result<(), InvalidMetadataError> {
+    let mut seen = HashSet::with_capacity(entries.len());
+    for (index, (name, _)) in entries.iter().enumerate() {
+        if name.is_empty() {
+            return Err(InvalidMetadataError::EmptyName { index });
+        }
+        if !seen.insert(name.as_str()) {
+            return Err(InvalidMetadataError::DuplicateName {
+                index,
+                name: name.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the name of a struct or enum, and the names of its fields or variants, can be
+/// written with the default [`XbfConfig`].
+pub(crate) fn check_name_lens(
+    name: &str,
+    entries: &[(String, XbfMetadata)],
+) -> std::result::Result<(), InvalidMetadataError> {
+    let config = XbfConfig::default();
+    check_name_len(name, &config)?;
+    entries
+        .iter()
+        .try_for_each(|(name, _)| check_name_len(name, &config))
+}
+
+/// Checks that every variant of an enum can be picked by its `u16` index.
+pub(crate) fn check_variant_count(
+    variants: &[(String, XbfMetadata)],
+) -> std::result::Result<(), InvalidMetadataError> {
+    if variants.len() > u16::MAX as usize + 1 {
+        return Err(InvalidMetadataError::TooManyVariants(variants.len()));
+    }
+    Ok(())
+}
+
+fn check_name_len(name: &str, config: &XbfConfig) -> std::result::Result<(), InvalidMetadataError> {
+    let encoding = config.length_encoding();
+    let fits = match encoding {
+        LengthEncoding::U16 => u16::try_from(name.len()).is_ok(),
+        LengthEncoding::U32 => u32::try_from(name.len()).is_ok(),
+        LengthEncoding::Varint => true,
+    };
+    if !fits {
+        return Err(InvalidMetadataError::NameTooLong {
+            len: name.len(),
+            encoding,
+        });
+    }
+    Ok(())
+}
+
+/// The error returned when metadata breaks one of the rules checked by
+/// [`XbfMetadata::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidMetadataError {
+    /// The field or variant at the given index has an empty name.
+    EmptyName { index: usize },
+    /// The field or variant at the given index has the same name as an earlier one.
+    DuplicateName { index: usize, name: String },
+    /// A name was too long for the configured [`LengthEncoding`].
+    NameTooLong {
+        len: usize,
+        encoding: LengthEncoding,
+    },
+    /// An enum had more variants than a `u16` index can pick from.
+    TooManyVariants(usize),
+}
+
+impl Display for InvalidMetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidMetadataError::EmptyName { index } => {
+                write!(f, "name at index {index} is empty")
+            }
+            InvalidMetadataError::DuplicateName { index, name } => {
+                write!(f, "name {name:?} at index {index} is already used")
+            }
+            InvalidMetadataError::NameTooLong { len, encoding } => {
+                write!(
+                    f,
+                    "name of {len} bytes does not fit in a {encoding:?} length"
+                )
+            }
+            InvalidMetadataError::TooManyVariants(x) => {
+                write!(
+                    f,
+                    "enum has {x} variants, but a u16 index can only pick 65536"
+                )
+            }
         }
     }
 }
 
+impl Error for InvalidMetadataError {}
+
 pub trait XbfMetadataUpcast: Into<XbfMetadata>
 where
     XbfMetadata: for<'a> From<&'a Self>,
@@ -97,3 +603,312 @@ where
         self.into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        DecodeLimits, LimitExceededError, XbfArray, XbfEnumValue, XbfMap, XbfOptional,
+        XbfPrimitive, XbfStruct, XbfTypeUpcast, XbfVec,
+    };
+    use std::io::Cursor;
+
+    fn test_value() -> XbfType {
+        let position = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 3);
+        let pet = XbfEnumMetadata::new(
+            "pet".to_string(),
+            vec![
+                ("dog".to_string(), XbfPrimitiveMetadata::String.into()),
+                ("cat".to_string(), XbfPrimitiveMetadata::U8.into()),
+            ],
+        );
+        let scores = XbfMapMetadata::new(
+            XbfPrimitiveMetadata::String,
+            XbfPrimitiveMetadata::I64.into(),
+        );
+        let metadata = XbfStructMetadata::new(
+            "player".to_string(),
+            vec![
+                ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+                ("position".to_string(), position.to_base_metadata()),
+                (
+                    "pets".to_string(),
+                    XbfVecMetadata::new(pet.to_base_metadata()).into(),
+                ),
+                ("scores".to_string(), scores.to_base_metadata()),
+                (
+                    "title".to_string(),
+                    XbfOptionalMetadata::new(XbfPrimitiveMetadata::String.into()).into(),
+                ),
+            ],
+        );
+        let pets = vec![
+            XbfEnumValue::new(
+                pet.clone(),
+                "dog",
+                XbfPrimitive::String("rex".to_string()).into(),
+            )
+            .unwrap()
+            .into(),
+            XbfEnumValue::new(pet.clone(), "cat", XbfPrimitive::U8(9).into())
+                .unwrap()
+                .into(),
+        ];
+        XbfStruct::new(
+            metadata,
+            vec![
+                XbfPrimitive::String("alice".to_string()).into(),
+                XbfArray::new(position, vec![XbfPrimitive::F32(1.0).into(); 3])
+                    .unwrap()
+                    .into(),
+                XbfVec::new(XbfVecMetadata::new(pet.into()), pets)
+                    .unwrap()
+                    .into(),
+                XbfMap::new(
+                    scores,
+                    vec![(
+                        XbfPrimitive::String("level 1".to_string()),
+                        XbfPrimitive::I64(100).into(),
+                    )],
+                )
+                .unwrap()
+                .into(),
+                XbfOptional::new(
+                    XbfOptionalMetadata::new(XbfPrimitiveMetadata::String.into()),
+                    Some(XbfPrimitive::String("champion".to_string()).into()),
+                )
+                .unwrap()
+                .into(),
+            ],
+        )
+        .unwrap()
+        .into_base_type()
+    }
+
+    #[test]
+    fn encoded_len_matches_serialize() {
+        let value = test_value();
+        let metadata = XbfMetadata::from(&value);
+        for encoding in [
+            crate::LengthEncoding::U16,
+            crate::LengthEncoding::U32,
+            crate::LengthEncoding::Varint,
+        ] {
+            let config = XbfConfig::new().with_length_encoding(encoding);
+
+            let mut writer = vec![];
+            value
+                .serialize_base_type_with_config(&mut writer, &config)
+                .unwrap();
+            assert_eq!(value.encoded_len_with_config(&config), writer.len());
+
+            let mut writer = vec![];
+            metadata
+                .serialize_base_metadata_with_config(&mut writer, &config)
+                .unwrap();
+            assert_eq!(metadata.encoded_len_with_config(&config), writer.len());
+        }
+    }
+
+    #[test]
+    fn fixed_size_works() {
+        let u8_metadata = XbfMetadata::from(XbfPrimitiveMetadata::U8);
+        let string_metadata = XbfMetadata::from(XbfPrimitiveMetadata::String);
+        let same_sizes = XbfEnumMetadata::new(
+            "same".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::U32.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::F32.into()),
+            ],
+        );
+        let different_sizes = XbfEnumMetadata::new(
+            "different".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::U32.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::U8.into()),
+            ],
+        );
+        let empty_struct = XbfStructMetadata::new("empty".to_string(), vec![]);
+
+        assert_eq!(u8_metadata.fixed_size(), Some(1));
+        assert_eq!(string_metadata.fixed_size(), None);
+        assert_eq!(
+            XbfMetadata::from(XbfVecMetadata::new(u8_metadata.clone())).fixed_size(),
+            None
+        );
+        assert_eq!(
+            XbfMetadata::from(XbfOptionalMetadata::new(u8_metadata.clone())).fixed_size(),
+            None
+        );
+        assert_eq!(
+            XbfMetadata::from(XbfOptionalMetadata::new(empty_struct.clone().into())).fixed_size(),
+            Some(1)
+        );
+        assert_eq!(XbfMetadata::from(empty_struct).fixed_size(), Some(0));
+        assert_eq!(XbfMetadata::from(same_sizes).fixed_size(), Some(6));
+        assert_eq!(XbfMetadata::from(different_sizes).fixed_size(), None);
+        assert_eq!(
+            XbfMetadata::from(XbfArrayMetadata::new(u8_metadata, 16)).fixed_size(),
+            Some(16)
+        );
+        assert_eq!(
+            XbfMetadata::from(XbfArrayMetadata::new(string_metadata, 16)).fixed_size(),
+            None
+        );
+    }
+
+    #[test]
+    fn skip_value_consumes_exactly_one_value() {
+        let value = test_value();
+        let metadata = XbfMetadata::from(&value);
+        let mut data = vec![];
+        value.serialize_base_type(&mut data).unwrap();
+        let len = data.len() as u64;
+        data.push(42);
+
+        let mut reader = Cursor::new(data.clone());
+        metadata.skip_value(&mut reader).unwrap();
+        assert_eq!(reader.position(), len);
+
+        let mut reader = Cursor::new(data);
+        metadata.skip_value_seek(&mut reader).unwrap();
+        assert_eq!(reader.position(), len);
+    }
+
+    #[test]
+    fn skip_value_works_with_config() {
+        let value = test_value();
+        let metadata = XbfMetadata::from(&value);
+        let config = XbfConfig::new().with_length_encoding(crate::LengthEncoding::Varint);
+        let mut data = vec![];
+        value
+            .serialize_base_type_with_config(&mut data, &config)
+            .unwrap();
+
+        let mut reader = Cursor::new(&data);
+        metadata
+            .skip_value_seek_with_config(&mut reader, &config)
+            .unwrap();
+        assert_eq!(reader.position(), data.len() as u64);
+    }
+
+    #[test]
+    fn skipping_truncated_value_fails() {
+        let value = test_value();
+        let metadata = XbfMetadata::from(&value);
+        let mut data = vec![];
+        value.serialize_base_type(&mut data).unwrap();
+        data.pop();
+
+        let err = metadata.skip_value(&mut Cursor::new(&data)).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(err.path(), Some("root.title"));
+
+        let err = metadata
+            .skip_value_seek(&mut Cursor::new(&data))
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(err.path(), Some("root.title"));
+    }
+
+    #[test]
+    fn skipping_lying_length_fails_when_seeking() {
+        let metadata = XbfMetadata::from(XbfPrimitiveMetadata::Bytes);
+        let mut data = vec![];
+        data.extend_from_slice(&u16::MAX.to_le_bytes());
+        data.extend_from_slice(&[0; 10]);
+
+        let err = metadata
+            .skip_value_seek(&mut Cursor::new(data))
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+    }
+
+    #[test]
+    fn skip_value_checks_enum_variants() {
+        let metadata = XbfMetadata::from(XbfEnumMetadata::new(
+            "unit".to_string(),
+            vec![("only".to_string(), XbfPrimitiveMetadata::U8.into())],
+        ));
+
+        let err = metadata
+            .skip_value(&mut Cursor::new(vec![1, 0, 0]))
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnknownVariant(1)));
+    }
+
+    #[test]
+    fn skip_value_checks_string_length() {
+        let metadata = XbfMetadata::from(XbfPrimitiveMetadata::String);
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_string_len(2));
+        let data = [3, 0, b'a', b'b', b'c'];
+
+        let err = metadata
+            .skip_value_with_config(&mut Cursor::new(data), &config)
+            .unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::StringLength {
+                limit: 2,
+                actual: 3
+            })
+        ));
+        let err =
+            XbfType::deserialize_base_type_with_config(&metadata, &mut Cursor::new(data), &config)
+                .unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::StringLength { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_works() {
+        let metadata = XbfMetadata::from(&test_value());
+        metadata.validate().unwrap();
+
+        let pet = XbfEnumMetadata::new(
+            "pet".to_string(),
+            vec![
+                ("dog".to_string(), XbfPrimitiveMetadata::String.into()),
+                ("dog".to_string(), XbfPrimitiveMetadata::U8.into()),
+            ],
+        );
+        let metadata = XbfMetadata::from(XbfMapMetadata::new(
+            XbfPrimitiveMetadata::String,
+            XbfOptionalMetadata::new(pet.into()).into(),
+        ));
+        let err = metadata.validate().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidMetadata(InvalidMetadataError::DuplicateName { index: 1, .. })
+        ));
+        assert_eq!(err.path(), Some("root[]"));
+    }
+
+    #[test]
+    fn validate_checks_name_lengths() {
+        let long_name = "a".repeat(u16::MAX as usize + 1);
+        let inner = XbfStructMetadata::new(
+            "inner".to_string(),
+            vec![(long_name, XbfPrimitiveMetadata::U8.into())],
+        );
+        let metadata = XbfMetadata::from(XbfStructMetadata::new(
+            "outer".to_string(),
+            vec![("inner".to_string(), inner.into())],
+        ));
+
+        let err = metadata.validate().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidMetadata(InvalidMetadataError::NameTooLong {
+                len: 65536,
+                encoding: LengthEncoding::U16
+            })
+        ));
+        assert_eq!(err.path(), Some("root.inner.<field 0>"));
+
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::U32);
+        metadata.validate_with_config(&config).unwrap();
+    }
+}
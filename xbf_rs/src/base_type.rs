@@ -1,36 +1,187 @@
-use crate::{xbf_primitive::XbfPrimitive, xbf_struct::XbfStruct, xbf_vec::XbfVec, XbfMetadata};
-use std::io::{self, Read, Write};
+use crate::{
+    bf16, f16, util::ReadContext, xbf_array::XbfArray, xbf_enum::XbfEnumValue, xbf_map::XbfMap,
+    xbf_optional::XbfOptional, xbf_primitive::XbfPrimitive, xbf_struct::XbfStruct, xbf_vec::XbfVec,
+    Result, XbfConfig, XbfMetadata, I256, U256,
+};
+use std::io::{Read, Write};
+
+macro_rules! primitive_accessor {
+    ($as:ident, $ty:ty) => {
+        #[doc = concat!("Calls [`XbfPrimitive::", stringify!($as), "`] if this is a primitive.")]
+        pub fn $as(&self) -> Option<$ty> {
+            self.as_primitive()?.$as()
+        }
+    };
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum XbfType {
     Primitive(XbfPrimitive),
     Vec(XbfVec),
     Struct(XbfStruct),
+    Optional(XbfOptional),
+    Enum(XbfEnumValue),
+    Map(XbfMap),
+    Array(XbfArray),
 }
 
 impl XbfType {
-    pub fn serialize_base_type(&self, writer: &mut impl Write) -> io::Result<()> {
+    pub fn serialize_base_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_base_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize any XBF type using the given [`XbfConfig`].
+    pub fn serialize_base_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
         match self {
-            XbfType::Primitive(x) => x.serialize_primitive_type(writer),
-            XbfType::Vec(x) => x.serialize_vec_type(writer),
-            XbfType::Struct(x) => x.serialize_struct_type(writer),
+            XbfType::Primitive(x) => x.serialize_primitive_type_with_config(writer, config),
+            XbfType::Vec(x) => x.serialize_vec_type_with_config(writer, config),
+            XbfType::Struct(x) => x.serialize_struct_type_with_config(writer, config),
+            XbfType::Optional(x) => x.serialize_optional_type_with_config(writer, config),
+            XbfType::Enum(x) => x.serialize_enum_type_with_config(writer, config),
+            XbfType::Map(x) => x.serialize_map_type_with_config(writer, config),
+            XbfType::Array(x) => x.serialize_array_type_with_config(writer, config),
         }
     }
 
     pub fn deserialize_base_type(
         metadata: &XbfMetadata,
         reader: &mut impl Read,
-    ) -> io::Result<XbfType> {
+    ) -> Result<XbfType> {
+        Self::deserialize_base_type_with_config(metadata, reader, &XbfConfig::default())
+    }
+
+    /// Deserialize any XBF type described by the given metadata using the given [`XbfConfig`].
+    pub fn deserialize_base_type_with_config(
+        metadata: &XbfMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfType> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_base_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_base_type_with_context(
+        metadata: &XbfMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfType> {
         match metadata {
             XbfMetadata::Primitive(x) => {
-                XbfPrimitive::deserialize_primitive_type(x, reader).map(|x| x.into())
+                XbfPrimitive::deserialize_primitive_type_with_context(x, reader).map(|x| x.into())
+            }
+            XbfMetadata::Vec(x) => {
+                XbfVec::deserialize_vec_type_with_context(x, reader).map(|x| x.into())
             }
-            XbfMetadata::Vec(x) => XbfVec::deserialize_vec_type(x, reader).map(|x| x.into()),
             XbfMetadata::Struct(x) => {
-                XbfStruct::deserialize_struct_type(x, reader).map(|x| x.into())
+                XbfStruct::deserialize_struct_type_with_context(x, reader).map(|x| x.into())
             }
+            XbfMetadata::Optional(x) => {
+                XbfOptional::deserialize_optional_type_with_context(x, reader).map(|x| x.into())
+            }
+            XbfMetadata::Enum(x) => {
+                XbfEnumValue::deserialize_enum_type_with_context(x, reader).map(|x| x.into())
+            }
+            XbfMetadata::Map(x) => {
+                XbfMap::deserialize_map_type_with_context(x, reader).map(|x| x.into())
+            }
+            XbfMetadata::Array(x) => {
+                XbfArray::deserialize_array_type_with_context(x, reader).map(|x| x.into())
+            }
+        }
+    }
+
+    /// Returns the number of bytes [`Self::serialize_base_type`] would write, without serializing.
+    ///
+    /// Like serializing, this does not include the metadata of the type. If a length is too large
+    /// for the length encoding the count is still returned, even though serializing would fail.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfType;
+    /// use xbf_rs::XbfVec;
+    /// use xbf_rs::XbfVecMetadata;
+    ///
+    /// let vec: XbfType = XbfVec::new(
+    ///     XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into()),
+    ///     vec![XbfPrimitive::I32(42).into(), XbfPrimitive::I32(69).into()],
+    /// )
+    /// .unwrap()
+    /// .into();
+    /// let mut writer = vec![];
+    /// vec.serialize_base_type(&mut writer).unwrap();
+    ///
+    /// assert_eq!(vec.encoded_len(), writer.len());
+    /// ```
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_base_type_with_config`] would write using
+    /// the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        match self {
+            XbfType::Primitive(x) => x.encoded_len_with_config(config),
+            XbfType::Vec(x) => x.encoded_len_with_config(config),
+            XbfType::Struct(x) => x.encoded_len_with_config(config),
+            XbfType::Optional(x) => x.encoded_len_with_config(config),
+            XbfType::Enum(x) => x.encoded_len_with_config(config),
+            XbfType::Map(x) => x.encoded_len_with_config(config),
+            XbfType::Array(x) => x.encoded_len_with_config(config),
+        }
+    }
+
+    /// Returns the primitive if this is a [`XbfType::Primitive`].
+    ///
+    /// Each of the accessors of [`XbfPrimitive`], such as [`XbfPrimitive::as_str`], is also
+    /// available directly on [`XbfType`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfType;
+    ///
+    /// let value = XbfType::from(XbfPrimitive::String("hello".to_string()));
+    ///
+    /// assert_eq!(value.as_primitive(), Some(&XbfPrimitive::String("hello".to_string())));
+    /// assert_eq!(value.as_str(), Some("hello"));
+    /// assert_eq!(value.as_u64(), None);
+    /// assert_eq!(String::try_from(value).unwrap(), "hello");
+    /// ```
+    pub fn as_primitive(&self) -> Option<&XbfPrimitive> {
+        match self {
+            XbfType::Primitive(x) => Some(x),
+            _ => None,
         }
     }
+
+    primitive_accessor!(as_bool, bool);
+    primitive_accessor!(as_u8, u8);
+    primitive_accessor!(as_u16, u16);
+    primitive_accessor!(as_u32, u32);
+    primitive_accessor!(as_u64, u64);
+    primitive_accessor!(as_u128, u128);
+    primitive_accessor!(as_u256, U256);
+    primitive_accessor!(as_i8, i8);
+    primitive_accessor!(as_i16, i16);
+    primitive_accessor!(as_i32, i32);
+    primitive_accessor!(as_i64, i64);
+    primitive_accessor!(as_i128, i128);
+    primitive_accessor!(as_i256, I256);
+    primitive_accessor!(as_f32, f32);
+    primitive_accessor!(as_f64, f64);
+    primitive_accessor!(as_char, char);
+    primitive_accessor!(as_f16, f16);
+    primitive_accessor!(as_bf16, bf16);
+    primitive_accessor!(as_bytes, &[u8]);
+    primitive_accessor!(as_str, &str);
 }
 
 impl From<XbfPrimitive> for XbfType {
@@ -69,6 +220,54 @@ impl From<&XbfStruct> for XbfType {
     }
 }
 
+impl From<XbfOptional> for XbfType {
+    fn from(value: XbfOptional) -> Self {
+        XbfType::Optional(value)
+    }
+}
+
+impl From<&XbfOptional> for XbfType {
+    fn from(value: &XbfOptional) -> Self {
+        XbfType::Optional(value.clone())
+    }
+}
+
+impl From<XbfEnumValue> for XbfType {
+    fn from(value: XbfEnumValue) -> Self {
+        XbfType::Enum(value)
+    }
+}
+
+impl From<&XbfEnumValue> for XbfType {
+    fn from(value: &XbfEnumValue) -> Self {
+        XbfType::Enum(value.clone())
+    }
+}
+
+impl From<XbfMap> for XbfType {
+    fn from(value: XbfMap) -> Self {
+        XbfType::Map(value)
+    }
+}
+
+impl From<&XbfMap> for XbfType {
+    fn from(value: &XbfMap) -> Self {
+        XbfType::Map(value.clone())
+    }
+}
+
+impl From<XbfArray> for XbfType {
+    fn from(value: XbfArray) -> Self {
+        XbfType::Array(value)
+    }
+}
+
+impl From<&XbfArray> for XbfType {
+    fn from(value: &XbfArray) -> Self {
+        XbfType::Array(value.clone())
+    }
+}
+
 pub trait XbfTypeUpcast: Into<XbfType>
 where
     XbfType: for<'a> From<&'a Self>,
@@ -0,0 +1,376 @@
+use crate::{
+    util::{preallocate, ReadContext},
+    xbf_map::has_duplicate_keys,
+    Result, XbfArray, XbfArrayMetadata, XbfConfig, XbfEnumMetadata, XbfEnumValue, XbfError, XbfMap,
+    XbfMapMetadata, XbfMetadata, XbfOptional, XbfOptionalMetadata, XbfPrimitiveRef, XbfStruct,
+    XbfStructMetadata, XbfType, XbfVec, XbfVecMetadata,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+
+/// Any XBF type, borrowing from the input it was read from rather than copying.
+///
+/// This is the borrowed counterpart of [`XbfType`]. Decoding into it never copies a string or
+/// bytes, which instead point into the input slice, and holds a reference to the metadata it was
+/// read with rather than cloning it. Use [`Self::to_owned`] to turn it into an [`XbfType`] that
+/// outlives the input.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::XbfMetadata;
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfPrimitiveRef;
+/// use xbf_rs::XbfStruct;
+/// use xbf_rs::XbfStructMetadata;
+/// use xbf_rs::XbfType;
+/// use xbf_rs::XbfTypeRef;
+///
+/// let metadata: XbfMetadata = XbfStructMetadata::new(
+///     "player".to_string(),
+///     vec![
+///         ("id".to_string(), XbfPrimitiveMetadata::U8.into()),
+///         ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+///     ],
+/// )
+/// .into();
+/// let data = [7, 5, 0, b'a', b'l', b'i', b'c', b'e'];
+/// let mut input = &data[..];
+///
+/// let player = XbfTypeRef::deserialize_base_type(&metadata, &mut input).unwrap();
+///
+/// let XbfTypeRef::Struct { fields, .. } = &player else {
+///     panic!("expected a struct");
+/// };
+/// assert_eq!(fields[1], XbfTypeRef::Primitive(XbfPrimitiveRef::String("alice")));
+/// assert!(input.is_empty());
+///
+/// let XbfMetadata::Struct(struct_metadata) = &metadata else {
+///     unreachable!()
+/// };
+/// assert_eq!(
+///     player.to_owned(),
+///     XbfType::from(XbfStruct::new_unchecked(
+///         struct_metadata.clone(),
+///         vec![XbfPrimitive::U8(7).into(), XbfPrimitive::String("alice".to_string()).into()],
+///     ))
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum XbfTypeRef<'a> {
+    Primitive(XbfPrimitiveRef<'a>),
+    Vec {
+        metadata: &'a XbfVecMetadata,
+        elements: Vec<XbfTypeRef<'a>>,
+    },
+    Struct {
+        metadata: &'a XbfStructMetadata,
+        fields: Vec<XbfTypeRef<'a>>,
+    },
+    Optional {
+        metadata: &'a XbfOptionalMetadata,
+        value: Option<Box<XbfTypeRef<'a>>>,
+    },
+    Enum {
+        metadata: &'a XbfEnumMetadata,
+        variant: u16,
+        value: Box<XbfTypeRef<'a>>,
+    },
+    Map {
+        metadata: &'a XbfMapMetadata,
+        entries: Vec<(XbfPrimitiveRef<'a>, XbfTypeRef<'a>)>,
+    },
+    Array {
+        metadata: &'a XbfArrayMetadata,
+        elements: Vec<XbfTypeRef<'a>>,
+    },
+}
+
+impl<'a> XbfTypeRef<'a> {
+    /// Deserialize any XBF type described by the given metadata from a slice, borrowing from it.
+    ///
+    /// The slice is advanced past the bytes that were read, so several values can be read from
+    /// one slice in turn. This accepts exactly the same input as
+    /// [`XbfType::deserialize_base_type`].
+    pub fn deserialize_base_type(metadata: &'a XbfMetadata, input: &mut &'a [u8]) -> Result<Self> {
+        Self::deserialize_base_type_with_config(metadata, input, &XbfConfig::default())
+    }
+
+    /// Deserialize any XBF type from a slice using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_base_type`] for more details.
+    pub fn deserialize_base_type_with_config(
+        metadata: &'a XbfMetadata,
+        input: &mut &'a [u8],
+        config: &XbfConfig,
+    ) -> Result<Self> {
+        ReadContext::decode_slice(input, config, |reader| {
+            Self::deserialize_base_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_base_type_with_context(
+        metadata: &'a XbfMetadata,
+        reader: &mut ReadContext<&'a [u8]>,
+    ) -> Result<Self> {
+        match metadata {
+            XbfMetadata::Primitive(x) => {
+                XbfPrimitiveRef::deserialize_primitive_type_with_context(x, reader)
+                    .map(XbfTypeRef::Primitive)
+            }
+            XbfMetadata::Vec(metadata) => reader.nested(|reader| {
+                let len = reader.read_elements_len()?;
+                let elements = Self::deserialize_elements(&metadata.inner_type, len, reader)?;
+                Ok(XbfTypeRef::Vec { metadata, elements })
+            }),
+            XbfMetadata::Struct(metadata) => reader.nested(|reader| {
+                let fields = metadata
+                    .fields
+                    .iter()
+                    .map(|(name, field_type)| {
+                        Self::deserialize_base_type_with_context(field_type, reader)
+                            .map_err(|e| e.within(format_args!(".{name}")))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(XbfTypeRef::Struct { metadata, fields })
+            }),
+            XbfMetadata::Optional(metadata) => reader.nested(|reader| {
+                let value = match reader.read_u8()? {
+                    0 => None,
+                    1 => Some(Box::new(Self::deserialize_base_type_with_context(
+                        &metadata.inner_type,
+                        reader,
+                    )?)),
+                    x => return Err(XbfError::InvalidPresenceByte(x)),
+                };
+                Ok(XbfTypeRef::Optional { metadata, value })
+            }),
+            XbfMetadata::Enum(metadata) => reader.nested(|reader| {
+                let variant = reader.read_u16::<LittleEndian>()?;
+                let (variant_name, variant_type) = metadata
+                    .variants
+                    .get(variant as usize)
+                    .ok_or(XbfError::UnknownVariant(variant))?;
+                let value = Self::deserialize_base_type_with_context(variant_type, reader)
+                    .map_err(|e| e.within(format_args!("::{variant_name}")))?;
+                Ok(XbfTypeRef::Enum {
+                    metadata,
+                    variant,
+                    value: Box::new(value),
+                })
+            }),
+            XbfMetadata::Map(metadata) => reader.nested(|reader| {
+                let len = reader.read_elements_len()?;
+                let mut entries = preallocate(len);
+                for i in 0..len {
+                    let entry = XbfPrimitiveRef::deserialize_primitive_type_with_context(
+                        &metadata.key_type,
+                        reader,
+                    )
+                    .and_then(|key| {
+                        Self::deserialize_base_type_with_context(&metadata.value_type, reader)
+                            .map(|value| (key, value))
+                    })
+                    .map_err(|e| e.within(format_args!("[{i}]")))?;
+                    entries.push(entry);
+                }
+                if has_duplicate_keys(entries.iter().map(|(k, _)| *k)) {
+                    return Err(XbfError::DuplicateKey);
+                }
+                Ok(XbfTypeRef::Map { metadata, entries })
+            }),
+            XbfMetadata::Array(metadata) => reader.nested(|reader| {
+                let elements =
+                    Self::deserialize_elements(&metadata.inner_type, metadata.len, reader)?;
+                Ok(XbfTypeRef::Array { metadata, elements })
+            }),
+        }
+    }
+
+    fn deserialize_elements(
+        metadata: &'a XbfMetadata,
+        len: usize,
+        reader: &mut ReadContext<&'a [u8]>,
+    ) -> Result<Vec<Self>> {
+        let mut elements = preallocate(len);
+        for i in 0..len {
+            elements.push(
+                Self::deserialize_base_type_with_context(metadata, reader)
+                    .map_err(|e| e.within(format_args!("[{i}]")))?,
+            );
+        }
+        Ok(elements)
+    }
+
+    /// Copies the value into an owned [`XbfType`], cloning the metadata it refers to.
+    pub fn to_owned(&self) -> XbfType {
+        match self {
+            XbfTypeRef::Primitive(x) => x.to_owned().into(),
+            XbfTypeRef::Vec { metadata, elements } => XbfVec::new_unchecked(
+                (*metadata).clone(),
+                elements.iter().map(Self::to_owned).collect(),
+            )
+            .into(),
+            XbfTypeRef::Struct { metadata, fields } => XbfStruct::new_unchecked(
+                (*metadata).clone(),
+                fields.iter().map(Self::to_owned).collect(),
+            )
+            .into(),
+            XbfTypeRef::Optional { metadata, value } => XbfOptional::new_unchecked(
+                (*metadata).clone(),
+                value.as_deref().map(Self::to_owned),
+            )
+            .into(),
+            XbfTypeRef::Enum {
+                metadata,
+                variant,
+                value,
+            } => XbfEnumValue::new_unchecked((*metadata).clone(), *variant, Self::to_owned(value))
+                .into(),
+            XbfTypeRef::Map { metadata, entries } => XbfMap::new_unchecked(
+                (*metadata).clone(),
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                    .collect(),
+            )
+            .into(),
+            XbfTypeRef::Array { metadata, elements } => XbfArray::new_unchecked(
+                (*metadata).clone(),
+                elements.iter().map(Self::to_owned).collect(),
+            )
+            .into(),
+        }
+    }
+}
+
+impl From<XbfTypeRef<'_>> for XbfType {
+    fn from(value: XbfTypeRef<'_>) -> Self {
+        value.to_owned()
+    }
+}
+
+impl From<&XbfTypeRef<'_>> for XbfType {
+    fn from(value: &XbfTypeRef<'_>) -> Self {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        LengthEncoding, XbfPrimitive, XbfPrimitiveMetadata, XbfTypeUpcast, XbfVecMetadata,
+    };
+
+    fn test_value() -> XbfType {
+        let pet = XbfEnumMetadata::new(
+            "pet".to_string(),
+            vec![
+                ("dog".to_string(), XbfPrimitiveMetadata::String.into()),
+                ("fish".to_string(), XbfPrimitiveMetadata::U8.into()),
+            ],
+        );
+        let position = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 2);
+        let scores = XbfMapMetadata::new(
+            XbfPrimitiveMetadata::String,
+            XbfPrimitiveMetadata::U32.into(),
+        );
+        let title = XbfOptionalMetadata::new(XbfPrimitiveMetadata::Bytes.into());
+        let player = XbfStructMetadata::new(
+            "player".to_string(),
+            vec![
+                ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+                ("position".to_string(), position.clone().into()),
+                (
+                    "pets".to_string(),
+                    XbfVecMetadata::new(pet.clone().into()).into(),
+                ),
+                ("scores".to_string(), scores.clone().into()),
+                ("title".to_string(), title.clone().into()),
+            ],
+        );
+        XbfStruct::new_unchecked(
+            player,
+            vec![
+                XbfPrimitive::String("alice".to_string()).into(),
+                XbfArray::new_unchecked(
+                    position,
+                    vec![XbfPrimitive::F32(1.0).into(), XbfPrimitive::F32(2.0).into()],
+                )
+                .into(),
+                XbfVec::new_unchecked(
+                    XbfVecMetadata::new(pet.clone().into()),
+                    vec![
+                        XbfEnumValue::new_unchecked(
+                            pet.clone(),
+                            0,
+                            XbfPrimitive::String("rex".to_string()).into(),
+                        )
+                        .into(),
+                        XbfEnumValue::new_unchecked(pet, 1, XbfPrimitive::U8(3).into()).into(),
+                    ],
+                )
+                .into(),
+                XbfMap::new_unchecked(
+                    scores,
+                    vec![(
+                        XbfPrimitive::String("level1".to_string()),
+                        XbfPrimitive::U32(10).into(),
+                    )],
+                )
+                .into(),
+                XbfOptional::new_unchecked(title, Some(XbfPrimitive::Bytes(vec![1, 2]).into()))
+                    .into(),
+            ],
+        )
+        .to_base_type()
+    }
+
+    #[test]
+    fn borrowed_value_matches_owned() {
+        let value = test_value();
+        let metadata = XbfMetadata::from(&value);
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::U32);
+        let mut writer = vec![];
+        value
+            .serialize_base_type_with_config(&mut writer, &config)
+            .unwrap();
+        writer.push(0xaa);
+
+        let mut input = &writer[..];
+        let borrowed =
+            XbfTypeRef::deserialize_base_type_with_config(&metadata, &mut input, &config).unwrap();
+        assert_eq!(input, [0xaa]);
+        assert_eq!(borrowed.to_owned(), value);
+        assert_eq!(XbfType::from(borrowed), value);
+    }
+
+    #[test]
+    fn borrowed_errors_match_owned() {
+        let value = test_value();
+        let metadata = XbfMetadata::from(&value);
+        let mut writer = vec![];
+        value.serialize_base_type(&mut writer).unwrap();
+        // cut off in the middle of the first pet's name
+        writer.truncate(2 + 5 + 8 + 2 + 2 + 2 + 1);
+
+        let owned_err =
+            XbfType::deserialize_base_type(&metadata, &mut std::io::Cursor::new(&writer))
+                .unwrap_err();
+        let borrowed_err =
+            XbfTypeRef::deserialize_base_type(&metadata, &mut &writer[..]).unwrap_err();
+        assert!(matches!(borrowed_err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(borrowed_err.path(), Some("root.pets[0]::dog"));
+        assert_eq!(borrowed_err.path(), owned_err.path());
+    }
+
+    #[test]
+    fn duplicate_keys_are_rejected() {
+        let metadata: XbfMetadata =
+            XbfMapMetadata::new(XbfPrimitiveMetadata::U8, XbfPrimitiveMetadata::U8.into()).into();
+        let data = [2, 0, 1, 10, 1, 11];
+        let err = XbfTypeRef::deserialize_base_type(&metadata, &mut &data[..]).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::DuplicateKey));
+    }
+}
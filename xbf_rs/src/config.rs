@@ -0,0 +1,271 @@
+//! Configuration shared by the serialize and deserialize functions.
+//!
+//! Every `serialize_*` and `deserialize_*` function has a `_with_config` counterpart that takes an
+//! [`XbfConfig`]. The plain functions use [`XbfConfig::default`], which matches the XBF
+//! specification. Both ends of a connection must use the same configuration.
+
+use std::{error::Error, fmt::Display};
+
+/// How lengths are written on the wire.
+///
+/// This applies to every length prefix, those of strings, bytes, vectors and maps as well as the
+/// lengths and counts contained in metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthEncoding {
+    /// An unsigned 16-bit little endian integer, as defined by the XBF specification.
+    #[default]
+    U16,
+    /// An unsigned 32-bit little endian integer.
+    U32,
+    /// An unsigned LEB128 variable length integer of at most 10 bytes.
+    Varint,
+}
+
+/// Options controlling how XBF data is serialized and deserialized.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::LengthEncoding;
+/// use xbf_rs::XbfConfig;
+/// use xbf_rs::XbfPrimitive;
+///
+/// let config = XbfConfig::new().with_length_encoding(LengthEncoding::U32);
+/// let primitive = XbfPrimitive::Bytes(vec![0; 70_000]);
+/// let mut writer = vec![];
+///
+/// // too long for the default 16-bit lengths
+/// assert!(primitive.serialize_primitive_type(&mut writer).is_err());
+///
+/// writer.clear();
+/// primitive
+///     .serialize_primitive_type_with_config(&mut writer, &config)
+///     .unwrap();
+/// assert_eq!(writer[..4], 70_000u32.to_le_bytes());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct XbfConfig {
+    length_encoding: LengthEncoding,
+    limits: DecodeLimits,
+}
+
+impl XbfConfig {
+    /// Creates a new configuration matching the XBF specification.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how lengths are written on the wire.
+    pub fn with_length_encoding(mut self, length_encoding: LengthEncoding) -> Self {
+        self.length_encoding = length_encoding;
+        self
+    }
+
+    /// Returns how lengths are written on the wire.
+    pub fn length_encoding(&self) -> LengthEncoding {
+        self.length_encoding
+    }
+
+    /// Sets the limits enforced while deserializing.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the limits enforced while deserializing.
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+}
+
+/// Bounds on the resources a single deserialize call may use.
+///
+/// Lengths and counts are read from the wire before the data they describe, so without limits a
+/// peer can make the decoder allocate huge buffers or recurse until the stack overflows. Every
+/// deserialize function checks these limits and fails with
+/// [`XbfError::LimitExceeded`](crate::XbfError::LimitExceeded) as soon as one is exceeded.
+///
+/// The defaults are generous enough for any data using 16-bit lengths, apart from the total
+/// size. Use [`DecodeLimits::unlimited`] to turn every check off for trusted input.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::DecodeLimits;
+/// use xbf_rs::LimitExceededError;
+/// use xbf_rs::XbfConfig;
+/// use xbf_rs::XbfError;
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfPrimitiveMetadata;
+///
+/// let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_string_len(4));
+/// let mut writer = vec![];
+/// XbfPrimitive::String("too long".to_string())
+///     .serialize_primitive_type(&mut writer)
+///     .unwrap();
+/// let mut reader = std::io::Cursor::new(writer);
+///
+/// let err = XbfPrimitive::deserialize_primitive_type_with_config(
+///     &XbfPrimitiveMetadata::String,
+///     &mut reader,
+///     &config,
+/// )
+/// .unwrap_err();
+/// assert!(matches!(
+///     err.root_cause(),
+///     XbfError::LimitExceeded(LimitExceededError::StringLength { limit: 4, actual: 8 })
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    max_depth: usize,
+    max_total_bytes: u64,
+    max_vec_elements: usize,
+    max_string_len: usize,
+    max_struct_fields: usize,
+}
+
+impl DecodeLimits {
+    /// Creates the default limits.
+    ///
+    /// These are a nesting depth of 128, 64 MiB in total, 1,048,576 elements per vector, 16 MiB
+    /// per string and 65,535 fields per struct.
+    pub fn new() -> Self {
+        Self {
+            max_depth: 128,
+            max_total_bytes: 64 * 1024 * 1024,
+            max_vec_elements: 1024 * 1024,
+            max_string_len: 16 * 1024 * 1024,
+            max_struct_fields: u16::MAX as usize,
+        }
+    }
+
+    /// Creates limits that never fail.
+    ///
+    /// Only use this for input you trust, a hostile peer can exhaust memory or the stack.
+    pub fn unlimited() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_total_bytes: u64::MAX,
+            max_vec_elements: usize::MAX,
+            max_string_len: usize::MAX,
+            max_struct_fields: usize::MAX,
+        }
+    }
+
+    /// Sets how deeply types may be nested.
+    ///
+    /// Every vector, struct, optional, enum, map and array counts as one level, primitives do not.
+    /// A vector of vectors of primitives therefore has a depth of two. This applies to metadata
+    /// and values alike.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets how many bytes a single deserialize call may read.
+    pub fn with_max_total_bytes(mut self, max_total_bytes: u64) -> Self {
+        self.max_total_bytes = max_total_bytes;
+        self
+    }
+
+    /// Sets how many elements a vector may contain.
+    ///
+    /// This also bounds the number of entries in a map and the length of an array.
+    pub fn with_max_vec_elements(mut self, max_vec_elements: usize) -> Self {
+        self.max_vec_elements = max_vec_elements;
+        self
+    }
+
+    /// Sets how many bytes a string may contain.
+    ///
+    /// This also bounds bytes primitives and the names contained in metadata.
+    pub fn with_max_string_len(mut self, max_string_len: usize) -> Self {
+        self.max_string_len = max_string_len;
+        self
+    }
+
+    /// Sets how many fields a struct may contain.
+    ///
+    /// This also bounds the number of variants in an enum.
+    pub fn with_max_struct_fields(mut self, max_struct_fields: usize) -> Self {
+        self.max_struct_fields = max_struct_fields;
+        self
+    }
+
+    /// Returns how deeply types may be nested.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Returns how many bytes a single deserialize call may read.
+    pub fn max_total_bytes(&self) -> u64 {
+        self.max_total_bytes
+    }
+
+    /// Returns how many elements a vector may contain.
+    pub fn max_vec_elements(&self) -> usize {
+        self.max_vec_elements
+    }
+
+    /// Returns how many bytes a string may contain.
+    pub fn max_string_len(&self) -> usize {
+        self.max_string_len
+    }
+
+    /// Returns how many fields a struct may contain.
+    pub fn max_struct_fields(&self) -> usize {
+        self.max_struct_fields
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error type for [`DecodeLimits`], one variant per limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitExceededError {
+    /// Types were nested more deeply than allowed.
+    Depth { limit: usize },
+    /// More bytes were read than allowed.
+    TotalBytes { limit: u64 },
+    /// A vector, map or array was longer than allowed.
+    VecElements { limit: usize, actual: usize },
+    /// A string or bytes was longer than allowed.
+    StringLength { limit: usize, actual: usize },
+    /// A struct or enum had more fields than allowed.
+    StructFields { limit: usize, actual: usize },
+}
+
+impl Display for LimitExceededError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceededError::Depth { limit } => {
+                write!(f, "types are nested more than {limit} levels deep")
+            }
+            LimitExceededError::TotalBytes { limit } => {
+                write!(f, "input is longer than {limit} bytes")
+            }
+            LimitExceededError::VecElements { limit, actual } => {
+                write!(f, "vector has {actual} elements, limit is {limit}")
+            }
+            LimitExceededError::StringLength { limit, actual } => {
+                write!(f, "string has {actual} bytes, limit is {limit}")
+            }
+            LimitExceededError::StructFields { limit, actual } => {
+                write!(f, "struct has {actual} fields, limit is {limit}")
+            }
+        }
+    }
+}
+
+impl Error for LimitExceededError {}
+
+impl From<LimitExceededError> for std::io::Error {
+    fn from(value: LimitExceededError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, value)
+    }
+}
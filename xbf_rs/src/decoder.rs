@@ -0,0 +1,1303 @@
+use crate::{
+    base_metadata::{check_names, check_variant_count},
+    stream::check_not_empty,
+    util::{preallocate, read_string, ReadContext, MAX_VARINT_LEN},
+    xbf_map::has_duplicate_keys,
+    LengthEncoding, LimitExceededError, Result, XbfArray, XbfArrayMetadata, XbfConfig,
+    XbfEnumMetadata, XbfEnumValue, XbfError, XbfMap, XbfMapMetadata, XbfMetadata, XbfOptional,
+    XbfOptionalMetadata, XbfPrimitive, XbfPrimitiveMetadata, XbfStruct, XbfStructMetadata, XbfType,
+    XbfVec, XbfVecMetadata, ARRAY_METADATA_DISCRIMINANT, ENUM_METADATA_DISCRIMINANT,
+    MAP_METADATA_DISCRIMINANT, OPTIONAL_METADATA_DISCRIMINANT, STRUCT_METADATA_DISCRIMINANT,
+    VEC_METADATA_DISCRIMINANT,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+
+/// What [`XbfDecoder::feed`] got to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeStatus {
+    /// Everything fed so far has been used, and more bytes are needed to finish the next header
+    /// or value.
+    NeedMoreData,
+    /// The metadata from the header of the stream.
+    Metadata(XbfMetadata),
+    /// The next value in the stream.
+    Value(XbfType),
+}
+
+/// Decodes a stream of values from chunks of bytes as they arrive, without ever blocking.
+///
+/// This is meant for event loops that read whatever a non-blocking socket has ready. Each chunk
+/// is passed to [`feed`](Self::feed), which decodes as far as it can and remembers where it got
+/// to, including partway through vectors, structs and the other compound types, so nothing is
+/// decoded twice however the input happens to be split up.
+///
+/// A decoder made with [`new`](Self::new) reads what an [`XbfStreamWriter`](crate::XbfStreamWriter)
+/// writes, returning the metadata from the header of the stream and then every value after it.
+/// One made with [`for_metadata`](Self::for_metadata) already knows the metadata, so returns values
+/// straight away.
+///
+/// The [`DecodeLimits`](crate::DecodeLimits) of the config apply to the header and to every value
+/// separately, and are checked as the bytes arrive, so a peer can't make the decoder hold on to
+/// more than they allow. Byte offsets in errors are counted from the first byte fed. After an
+/// error every later call to [`feed`](Self::feed) returns the same error, since there's no
+/// telling where the next value would start.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::DecodeStatus;
+/// use xbf_rs::XbfDecoder;
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfStreamWriter;
+///
+/// let mut writer = XbfStreamWriter::new(vec![], XbfPrimitiveMetadata::String.into()).unwrap();
+/// writer.write(&XbfPrimitive::String("hello".to_string()).into()).unwrap();
+/// writer.write(&XbfPrimitive::String("world".to_string()).into()).unwrap();
+/// let bytes = writer.into_inner();
+///
+/// let mut decoder = XbfDecoder::new();
+/// let mut values = vec![];
+/// // pretend the bytes arrive three at a time
+/// for mut chunk in bytes.chunks(3) {
+///     loop {
+///         match decoder.feed(chunk).unwrap() {
+///             DecodeStatus::NeedMoreData => break,
+///             DecodeStatus::Metadata(metadata) => {
+///                 assert_eq!(metadata, XbfPrimitiveMetadata::String.into())
+///             }
+///             DecodeStatus::Value(value) => values.push(value),
+///         }
+///         // whatever was left of the chunk is kept by the decoder
+///         chunk = &[];
+///     }
+/// }
+///
+/// assert_eq!(
+///     values,
+///     [
+///         XbfPrimitive::String("hello".to_string()).into(),
+///         XbfPrimitive::String("world".to_string()).into(),
+///     ]
+/// );
+/// assert!(!decoder.has_partial());
+/// ```
+#[derive(Debug)]
+pub struct XbfDecoder {
+    config: XbfConfig,
+    metadata: Option<XbfMetadata>,
+    /// Bytes fed after the last header or value returned, which haven't been decoded yet, from
+    /// `pending_start` on. The bytes before it are only removed once they make up most of the
+    /// buffer, so that a chunk holding many values isn't moved along after every one of them.
+    pending: Vec<u8>,
+    pending_start: usize,
+    scratch: Scratch,
+    header: MetadataProgress,
+    value: ValueProgress,
+    decoded: u64,
+    bytes_read: u64,
+    /// The error the decoder failed with, if it has.
+    failed: Option<XbfError>,
+}
+
+impl XbfDecoder {
+    /// Creates a new [`XbfDecoder`] that expects the header of a stream first.
+    pub fn new() -> Self {
+        Self::with_config(&XbfConfig::default())
+    }
+
+    /// Creates a new [`XbfDecoder`] that expects the header of a stream first, decoding using the
+    /// given [`XbfConfig`].
+    pub fn with_config(config: &XbfConfig) -> Self {
+        Self {
+            config: *config,
+            metadata: None,
+            pending: vec![],
+            pending_start: 0,
+            scratch: Scratch::default(),
+            header: MetadataProgress::default(),
+            value: ValueProgress::default(),
+            decoded: 0,
+            bytes_read: 0,
+            failed: None,
+        }
+    }
+
+    /// Creates a new [`XbfDecoder`] for values of the given metadata, with no header before them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XbfError::TypeMismatch`] if values of the metadata take up no bytes at all, since
+    /// there would be no telling how many of them there are.
+    pub fn for_metadata(metadata: XbfMetadata) -> Result<Self> {
+        Self::for_metadata_with_config(metadata, &XbfConfig::default())
+    }
+
+    /// Creates a new [`XbfDecoder`] for values of the given metadata that decodes using the given
+    /// [`XbfConfig`].
+    ///
+    /// See [`Self::for_metadata`] for more details.
+    pub fn for_metadata_with_config(metadata: XbfMetadata, config: &XbfConfig) -> Result<Self> {
+        check_not_empty(&metadata)?;
+        Ok(Self {
+            metadata: Some(metadata),
+            ..Self::with_config(config)
+        })
+    }
+
+    /// Decodes as much as it can of everything fed so far followed by `chunk`.
+    ///
+    /// Returns the first header or value finished, keeping whatever comes after it for later, so
+    /// once the input has run out keep calling this with an empty chunk until it returns
+    /// [`DecodeStatus::NeedMoreData`]. Partly decoded headers and values are kept as they are,
+    /// and carried on with when more bytes arrive.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error that deserializing the same bytes in one go would, wrapped in
+    /// [`XbfError::At`]. The path of an error in a value starts with its index, such as
+    /// `root[3].name`. Once an error has been returned, the same error is returned again by every
+    /// later call.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<DecodeStatus> {
+        if let Some(e) = &self.failed {
+            return Err(e.duplicate());
+        }
+        let status = self.feed_chunk(chunk);
+        if let Err(e) = &status {
+            self.failed = Some(e.duplicate());
+        }
+        status
+    }
+
+    fn feed_chunk(&mut self, chunk: &[u8]) -> Result<DecodeStatus> {
+        if self.pending_start == self.pending.len() {
+            self.pending.clear();
+            self.pending_start = 0;
+            let mut input = chunk;
+            let status = self.decode(&mut input);
+            self.pending.extend_from_slice(input);
+            status
+        } else {
+            if self.pending_start > self.pending.len() / 2 {
+                self.pending.drain(..self.pending_start);
+                self.pending_start = 0;
+            }
+            let mut pending = std::mem::take(&mut self.pending);
+            pending.extend_from_slice(chunk);
+            let mut input = &pending[self.pending_start..];
+            let status = self.decode(&mut input);
+            self.pending_start = pending.len() - input.len();
+            self.pending = pending;
+            status
+        }
+    }
+
+    fn decode(&mut self, input: &mut &[u8]) -> Result<DecodeStatus> {
+        let start = input.len();
+        let result = self.decode_next(input);
+        self.bytes_read += (start - input.len()) as u64;
+        result.map_err(|e| e.at_offset(self.bytes_read))
+    }
+
+    fn decode_next(&mut self, input: &mut &[u8]) -> Result<DecodeStatus> {
+        let Some(metadata) = &self.metadata else {
+            let Some(metadata) = self
+                .header
+                .advance(input, &mut self.scratch, &self.config)?
+            else {
+                return Ok(DecodeStatus::NeedMoreData);
+            };
+            check_not_empty(&metadata)?;
+            self.metadata = Some(metadata.clone());
+            return Ok(DecodeStatus::Metadata(metadata));
+        };
+        let index = self.decoded;
+        let value = self
+            .value
+            .advance(input, &mut self.scratch, metadata, &self.config)
+            .map_err(|e| e.within(format_args!("[{index}]")))?;
+        Ok(match value {
+            Some(value) => {
+                self.decoded += 1;
+                DecodeStatus::Value(value)
+            }
+            None => DecodeStatus::NeedMoreData,
+        })
+    }
+
+    /// Returns the metadata of the values being decoded, once it is known.
+    pub fn metadata(&self) -> Option<&XbfMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Returns the number of values decoded so far.
+    pub fn decoded(&self) -> u64 {
+        self.decoded
+    }
+
+    /// Returns whether any bytes have been fed that aren't part of a header or value returned yet.
+    ///
+    /// Once the input has ended and [`feed`](Self::feed) has returned
+    /// [`DecodeStatus::NeedMoreData`], this says whether the input ended partway through a value.
+    pub fn has_partial(&self) -> bool {
+        self.pending_start < self.pending.len()
+            || !matches!(self.header.step, MetadataStep::Idle)
+            || !matches!(self.value.step, ValueStep::Idle)
+    }
+}
+
+impl Default for XbfDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How far through a single header or value an [`XbfAsyncReader`](crate::XbfAsyncReader) has
+/// got, so that bytes arriving a few at a time are only decoded once.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default)]
+pub(crate) struct Progress {
+    scratch: Scratch,
+    header: MetadataProgress,
+    value: ValueProgress,
+}
+
+#[cfg(feature = "tokio")]
+impl Progress {
+    /// Decodes as much of a header as it can from the input, returning it once finished.
+    pub(crate) fn metadata(
+        &mut self,
+        input: &mut &[u8],
+        config: &XbfConfig,
+    ) -> Result<Option<XbfMetadata>> {
+        self.header.advance(input, &mut self.scratch, config)
+    }
+
+    /// Decodes as much of a value as it can from the input, returning it once finished.
+    pub(crate) fn value(
+        &mut self,
+        input: &mut &[u8],
+        metadata: &XbfMetadata,
+        config: &XbfConfig,
+    ) -> Result<Option<XbfType>> {
+        if matches!(self.value.step, ValueStep::Idle) {
+            // values that take up no bytes are finished without waiting for any input
+            self.value.step = ValueStep::Start(metadata.clone());
+        }
+        self.value
+            .advance(input, &mut self.scratch, metadata, config)
+    }
+
+    /// Returns the error for the input ending partway through, with the path of where it ended.
+    pub(crate) fn unexpected_eof(&self) -> XbfError {
+        let e = XbfError::UnexpectedEof;
+        let e = self
+            .header
+            .stack
+            .iter()
+            .rev()
+            .fold(e, |e, frame| frame.within(e));
+        self.value
+            .stack
+            .iter()
+            .rev()
+            .fold(e, |e, frame| frame.within(e))
+    }
+}
+
+/// How a primitive, length or tag is laid out, all of which has to arrive before it can be
+/// decoded.
+#[derive(Debug, Clone, Copy)]
+enum Leaf {
+    /// Exactly this many bytes.
+    Fixed(usize),
+    /// A length.
+    Len,
+    /// A length followed by that many bytes.
+    Bytes,
+}
+
+impl Leaf {
+    fn primitive(metadata: XbfPrimitiveMetadata) -> Self {
+        metadata.fixed_size().map_or(Leaf::Bytes, Leaf::Fixed)
+    }
+}
+
+/// The bytes of the primitive, length or tag being decoded.
+#[derive(Debug, Default)]
+struct Scratch {
+    bytes: Vec<u8>,
+    /// The number of bytes of the current header or value used so far.
+    used: u64,
+}
+
+impl Scratch {
+    /// Moves bytes of `leaf` out of the input, returning whether all of them have arrived.
+    fn fill(&mut self, input: &mut &[u8], leaf: Leaf, config: &XbfConfig) -> Result<bool> {
+        while let Some(needed) = self.remaining(leaf, config)? {
+            if input.is_empty() {
+                return Ok(false);
+            }
+            let (bytes, rest) = input.split_at(needed.min(input.len()));
+            let limit = config.limits().max_total_bytes();
+            if bytes.len() as u64 > limit - self.used {
+                return Err(LimitExceededError::TotalBytes { limit }.into());
+            }
+            self.bytes.extend_from_slice(bytes);
+            self.used += bytes.len() as u64;
+            *input = rest;
+        }
+        Ok(true)
+    }
+
+    /// Returns how many more bytes `leaf` needs, or `None` if it's all there.
+    fn remaining(&self, leaf: Leaf, config: &XbfConfig) -> Result<Option<usize>> {
+        let prefix = || prefix_len(&self.bytes, config.length_encoding());
+        let total = match leaf {
+            Leaf::Fixed(len) => len,
+            Leaf::Len => match prefix() {
+                Ok(prefix) => prefix,
+                Err(needed) => return Ok(Some(needed)),
+            },
+            Leaf::Bytes => match prefix() {
+                Ok(prefix) => {
+                    let len = ReadContext::new(&self.bytes[..prefix], config).read_bytes_len()?;
+                    prefix + len
+                }
+                Err(needed) => return Ok(Some(needed)),
+            },
+        };
+        Ok(total.checked_sub(self.bytes.len()).filter(|&x| x > 0))
+    }
+
+    fn reader<'a>(&'a self, config: &XbfConfig) -> ReadContext<&'a [u8]> {
+        ReadContext::new(self.bytes.as_slice(), config)
+    }
+}
+
+/// Returns the size of the length at the start of `bytes`, or how many more bytes are needed to
+/// tell.
+fn prefix_len(bytes: &[u8], encoding: LengthEncoding) -> std::result::Result<usize, usize> {
+    let len = match encoding {
+        LengthEncoding::U16 => 2,
+        LengthEncoding::U32 => 4,
+        LengthEncoding::Varint => match bytes.iter().position(|x| x & 0x80 == 0) {
+            Some(i) => i + 1,
+            None if bytes.len() >= MAX_VARINT_LEN => MAX_VARINT_LEN,
+            None => return Err(1),
+        },
+    };
+    match len.checked_sub(bytes.len()) {
+        Some(needed) if needed > 0 => Err(needed),
+        _ => Ok(len),
+    }
+}
+
+/// Fails if starting a compound type inside `depth` others would exceed the depth limit.
+fn check_depth(depth: usize, config: &XbfConfig) -> Result<()> {
+    let limit = config.limits().max_depth();
+    if depth >= limit {
+        return Err(LimitExceededError::Depth { limit }.into());
+    }
+    Ok(())
+}
+
+/// How far through a header the decoder has got.
+#[derive(Debug, Default)]
+struct MetadataProgress {
+    /// The compound types partway through being decoded, outermost first.
+    stack: Vec<MetadataFrame>,
+    step: MetadataStep,
+}
+
+#[derive(Debug, Default)]
+enum MetadataStep {
+    /// Nothing of the header has arrived yet.
+    #[default]
+    Idle,
+    /// Waiting on the bytes of a discriminant, name or length.
+    Read(MetadataRead),
+    /// Hand finished metadata to the innermost compound type, or return it if there isn't one.
+    Finish(XbfMetadata),
+}
+
+#[derive(Debug)]
+enum MetadataRead {
+    Discriminant,
+    Name(NamedKind),
+    EntriesLen(NamedKind, String),
+    EntryName,
+    ArrayLen,
+    MapKey,
+}
+
+impl MetadataRead {
+    fn leaf(&self) -> Leaf {
+        match self {
+            MetadataRead::Discriminant | MetadataRead::MapKey => Leaf::Fixed(1),
+            MetadataRead::Name(_) | MetadataRead::EntryName => Leaf::Bytes,
+            MetadataRead::EntriesLen(..) | MetadataRead::ArrayLen => Leaf::Len,
+        }
+    }
+}
+
+/// Structs and enums are laid out the same way, as a name followed by named entries.
+#[derive(Debug, Clone, Copy)]
+enum NamedKind {
+    Struct,
+    Enum,
+}
+
+#[derive(Debug)]
+enum MetadataFrame {
+    Vec,
+    Optional,
+    Array(usize),
+    Map(XbfPrimitiveMetadata),
+    Named {
+        kind: NamedKind,
+        name: String,
+        len: usize,
+        entries: Vec<(String, XbfMetadata)>,
+        /// The name of the entry whose type is being decoded.
+        entry_name: Option<String>,
+    },
+}
+
+impl MetadataFrame {
+    /// Adds where the type being decoded sits within this one to the path of an error.
+    fn within(&self, e: XbfError) -> XbfError {
+        match self {
+            MetadataFrame::Vec | MetadataFrame::Array(_) | MetadataFrame::Map(_) => e.within("[]"),
+            MetadataFrame::Optional => e,
+            MetadataFrame::Named {
+                kind,
+                entries,
+                entry_name,
+                ..
+            } => match (kind, entry_name) {
+                (NamedKind::Struct, Some(name)) => e.within(format_args!(".{name}")),
+                (NamedKind::Struct, None) => e.within(format_args!(".<field {}>", entries.len())),
+                (NamedKind::Enum, Some(name)) => e.within(format_args!("::{name}")),
+                (NamedKind::Enum, None) => e.within(format_args!("::<variant {}>", entries.len())),
+            },
+        }
+    }
+}
+
+impl MetadataProgress {
+    fn advance(
+        &mut self,
+        input: &mut &[u8],
+        scratch: &mut Scratch,
+        config: &XbfConfig,
+    ) -> Result<Option<XbfMetadata>> {
+        self.run(input, scratch, config)
+            .map_err(|e| self.stack.iter().rev().fold(e, |e, frame| frame.within(e)))
+    }
+
+    fn run(
+        &mut self,
+        input: &mut &[u8],
+        scratch: &mut Scratch,
+        config: &XbfConfig,
+    ) -> Result<Option<XbfMetadata>> {
+        loop {
+            self.step = match std::mem::take(&mut self.step) {
+                MetadataStep::Idle if input.is_empty() => return Ok(None),
+                MetadataStep::Idle => MetadataStep::Read(MetadataRead::Discriminant),
+                MetadataStep::Read(read) => {
+                    if !scratch.fill(input, read.leaf(), config)? {
+                        self.step = MetadataStep::Read(read);
+                        return Ok(None);
+                    }
+                    let step = self.read(read, &mut scratch.reader(config));
+                    scratch.bytes.clear();
+                    step?
+                }
+                MetadataStep::Finish(metadata) => match self.stack.pop() {
+                    Some(frame) => self.finish(frame, metadata)?,
+                    None => {
+                        scratch.used = 0;
+                        return Ok(Some(metadata));
+                    }
+                },
+            }
+        }
+    }
+
+    fn read(
+        &mut self,
+        read: MetadataRead,
+        reader: &mut ReadContext<&[u8]>,
+    ) -> Result<MetadataStep> {
+        let next = match read {
+            MetadataRead::Discriminant => {
+                let discriminant = reader.read_u8()?;
+                if let Ok(x) = XbfPrimitiveMetadata::try_from(discriminant) {
+                    return Ok(MetadataStep::Finish(x.into()));
+                }
+                let (frame, next) = match discriminant {
+                    VEC_METADATA_DISCRIMINANT => {
+                        (Some(MetadataFrame::Vec), MetadataRead::Discriminant)
+                    }
+                    STRUCT_METADATA_DISCRIMINANT => (None, MetadataRead::Name(NamedKind::Struct)),
+                    OPTIONAL_METADATA_DISCRIMINANT => {
+                        (Some(MetadataFrame::Optional), MetadataRead::Discriminant)
+                    }
+                    ENUM_METADATA_DISCRIMINANT => (None, MetadataRead::Name(NamedKind::Enum)),
+                    MAP_METADATA_DISCRIMINANT => (None, MetadataRead::MapKey),
+                    ARRAY_METADATA_DISCRIMINANT => (None, MetadataRead::ArrayLen),
+                    x => return Err(XbfError::UnknownDiscriminant(x)),
+                };
+                check_depth(self.stack.len(), reader.config())?;
+                self.stack.extend(frame);
+                next
+            }
+            MetadataRead::Name(kind) => MetadataRead::EntriesLen(kind, read_string(reader)?),
+            MetadataRead::EntriesLen(kind, name) => {
+                let len = reader.read_fields_len()?;
+                self.stack.push(MetadataFrame::Named {
+                    kind,
+                    name,
+                    len,
+                    entries: preallocate(len),
+                    entry_name: None,
+                });
+                return self.next_entry();
+            }
+            MetadataRead::EntryName => {
+                let name = read_string(reader)?;
+                let Some(MetadataFrame::Named { entry_name, .. }) = self.stack.last_mut() else {
+                    unreachable!("entry names are only read inside a struct or enum");
+                };
+                *entry_name = Some(name);
+                MetadataRead::Discriminant
+            }
+            MetadataRead::ArrayLen => {
+                let len = reader.read_elements_len()?;
+                self.stack.push(MetadataFrame::Array(len));
+                MetadataRead::Discriminant
+            }
+            MetadataRead::MapKey => {
+                let key = reader.read_u8()?;
+                let key_type = XbfPrimitiveMetadata::try_from(key)
+                    .map_err(|_| XbfError::InvalidMapKey(key))?;
+                self.stack.push(MetadataFrame::Map(key_type));
+                MetadataRead::Discriminant
+            }
+        };
+        Ok(MetadataStep::Read(next))
+    }
+
+    fn finish(&mut self, frame: MetadataFrame, metadata: XbfMetadata) -> Result<MetadataStep> {
+        let metadata = match frame {
+            MetadataFrame::Vec => XbfVecMetadata::new(metadata).into(),
+            MetadataFrame::Optional => XbfOptionalMetadata::new(metadata).into(),
+            MetadataFrame::Array(len) => XbfArrayMetadata::new(metadata, len).into(),
+            MetadataFrame::Map(key_type) => XbfMapMetadata::new(key_type, metadata).into(),
+            MetadataFrame::Named {
+                kind,
+                name,
+                len,
+                mut entries,
+                entry_name,
+            } => {
+                let entry_name = entry_name.expect("entry types are only read after their name");
+                entries.push((entry_name, metadata));
+                self.stack.push(MetadataFrame::Named {
+                    kind,
+                    name,
+                    len,
+                    entries,
+                    entry_name: None,
+                });
+                return self.next_entry();
+            }
+        };
+        Ok(MetadataStep::Finish(metadata))
+    }
+
+    /// Reads the name of the next entry of the innermost struct or enum, or finishes it once it
+    /// has all of them.
+    fn next_entry(&mut self) -> Result<MetadataStep> {
+        if let Some(MetadataFrame::Named { len, entries, .. }) = self.stack.last() {
+            if entries.len() < *len {
+                return Ok(MetadataStep::Read(MetadataRead::EntryName));
+            }
+        }
+        let Some(MetadataFrame::Named {
+            kind,
+            name,
+            entries,
+            ..
+        }) = self.stack.pop()
+        else {
+            unreachable!("entries are only read inside a struct or enum");
+        };
+        let metadata = match kind {
+            NamedKind::Struct => {
+                check_names(&entries)?;
+                XbfStructMetadata::new(name, entries).into()
+            }
+            NamedKind::Enum => {
+                check_variant_count(&entries)?;
+                check_names(&entries)?;
+                XbfEnumMetadata::new(name, entries).into()
+            }
+        };
+        Ok(MetadataStep::Finish(metadata))
+    }
+}
+
+/// How far through a value the decoder has got.
+#[derive(Debug, Default)]
+struct ValueProgress {
+    /// The compound values partway through being decoded, outermost first.
+    stack: Vec<ValueFrame>,
+    step: ValueStep,
+}
+
+#[derive(Debug, Default)]
+enum ValueStep {
+    /// Nothing of the next value has arrived yet.
+    #[default]
+    Idle,
+    /// Start on a value of the given type.
+    Start(XbfMetadata),
+    /// Waiting on the bytes of a primitive, length or tag.
+    Read(ValueRead),
+    /// Start on the next child of the innermost compound value, or finish it once it has all of
+    /// them.
+    Resume,
+    /// Hand a finished value to the innermost compound value, or return it if there isn't one.
+    Finish(XbfType),
+}
+
+#[derive(Debug)]
+enum ValueRead {
+    Primitive(XbfPrimitiveMetadata),
+    MapKey(XbfPrimitiveMetadata),
+    VecLen(XbfVecMetadata),
+    MapLen(XbfMapMetadata),
+    Presence(XbfOptionalMetadata),
+    Variant(XbfEnumMetadata),
+}
+
+impl ValueRead {
+    fn leaf(&self) -> Leaf {
+        match self {
+            ValueRead::Primitive(x) | ValueRead::MapKey(x) => Leaf::primitive(*x),
+            ValueRead::VecLen(_) | ValueRead::MapLen(_) => Leaf::Len,
+            ValueRead::Presence(_) => Leaf::Fixed(1),
+            ValueRead::Variant(_) => Leaf::Fixed(2),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ValueFrame {
+    Vec {
+        metadata: XbfVecMetadata,
+        len: usize,
+        elements: Vec<XbfType>,
+    },
+    Struct {
+        metadata: XbfStructMetadata,
+        fields: Vec<XbfType>,
+    },
+    Optional(XbfOptionalMetadata),
+    Enum {
+        metadata: XbfEnumMetadata,
+        variant: u16,
+    },
+    Map {
+        metadata: XbfMapMetadata,
+        len: usize,
+        entries: Vec<(XbfPrimitive, XbfType)>,
+        /// The key of the entry whose value is being decoded.
+        key: Option<XbfPrimitive>,
+    },
+    Array {
+        metadata: XbfArrayMetadata,
+        elements: Vec<XbfType>,
+    },
+}
+
+impl ValueFrame {
+    /// Adds where the value being decoded sits within this one to the path of an error.
+    fn within(&self, e: XbfError) -> XbfError {
+        match self {
+            ValueFrame::Vec { elements, .. } | ValueFrame::Array { elements, .. } => {
+                e.within(format_args!("[{}]", elements.len()))
+            }
+            ValueFrame::Map { entries, .. } => e.within(format_args!("[{}]", entries.len())),
+            ValueFrame::Struct { metadata, fields } => match metadata.fields.get(fields.len()) {
+                Some((name, _)) => e.within(format_args!(".{name}")),
+                None => e,
+            },
+            ValueFrame::Optional(_) => e,
+            ValueFrame::Enum { metadata, variant } => {
+                match metadata.variants.get(*variant as usize) {
+                    Some((name, _)) => e.within(format_args!("::{name}")),
+                    None => e,
+                }
+            }
+        }
+    }
+}
+
+impl ValueProgress {
+    fn advance(
+        &mut self,
+        input: &mut &[u8],
+        scratch: &mut Scratch,
+        metadata: &XbfMetadata,
+        config: &XbfConfig,
+    ) -> Result<Option<XbfType>> {
+        self.run(input, scratch, metadata, config)
+            .map_err(|e| self.stack.iter().rev().fold(e, |e, frame| frame.within(e)))
+    }
+
+    fn run(
+        &mut self,
+        input: &mut &[u8],
+        scratch: &mut Scratch,
+        metadata: &XbfMetadata,
+        config: &XbfConfig,
+    ) -> Result<Option<XbfType>> {
+        loop {
+            self.step = match std::mem::take(&mut self.step) {
+                ValueStep::Idle if input.is_empty() => return Ok(None),
+                ValueStep::Idle => ValueStep::Start(metadata.clone()),
+                ValueStep::Start(metadata) => self.start(metadata, config)?,
+                ValueStep::Read(read) => {
+                    if !scratch.fill(input, read.leaf(), config)? {
+                        self.step = ValueStep::Read(read);
+                        return Ok(None);
+                    }
+                    let step = self.read(read, &mut scratch.reader(config));
+                    scratch.bytes.clear();
+                    step?
+                }
+                ValueStep::Resume => self.resume()?,
+                ValueStep::Finish(value) => match self.stack.pop() {
+                    Some(frame) => self.finish(frame, value),
+                    None => {
+                        scratch.used = 0;
+                        return Ok(Some(value));
+                    }
+                },
+            }
+        }
+    }
+
+    fn start(&mut self, metadata: XbfMetadata, config: &XbfConfig) -> Result<ValueStep> {
+        if !matches!(metadata, XbfMetadata::Primitive(_)) {
+            check_depth(self.stack.len(), config)?;
+        }
+        Ok(match metadata {
+            XbfMetadata::Primitive(x) => ValueStep::Read(ValueRead::Primitive(x)),
+            XbfMetadata::Vec(x) => ValueStep::Read(ValueRead::VecLen(x)),
+            XbfMetadata::Struct(x) => {
+                self.stack.push(ValueFrame::Struct {
+                    fields: preallocate(x.fields.len()),
+                    metadata: x,
+                });
+                ValueStep::Resume
+            }
+            XbfMetadata::Optional(x) => ValueStep::Read(ValueRead::Presence(x)),
+            XbfMetadata::Enum(x) => ValueStep::Read(ValueRead::Variant(x)),
+            XbfMetadata::Map(x) => ValueStep::Read(ValueRead::MapLen(x)),
+            XbfMetadata::Array(x) => {
+                self.stack.push(ValueFrame::Array {
+                    elements: preallocate(x.len()),
+                    metadata: x,
+                });
+                ValueStep::Resume
+            }
+        })
+    }
+
+    fn read(&mut self, read: ValueRead, reader: &mut ReadContext<&[u8]>) -> Result<ValueStep> {
+        Ok(match read {
+            ValueRead::Primitive(x) => ValueStep::Finish(
+                XbfPrimitive::deserialize_primitive_type_with_context(&x, reader)?.into(),
+            ),
+            ValueRead::MapKey(x) => {
+                let key = XbfPrimitive::deserialize_primitive_type_with_context(&x, reader)?;
+                let Some(ValueFrame::Map {
+                    metadata,
+                    key: slot,
+                    ..
+                }) = self.stack.last_mut()
+                else {
+                    unreachable!("map keys are only read inside a map");
+                };
+                *slot = Some(key);
+                ValueStep::Start((*metadata.value_type).clone())
+            }
+            ValueRead::VecLen(metadata) => {
+                let len = reader.read_elements_len()?;
+                self.stack.push(ValueFrame::Vec {
+                    metadata,
+                    len,
+                    elements: preallocate(len),
+                });
+                ValueStep::Resume
+            }
+            ValueRead::MapLen(metadata) => {
+                let len = reader.read_elements_len()?;
+                self.stack.push(ValueFrame::Map {
+                    metadata,
+                    len,
+                    entries: preallocate(len),
+                    key: None,
+                });
+                ValueStep::Resume
+            }
+            ValueRead::Presence(metadata) => match reader.read_u8()? {
+                0 => ValueStep::Finish(XbfOptional::new_unchecked(metadata, None).into()),
+                1 => {
+                    let inner_type = (*metadata.inner_type).clone();
+                    self.stack.push(ValueFrame::Optional(metadata));
+                    ValueStep::Start(inner_type)
+                }
+                x => return Err(XbfError::InvalidPresenceByte(x)),
+            },
+            ValueRead::Variant(metadata) => {
+                let variant = reader.read_u16::<LittleEndian>()?;
+                let (_, variant_type) = metadata
+                    .variants
+                    .get(variant as usize)
+                    .ok_or(XbfError::UnknownVariant(variant))?;
+                let variant_type = variant_type.clone();
+                self.stack.push(ValueFrame::Enum { metadata, variant });
+                ValueStep::Start(variant_type)
+            }
+        })
+    }
+
+    fn resume(&mut self) -> Result<ValueStep> {
+        let next = match self.stack.last() {
+            Some(ValueFrame::Vec {
+                metadata,
+                len,
+                elements,
+            }) if elements.len() < *len => ValueStep::Start((*metadata.inner_type).clone()),
+            Some(ValueFrame::Struct { metadata, fields })
+                if fields.len() < metadata.fields.len() =>
+            {
+                ValueStep::Start(metadata.fields[fields.len()].1.clone())
+            }
+            Some(ValueFrame::Map {
+                metadata,
+                len,
+                entries,
+                ..
+            }) if entries.len() < *len => ValueStep::Read(ValueRead::MapKey(metadata.key_type)),
+            Some(ValueFrame::Array { metadata, elements }) if elements.len() < metadata.len() => {
+                ValueStep::Start((*metadata.inner_type).clone())
+            }
+            _ => return self.pop(),
+        };
+        Ok(next)
+    }
+
+    /// Finishes the innermost compound value once it has all of its children.
+    fn pop(&mut self) -> Result<ValueStep> {
+        let value = match self.stack.pop() {
+            Some(ValueFrame::Vec {
+                metadata, elements, ..
+            }) => XbfVec::new_unchecked(metadata, elements).into(),
+            Some(ValueFrame::Struct { metadata, fields }) => {
+                XbfStruct::new_unchecked(metadata, fields).into()
+            }
+            Some(ValueFrame::Map {
+                metadata, entries, ..
+            }) => {
+                if has_duplicate_keys(entries.iter().map(|(k, _)| k.into())) {
+                    return Err(XbfError::DuplicateKey);
+                }
+                XbfMap::new_unchecked(metadata, entries).into()
+            }
+            Some(ValueFrame::Array { metadata, elements }) => {
+                XbfArray::new_unchecked(metadata, elements).into()
+            }
+            frame => unreachable!("{frame:?} is finished along with its value"),
+        };
+        Ok(ValueStep::Finish(value))
+    }
+
+    fn finish(&mut self, mut frame: ValueFrame, value: XbfType) -> ValueStep {
+        match &mut frame {
+            ValueFrame::Vec { elements, .. } | ValueFrame::Array { elements, .. } => {
+                elements.push(value)
+            }
+            ValueFrame::Struct { fields, .. } => fields.push(value),
+            ValueFrame::Map { entries, key, .. } => {
+                let key = key
+                    .take()
+                    .expect("map values are only read after their key");
+                entries.push((key, value));
+            }
+            ValueFrame::Optional(metadata) => {
+                let metadata = metadata.clone();
+                return ValueStep::Finish(XbfOptional::new_unchecked(metadata, Some(value)).into());
+            }
+            ValueFrame::Enum { metadata, variant } => {
+                let metadata = metadata.clone();
+                return ValueStep::Finish(
+                    XbfEnumValue::new_unchecked(metadata, *variant, value).into(),
+                );
+            }
+        }
+        self.stack.push(frame);
+        ValueStep::Resume
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DecodeLimits, XbfStreamReader, XbfStreamWriter};
+
+    fn shape_metadata() -> XbfEnumMetadata {
+        let point = XbfArrayMetadata::new(XbfPrimitiveMetadata::I16.into(), 2);
+        XbfEnumMetadata::new(
+            "shape".to_string(),
+            vec![
+                ("circle".to_string(), XbfPrimitiveMetadata::F32.into()),
+                (
+                    "polygon".to_string(),
+                    XbfVecMetadata::new(point.into()).into(),
+                ),
+                (
+                    "empty".to_string(),
+                    XbfStructMetadata::new("nothing".to_string(), vec![]).into(),
+                ),
+            ],
+        )
+    }
+
+    fn scores_metadata() -> XbfMapMetadata {
+        XbfMapMetadata::new(
+            XbfPrimitiveMetadata::String,
+            XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into()).into(),
+        )
+    }
+
+    fn record_metadata() -> XbfStructMetadata {
+        XbfStructMetadata::new(
+            "record".to_string(),
+            vec![
+                ("id".to_string(), XbfPrimitiveMetadata::U64.into()),
+                ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+                (
+                    "tags".to_string(),
+                    XbfVecMetadata::new(XbfPrimitiveMetadata::String.into()).into(),
+                ),
+                ("scores".to_string(), scores_metadata().into()),
+                ("shape".to_string(), shape_metadata().into()),
+                ("raw".to_string(), XbfPrimitiveMetadata::Bytes.into()),
+            ],
+        )
+    }
+
+    fn string(s: impl Into<String>) -> XbfPrimitive {
+        XbfPrimitive::String(s.into())
+    }
+
+    fn record(i: u32) -> XbfType {
+        let tags = (0..i % 4).map(|x| string(x.to_string()).into()).collect();
+        let tags = XbfVec::new(
+            XbfVecMetadata::new(XbfPrimitiveMetadata::String.into()),
+            tags,
+        );
+        let scores = (0..i % 3)
+            .map(|x| {
+                let score = (x % 2 == 0).then(|| XbfPrimitive::I32(x as i32).into());
+                let score = XbfOptional::new(
+                    XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into()),
+                    score,
+                );
+                (string(format!("k{x}")), score.unwrap().into())
+            })
+            .collect();
+        let shape = match i % 3 {
+            0 => XbfEnumValue::new(
+                shape_metadata(),
+                "circle",
+                XbfPrimitive::F32(i as f32).into(),
+            ),
+            1 => {
+                let point = XbfArrayMetadata::new(XbfPrimitiveMetadata::I16.into(), 2);
+                let points = (0..i % 5)
+                    .map(|x| {
+                        let x = x as i16;
+                        let coords =
+                            vec![XbfPrimitive::I16(x).into(), XbfPrimitive::I16(-x).into()];
+                        XbfArray::new(point.clone(), coords).unwrap().into()
+                    })
+                    .collect();
+                let polygon = XbfVec::new(XbfVecMetadata::new(point.into()), points).unwrap();
+                XbfEnumValue::new(shape_metadata(), "polygon", polygon.into())
+            }
+            _ => {
+                let nothing = XbfStructMetadata::new("nothing".to_string(), vec![]);
+                let nothing = XbfStruct::new(nothing, vec![]).unwrap();
+                XbfEnumValue::new(shape_metadata(), "empty", nothing.into())
+            }
+        };
+        XbfStruct::new(
+            record_metadata(),
+            vec![
+                XbfPrimitive::U64(i.into()).into(),
+                string(format!("record {i}")).into(),
+                tags.unwrap().into(),
+                XbfMap::new(scores_metadata(), scores).unwrap().into(),
+                shape.unwrap().into(),
+                XbfPrimitive::Bytes(vec![i as u8; i as usize % 7]).into(),
+            ],
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn stream(values: &[XbfType], config: &XbfConfig) -> Vec<u8> {
+        let mut writer =
+            XbfStreamWriter::with_config(vec![], record_metadata().into(), config).unwrap();
+        for value in values {
+            writer.write(value).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    /// Feeds the bytes `size` at a time, collecting everything decoded.
+    fn feed_chunks(
+        decoder: &mut XbfDecoder,
+        bytes: &[u8],
+        size: usize,
+    ) -> Result<Vec<DecodeStatus>> {
+        let mut decoded = vec![];
+        for mut chunk in bytes.chunks(size) {
+            loop {
+                match decoder.feed(chunk)? {
+                    DecodeStatus::NeedMoreData => break,
+                    x => decoded.push(x),
+                }
+                chunk = &[];
+            }
+        }
+        Ok(decoded)
+    }
+
+    #[test]
+    fn decodes_a_stream_however_it_is_split() {
+        let values: Vec<_> = (0..20).map(record).collect();
+        for config in [
+            XbfConfig::default(),
+            XbfConfig::new().with_length_encoding(LengthEncoding::Varint),
+        ] {
+            let bytes = stream(&values, &config);
+            let mut expected = vec![DecodeStatus::Metadata(record_metadata().into())];
+            expected.extend(values.iter().cloned().map(DecodeStatus::Value));
+
+            for size in [1, 2, 3, 7, 64, bytes.len()] {
+                let mut decoder = XbfDecoder::with_config(&config);
+                assert_eq!(feed_chunks(&mut decoder, &bytes, size).unwrap(), expected);
+                assert_eq!(decoder.metadata(), Some(&record_metadata().into()));
+                assert_eq!(decoder.decoded(), 20);
+                assert!(!decoder.has_partial());
+            }
+        }
+    }
+
+    #[test]
+    fn bytes_after_a_value_are_kept_for_later() {
+        let mut decoder = XbfDecoder::for_metadata(XbfPrimitiveMetadata::U16.into()).unwrap();
+
+        let value = |x| DecodeStatus::Value(XbfPrimitive::U16(x).into());
+        assert_eq!(decoder.feed(&[1, 0, 2, 0, 3]).unwrap(), value(1));
+        assert!(decoder.has_partial());
+        assert_eq!(decoder.feed(&[]).unwrap(), value(2));
+        assert_eq!(decoder.feed(&[]).unwrap(), DecodeStatus::NeedMoreData);
+        assert!(decoder.has_partial());
+        assert_eq!(decoder.feed(&[0]).unwrap(), value(3));
+        assert_eq!(decoder.feed(&[]).unwrap(), DecodeStatus::NeedMoreData);
+        assert!(!decoder.has_partial());
+    }
+
+    #[test]
+    fn many_values_in_one_chunk_are_decoded() {
+        let mut decoder = XbfDecoder::for_metadata(XbfPrimitiveMetadata::U16.into()).unwrap();
+        let bytes: Vec<_> = (0..200_000u32)
+            .flat_map(|x| (x as u16).to_le_bytes())
+            .collect();
+
+        let decoded = feed_chunks(&mut decoder, &bytes, bytes.len()).unwrap();
+
+        assert_eq!(decoded.len(), 200_000);
+        assert_eq!(
+            decoded[199_999],
+            DecodeStatus::Value(XbfPrimitive::U16(199_999u32 as u16).into())
+        );
+        assert!(!decoder.has_partial());
+        assert!(decoder.pending.is_empty());
+    }
+
+    #[test]
+    fn input_ending_partway_through_a_value_is_reported() {
+        let values: Vec<_> = (0..5).map(record).collect();
+        let bytes = stream(&values, &XbfConfig::default());
+
+        let mut decoder = XbfDecoder::new();
+        let decoded = feed_chunks(&mut decoder, &bytes[..bytes.len() - 1], 5).unwrap();
+
+        assert_eq!(decoded.len(), 5);
+        assert_eq!(decoder.decoded(), 4);
+        assert!(decoder.has_partial());
+    }
+
+    #[test]
+    fn empty_values_are_rejected() {
+        let nothing: XbfMetadata = XbfStructMetadata::new("nothing".to_string(), vec![]).into();
+        let err = XbfDecoder::for_metadata(nothing.clone()).unwrap_err();
+        assert!(matches!(err, XbfError::TypeMismatch(_)));
+
+        let mut header = vec![];
+        nothing.serialize_base_metadata(&mut header).unwrap();
+        let err = XbfDecoder::new().feed(&header).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+        assert_eq!(err.offset(), Some(header.len() as u64));
+    }
+
+    fn parts(e: &XbfError) -> (String, Option<&str>, Option<u64>) {
+        (e.root_cause().to_string(), e.path(), e.offset())
+    }
+
+    #[test]
+    fn errors_match_the_stream_reader() {
+        let optionals = [VEC_METADATA_DISCRIMINANT, OPTIONAL_METADATA_DISCRIMINANT, 1];
+        let map = [MAP_METADATA_DISCRIMINANT, 1, 1];
+        // a struct or enum with a single u8 entry
+        let named = |discriminant, entry: &[u8]| {
+            let header = [discriminant, 1, 0, b'n', 1, 0, entry.len() as u8, 0];
+            [header.as_slice(), entry, &[1]].concat()
+        };
+        let cases: [Vec<u8>; 5] = [
+            // a presence byte of 2 in the second optional
+            [optionals.as_slice(), &[2, 0, 1, 5, 2]].concat(),
+            // the same key twice
+            [map.as_slice(), &[2, 0, 1, 1, 1, 2]].concat(),
+            // a map key that isn't a primitive
+            vec![MAP_METADATA_DISCRIMINANT, VEC_METADATA_DISCRIMINANT, 1],
+            // a field name that isn't utf8
+            named(STRUCT_METADATA_DISCRIMINANT, &[0xff]),
+            // a variant past the end of the enum
+            [named(ENUM_METADATA_DISCRIMINANT, b"a").as_slice(), &[1, 0]].concat(),
+        ];
+        for bytes in cases {
+            let expected = match XbfStreamReader::new(bytes.as_slice()) {
+                Err(e) => e,
+                Ok(mut reader) => reader.find_map(|x| x.err()).unwrap(),
+            };
+            let err = feed_chunks(&mut XbfDecoder::new(), &bytes, 1).unwrap_err();
+            assert_eq!(parts(&err), parts(&expected), "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn errors_are_returned_again_by_later_feeds() {
+        let config = XbfConfig::default();
+        let mut bytes = stream(&[record(1)], &config);
+        let mut value = vec![];
+        record(5).serialize_base_type(&mut value).unwrap();
+        let tags = [1, 0, 1, 0, b'0'];
+        let tag = value.windows(5).position(|x| x == tags).unwrap() + 4;
+        value[tag] = 0xff;
+        bytes.extend(&value[..tag + 1]);
+        let mut decoder = XbfDecoder::new();
+
+        let err = feed_chunks(&mut decoder, &bytes, 3).unwrap_err();
+
+        // the rest of the broken value and a whole valid one
+        let mut rest = value[tag + 1..].to_vec();
+        record(2).serialize_base_type(&mut rest).unwrap();
+        for chunk in [rest.as_slice(), &[]] {
+            let again = decoder.feed(chunk).unwrap_err();
+            assert_eq!(parts(&again), parts(&err));
+        }
+        assert_eq!(decoder.decoded(), 1);
+    }
+
+    #[test]
+    fn error_paths_go_through_nested_values() {
+        let config = XbfConfig::default();
+        let mut bytes = stream(&[record(1)], &config);
+        // the first tag is a single byte, which isn't utf8
+        let mut value = vec![];
+        record(5).serialize_base_type(&mut value).unwrap();
+        let tags = [1, 0, 1, 0, b'0'];
+        let tag = value.windows(5).position(|x| x == tags).unwrap() + 4;
+        value[tag] = 0xff;
+        bytes.extend(&value);
+
+        let err = feed_chunks(&mut XbfDecoder::new(), &bytes, 3).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::InvalidUtf8));
+        assert_eq!(err.path(), Some("root[1].tags[0]"));
+        let header_len = XbfMetadata::from(record_metadata()).encoded_len();
+        let offset = header_len + record(1).encoded_len() + tag + 1;
+        assert_eq!(err.offset(), Some(offset as u64));
+    }
+
+    #[test]
+    fn limits_are_checked_as_the_bytes_arrive() {
+        let limited = |limits| XbfConfig::new().with_limits(limits);
+        let bytes_metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into());
+
+        let config = limited(DecodeLimits::new().with_max_vec_elements(10));
+        let mut decoder =
+            XbfDecoder::for_metadata_with_config(bytes_metadata.clone().into(), &config).unwrap();
+        let err = decoder.feed(&[100, 0]).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::VecElements {
+                limit: 10,
+                actual: 100
+            })
+        ));
+        assert_eq!(err.path(), Some("root[0]"));
+
+        let config = limited(DecodeLimits::new().with_max_string_len(4));
+        let mut decoder =
+            XbfDecoder::for_metadata_with_config(XbfPrimitiveMetadata::String.into(), &config)
+                .unwrap();
+        let err = decoder.feed(&[100, 0]).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::StringLength {
+                limit: 4,
+                actual: 100
+            })
+        ));
+
+        let config = limited(DecodeLimits::new().with_max_total_bytes(8));
+        let mut decoder =
+            XbfDecoder::for_metadata_with_config(bytes_metadata.into(), &config).unwrap();
+        let err = feed_chunks(&mut decoder, &[[20, 0].as_slice(), &[1; 20]].concat(), 1);
+        assert!(matches!(
+            err.unwrap_err().root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::TotalBytes { limit: 8 })
+        ));
+
+        let config = limited(DecodeLimits::new().with_max_depth(4));
+        let header = [[VEC_METADATA_DISCRIMINANT; 5].as_slice(), &[1]].concat();
+        let err = feed_chunks(&mut XbfDecoder::with_config(&config), &header, 1).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::Depth { limit: 4 })
+        ));
+        assert_eq!(err.path(), Some("root[][][][]"));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{InvalidFrameError, InvalidMetadataError, LengthEncoding, LimitExceededError};
+use std::{error::Error, fmt::Display, io};
+
+/// A [`Result`](std::result::Result) whose error defaults to [`XbfError`].
+pub type Result<T, E = XbfError> = std::result::Result<T, E>;
+
+/// The error type returned by every serialize and deserialize function in this crate.
+///
+/// Errors raised inside a nested type are wrapped in [`XbfError::At`], which records the path of
+/// the value that failed, such as `root.players[3].name`. Errors raised while deserializing are
+/// always wrapped and also record the byte offset the reader had reached. Use
+/// [`XbfError::root_cause`] to match on what went wrong regardless of where.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::XbfError;
+/// use xbf_rs::XbfMetadata;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfStructMetadata;
+/// use xbf_rs::XbfType;
+///
+/// let metadata = XbfStructMetadata::new(
+///     "player".to_string(),
+///     vec![
+///         ("id".to_string(), XbfPrimitiveMetadata::U8.into()),
+///         ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+///     ],
+/// );
+/// // an id, then a name whose length claims two bytes but the bytes are not utf8
+/// let mut reader = std::io::Cursor::new(vec![7, 2, 0, 0xff, 0xfe]);
+///
+/// let err = XbfType::deserialize_base_type(&metadata.into(), &mut reader).unwrap_err();
+///
+/// assert!(matches!(err.root_cause(), XbfError::InvalidUtf8));
+/// assert_eq!(err.path(), Some("root.name"));
+/// assert_eq!(err.offset(), Some(5));
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum XbfError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The input ended in the middle of a value.
+    UnexpectedEof,
+    /// A string was not valid UTF-8.
+    InvalidUtf8,
+    /// A char was not a Unicode Scalar Value.
+    InvalidChar(u32),
+    /// A metadata discriminant did not correspond to any type.
+    UnknownDiscriminant(u8),
+    /// The key type of map metadata was not a primitive.
+    InvalidMapKey(u8),
+    /// The presence byte of an optional was neither `0` nor `1`.
+    InvalidPresenceByte(u8),
+    /// An enum variant index was out of range for its metadata.
+    UnknownVariant(u16),
+    /// A map contained the same key more than once.
+    DuplicateKey,
+    /// A varint length did not fit in the integer it is read into.
+    InvalidVarint,
+    /// A length was too large for the configured [`LengthEncoding`].
+    LengthOverflow {
+        len: usize,
+        encoding: LengthEncoding,
+    },
+    /// One of the configured [`DecodeLimits`](crate::DecodeLimits) was exceeded.
+    LimitExceeded(LimitExceededError),
+    /// Metadata broke one of the rules checked by
+    /// [`XbfMetadata::validate`](crate::XbfMetadata::validate).
+    InvalidMetadata(InvalidMetadataError),
+    /// A frame read by a [`FrameReader`](crate::FrameReader) was corrupt.
+    InvalidFrame(InvalidFrameError),
+    /// A value did not match its metadata.
+    TypeMismatch(String),
+    /// A message from a serde `Serialize` or `Deserialize` implementation.
+    Custom(String),
+    /// Another error, along with where it occurred.
+    At {
+        /// The path of the value that failed, starting with `root`.
+        path: String,
+        /// The number of bytes read before the error occurred, if deserializing.
+        offset: Option<u64>,
+        /// The error that occurred.
+        source: Box<XbfError>,
+    },
+}
+
+impl XbfError {
+    /// Returns the error without where it occurred.
+    pub fn root_cause(&self) -> &XbfError {
+        match self {
+            XbfError::At { source, .. } => source,
+            x => x,
+        }
+    }
+
+    /// Returns the path of the value that failed, if known.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            XbfError::At { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes read before the error occurred, if deserializing.
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            XbfError::At { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    /// Prepends a path segment, such as `.name` or `[3]`, to where the error occurred.
+    pub(crate) fn within(self, segment: impl Display) -> Self {
+        match self {
+            XbfError::At {
+                mut path,
+                offset,
+                source,
+            } => {
+                path.insert_str("root".len(), &segment.to_string());
+                XbfError::At {
+                    path,
+                    offset,
+                    source,
+                }
+            }
+            x => XbfError::At {
+                path: format!("root{segment}"),
+                offset: None,
+                source: Box::new(x),
+            },
+        }
+    }
+
+    /// Returns an equivalent error, for when one has to be returned more than once. An
+    /// [`XbfError::Io`] keeps only the kind and message of the original.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            XbfError::Io(e) => XbfError::Io(io::Error::new(e.kind(), e.to_string())),
+            XbfError::UnexpectedEof => XbfError::UnexpectedEof,
+            XbfError::InvalidUtf8 => XbfError::InvalidUtf8,
+            XbfError::InvalidChar(x) => XbfError::InvalidChar(*x),
+            XbfError::UnknownDiscriminant(x) => XbfError::UnknownDiscriminant(*x),
+            XbfError::InvalidMapKey(x) => XbfError::InvalidMapKey(*x),
+            XbfError::InvalidPresenceByte(x) => XbfError::InvalidPresenceByte(*x),
+            XbfError::UnknownVariant(x) => XbfError::UnknownVariant(*x),
+            XbfError::DuplicateKey => XbfError::DuplicateKey,
+            XbfError::InvalidVarint => XbfError::InvalidVarint,
+            XbfError::LengthOverflow { len, encoding } => XbfError::LengthOverflow {
+                len: *len,
+                encoding: *encoding,
+            },
+            XbfError::LimitExceeded(x) => XbfError::LimitExceeded(x.clone()),
+            XbfError::InvalidMetadata(x) => XbfError::InvalidMetadata(x.clone()),
+            XbfError::InvalidFrame(x) => XbfError::InvalidFrame(x.clone()),
+            XbfError::TypeMismatch(x) => XbfError::TypeMismatch(x.clone()),
+            XbfError::Custom(x) => XbfError::Custom(x.clone()),
+            XbfError::At {
+                path,
+                offset,
+                source,
+            } => XbfError::At {
+                path: path.clone(),
+                offset: *offset,
+                source: Box::new(source.duplicate()),
+            },
+        }
+    }
+
+    /// Records the number of bytes read before the error occurred.
+    pub(crate) fn at_offset(self, offset: u64) -> Self {
+        match self {
+            XbfError::At { path, source, .. } => XbfError::At {
+                path,
+                offset: Some(offset),
+                source,
+            },
+            x => XbfError::At {
+                path: "root".to_string(),
+                offset: Some(offset),
+                source: Box::new(x),
+            },
+        }
+    }
+}
+
+impl Display for XbfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XbfError::Io(e) => write!(f, "{e}"),
+            XbfError::UnexpectedEof => write!(f, "unexpected end of input"),
+            XbfError::InvalidUtf8 => write!(f, "invalid utf8"),
+            XbfError::InvalidChar(x) => write!(f, "invalid char {x:#x}"),
+            XbfError::UnknownDiscriminant(x) => write!(f, "unknown metadata discriminant {x}"),
+            XbfError::InvalidMapKey(x) => {
+                write!(f, "map key discriminant {x} is not a primitive")
+            }
+            XbfError::InvalidPresenceByte(x) => write!(f, "invalid optional presence byte {x}"),
+            XbfError::UnknownVariant(x) => write!(f, "unknown enum variant index {x}"),
+            XbfError::DuplicateKey => write!(f, "duplicate map key"),
+            XbfError::InvalidVarint => write!(f, "varint length is too large"),
+            XbfError::LengthOverflow { len, encoding } => {
+                write!(f, "length {len} does not fit in a {encoding:?} length")
+            }
+            XbfError::LimitExceeded(e) => write!(f, "{e}"),
+            XbfError::InvalidMetadata(e) => write!(f, "invalid metadata: {e}"),
+            XbfError::InvalidFrame(e) => write!(f, "invalid frame: {e}"),
+            XbfError::TypeMismatch(x) => write!(f, "{x}"),
+            XbfError::Custom(x) => write!(f, "{x}"),
+            XbfError::At {
+                path,
+                offset,
+                source,
+            } => {
+                write!(f, "{source} at {path}")?;
+                match offset {
+                    Some(offset) => write!(f, " (byte {offset})"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Error for XbfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            XbfError::Io(e) => Some(e),
+            XbfError::LimitExceeded(e) => Some(e),
+            XbfError::InvalidMetadata(e) => Some(e),
+            XbfError::InvalidFrame(e) => Some(e),
+            XbfError::At { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for XbfError {
+    fn from(value: io::Error) -> Self {
+        if value.kind() == io::ErrorKind::UnexpectedEof {
+            return XbfError::UnexpectedEof;
+        }
+        // limits enforced inside a reader have to travel through io::Error
+        if value
+            .get_ref()
+            .is_some_and(|e| e.is::<LimitExceededError>())
+        {
+            let inner = value.into_inner().expect("checked above");
+            let e = inner.downcast().expect("checked above");
+            return XbfError::LimitExceeded(*e);
+        }
+        XbfError::Io(value)
+    }
+}
+
+impl From<LimitExceededError> for XbfError {
+    fn from(value: LimitExceededError) -> Self {
+        XbfError::LimitExceeded(value)
+    }
+}
+
+impl From<InvalidMetadataError> for XbfError {
+    fn from(value: InvalidMetadataError) -> Self {
+        XbfError::InvalidMetadata(value)
+    }
+}
+
+impl From<InvalidFrameError> for XbfError {
+    fn from(value: InvalidFrameError) -> Self {
+        XbfError::InvalidFrame(value)
+    }
+}
+
+impl From<XbfError> for io::Error {
+    fn from(value: XbfError) -> Self {
+        let kind = match value.root_cause() {
+            XbfError::Io(e) => e.kind(),
+            XbfError::UnexpectedEof => io::ErrorKind::UnexpectedEof,
+            XbfError::LengthOverflow { .. } | XbfError::TypeMismatch(_) => {
+                io::ErrorKind::InvalidInput
+            }
+            _ => io::ErrorKind::InvalidData,
+        };
+        match value {
+            XbfError::Io(e) => e,
+            x => io::Error::new(kind, x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn path_segments_are_prepended() {
+        let err = XbfError::InvalidUtf8
+            .within(".name")
+            .within("[3]")
+            .within(".players")
+            .at_offset(42);
+
+        assert_eq!(err.path(), Some("root.players[3].name"));
+        assert_eq!(err.offset(), Some(42));
+        assert!(matches!(err.root_cause(), XbfError::InvalidUtf8));
+        assert_eq!(
+            err.to_string(),
+            "invalid utf8 at root.players[3].name (byte 42)"
+        );
+    }
+
+    #[test]
+    fn limits_from_io_errors_are_unwrapped() {
+        let err = XbfError::from(io::Error::from(LimitExceededError::TotalBytes { limit: 4 }));
+        assert!(matches!(
+            err,
+            XbfError::LimitExceeded(LimitExceededError::TotalBytes { limit: 4 })
+        ));
+
+        let err = XbfError::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(matches!(err, XbfError::UnexpectedEof));
+    }
+}
@@ -0,0 +1,956 @@
+use crate::{util::ReadContext, LengthEncoding, Result, XbfConfig, XbfError, XbfMetadata, XbfType};
+use byteorder::{ByteOrder, LittleEndian};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::Display,
+    io::{Read, Write},
+};
+
+/// The bytes every frame starts with.
+pub const FRAME_MAGIC: [u8; 4] = *b"XBF\x1f";
+
+/// The version of the frame format written by [`FrameWriter`].
+pub const FRAME_VERSION: u8 = 1;
+
+/// The flag set when the payload starts with the metadata of the value.
+pub const FRAME_FLAG_SCHEMA: u8 = 0b001;
+
+/// The flag set when the payload is compressed.
+pub const FRAME_FLAG_COMPRESSED: u8 = 0b010;
+
+/// The flag set when the payload is followed by a checksum.
+pub const FRAME_FLAG_CHECKSUM: u8 = 0b100;
+
+const KNOWN_FLAGS: u8 = FRAME_FLAG_SCHEMA | FRAME_FLAG_COMPRESSED | FRAME_FLAG_CHECKSUM;
+
+/// The number of bytes in a frame before the payload.
+const HEADER_LEN: usize = FRAME_MAGIC.len() + 6;
+
+const CHECKSUM_LEN: usize = 4;
+
+/// Writes values as length delimited frames, for sending XBF over byte streams such as sockets
+/// and pipes.
+///
+/// Every frame is laid out as:
+///
+/// | Field    | Size   | Contents                                                            |
+/// | -------- | ------ | ------------------------------------------------------------------- |
+/// | magic    | 4      | [`FRAME_MAGIC`]                                                     |
+/// | version  | 1      | [`FRAME_VERSION`]                                                   |
+/// | flags    | 1      | any of the `FRAME_FLAG_*` constants                                 |
+/// | length   | 4      | the number of bytes in the payload as a little endian `u32`         |
+/// | payload  | length | the metadata of the value if the frame has a schema, then the value |
+/// | checksum | 0 or 4 | the CRC-32 of the version through to the end of the payload         |
+///
+/// A frame without a schema holds a value of the metadata sent by the most recent frame that had
+/// one. If the payload is compressed, it's the uncompressed length as a little endian `u32`
+/// followed by the LZ4 block of the metadata and value.
+///
+/// When a frame is found to be corrupt, a [`FrameReader`] searches for the magic of the next frame
+/// starting just after the start of the corrupt one, so a single bad frame doesn't end the
+/// stream. Checksums make this reliable, since without them a corrupt length can go unnoticed.
+///
+/// The metadata of a value is only sent when it differs from that of the previous frame, unless
+/// [`Self::with_schema_in_every_frame`] is set. Since a corrupt frame may have been the one that
+/// changed the metadata, the reader forgets the metadata it had after skipping one, and frames
+/// without a schema fail until the next frame with one. Set
+/// [`Self::with_schema_in_every_frame`] for a reader to recover straight after a corrupt frame.
+/// Checksums are on by default.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::FrameReader;
+/// use xbf_rs::FrameWriter;
+/// use xbf_rs::XbfPrimitive;
+///
+/// let mut writer = FrameWriter::new(vec![]);
+/// writer.write_frame(&XbfPrimitive::U16(1).into()).unwrap();
+/// writer.write_frame(&XbfPrimitive::U16(2).into()).unwrap();
+/// writer.write_frame(&XbfPrimitive::String("three".to_string()).into()).unwrap();
+/// let bytes = writer.into_inner();
+///
+/// let reader = FrameReader::new(bytes.as_slice());
+/// let values = reader.collect::<Result<Vec<_>, _>>().unwrap();
+///
+/// assert_eq!(
+///     values,
+///     [
+///         XbfPrimitive::U16(1).into(),
+///         XbfPrimitive::U16(2).into(),
+///         XbfPrimitive::String("three".to_string()).into(),
+///     ]
+/// );
+/// ```
+#[derive(Debug)]
+pub struct FrameWriter<W> {
+    writer: W,
+    config: XbfConfig,
+    checksum: bool,
+    compression: bool,
+    schema_in_every_frame: bool,
+    last_schema: Option<XbfMetadata>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /// Creates a new [`FrameWriter`].
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, &XbfConfig::default())
+    }
+
+    /// Creates a new [`FrameWriter`] that serializes payloads using the given [`XbfConfig`].
+    pub fn with_config(writer: W, config: &XbfConfig) -> Self {
+        Self {
+            writer,
+            config: *config,
+            checksum: true,
+            compression: false,
+            schema_in_every_frame: false,
+            last_schema: None,
+        }
+    }
+
+    /// Sets whether frames are followed by a checksum.
+    pub fn with_checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Sets whether payloads are compressed with LZ4. A payload is only sent compressed if that
+    /// makes it smaller.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets whether every frame carries the metadata of its value, rather than only those whose
+    /// metadata changed.
+    ///
+    /// This lets a reader pick up from any frame, such as after a frame that changed the metadata
+    /// was lost to corruption.
+    pub fn with_schema_in_every_frame(mut self, schema_in_every_frame: bool) -> Self {
+        self.schema_in_every_frame = schema_in_every_frame;
+        self
+    }
+
+    /// Writes a value as a single frame.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns an error if the metadata of the value is
+    /// not valid, see [`XbfMetadata::validate`], if the value can't be serialized, or if the
+    /// payload is longer than [`u32::MAX`] bytes, in which case nothing is written.
+    pub fn write_frame(&mut self, value: &XbfType) -> Result<()> {
+        let metadata = XbfMetadata::from(value);
+        let schema = self.schema_in_every_frame || self.last_schema.as_ref() != Some(&metadata);
+        let mut flags = 0;
+        let mut payload = vec![];
+        if schema {
+            metadata.validate_with_config(&self.config)?;
+            metadata.serialize_base_metadata_with_config(&mut payload, &self.config)?;
+            flags |= FRAME_FLAG_SCHEMA;
+        }
+        value.serialize_base_type_with_config(&mut payload, &self.config)?;
+        if self.compression {
+            if let Some(compressed) = compress(&payload) {
+                payload = compressed;
+                flags |= FRAME_FLAG_COMPRESSED;
+            }
+        }
+        if self.checksum {
+            flags |= FRAME_FLAG_CHECKSUM;
+        }
+        let len = u32::try_from(payload.len()).map_err(|_| XbfError::LengthOverflow {
+            len: payload.len(),
+            encoding: LengthEncoding::U32,
+        })?;
+
+        let mut frame = Vec::with_capacity(HEADER_LEN + payload.len() + CHECKSUM_LEN);
+        frame.extend_from_slice(&FRAME_MAGIC);
+        frame.extend_from_slice(&[FRAME_VERSION, flags]);
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(&payload);
+        if self.checksum {
+            let checksum = crc32fast::hash(&frame[FRAME_MAGIC.len()..]);
+            frame.extend_from_slice(&checksum.to_le_bytes());
+        }
+        self.writer.write_all(&frame)?;
+
+        if schema {
+            self.last_schema = Some(metadata);
+        }
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+impl<W> FrameWriter<W> {
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly will corrupt the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the underlying writer, without flushing it.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads frames written by a [`FrameWriter`], see there for the layout.
+///
+/// The reader is an [`Iterator`] over the values of the frames, which ends once the input ends
+/// cleanly between two frames. A corrupt frame yields an [`XbfError::InvalidFrame`], after
+/// which the reader carries on with the next frame it can find, see [`FrameWriter`] for how this
+/// affects frames without a schema. A frame whose payload doesn't decode yields the error from
+/// decoding it, after which the reader carries on with the frame straight after it. An error
+/// from the underlying reader, such as [`std::io::ErrorKind::WouldBlock`], leaves the frame
+/// being read to be read again from its start. Byte offsets in errors are counted from the start
+/// of the input.
+///
+/// Payloads are limited to [`DecodeLimits::max_total_bytes`](crate::DecodeLimits) bytes, both as
+/// sent and once uncompressed. The reader may read one byte at a time while searching for the
+/// next frame, so wrap unbuffered readers in a [`BufReader`](std::io::BufReader).
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::FrameReader;
+/// use xbf_rs::FrameWriter;
+/// use xbf_rs::InvalidFrameError;
+/// use xbf_rs::XbfError;
+/// use xbf_rs::XbfPrimitive;
+///
+/// let mut writer = FrameWriter::new(vec![]).with_schema_in_every_frame(true);
+/// writer.write_frame(&XbfPrimitive::U16(1).into()).unwrap();
+/// writer.write_frame(&XbfPrimitive::U16(2).into()).unwrap();
+/// let mut bytes = writer.into_inner();
+/// // flip a bit in the payload of the first frame
+/// bytes[11] ^= 1;
+///
+/// let mut reader = FrameReader::new(bytes.as_slice());
+///
+/// let err = reader.next().unwrap().unwrap_err();
+/// assert!(matches!(
+///     err.root_cause(),
+///     XbfError::InvalidFrame(InvalidFrameError::ChecksumMismatch { .. })
+/// ));
+/// assert_eq!(reader.next().unwrap().unwrap(), XbfPrimitive::U16(2).into());
+/// assert!(reader.next().is_none());
+/// ```
+#[derive(Debug)]
+pub struct FrameReader<R> {
+    reader: R,
+    config: XbfConfig,
+    metadata: Option<XbfMetadata>,
+    /// Bytes of a corrupt frame given back to be searched for the start of the next frame.
+    pending: VecDeque<u8>,
+    resync: bool,
+    bytes_read: u64,
+}
+
+impl<R: Read> FrameReader<R> {
+    /// Creates a new [`FrameReader`].
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, &XbfConfig::default())
+    }
+
+    /// Creates a new [`FrameReader`] that deserializes payloads using the given [`XbfConfig`].
+    pub fn with_config(reader: R, config: &XbfConfig) -> Self {
+        Self {
+            reader,
+            config: *config,
+            metadata: None,
+            pending: VecDeque::new(),
+            resync: false,
+            bytes_read: 0,
+        }
+    }
+
+    /// Reads the next frame and returns its value, or `None` if the input has ended.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the reader, returns [`XbfError::InvalidFrame`] if the frame is
+    /// corrupt, [`XbfError::UnexpectedEof`] if the input ends in the middle of a frame, or the
+    /// error from decoding the payload. Reading again after an error from the reader retries the
+    /// same frame, and after any other error carries on with the next frame.
+    pub fn read_frame(&mut self) -> Result<Option<XbfType>> {
+        let mut frame = Vec::with_capacity(HEADER_LEN);
+        if self.resync {
+            if !self.find_magic()? {
+                return Ok(None);
+            }
+            self.resync = false;
+            frame.extend_from_slice(&FRAME_MAGIC);
+        } else {
+            self.fill(&mut frame, FRAME_MAGIC.len())?;
+            if frame.is_empty() {
+                return Ok(None);
+            }
+        }
+        let start = self.bytes_read - frame.len() as u64;
+
+        let header = self.read_header(&mut frame);
+        let payload = header.and_then(|flags| Ok((flags, self.read_payload(&mut frame, flags)?)));
+        let (flags, payload) = match payload {
+            Ok(x) => x,
+            Err(e) => {
+                if !matches!(e, XbfError::Io(_)) {
+                    self.skip_corrupt(&frame);
+                }
+                return Err(e.at_offset(start));
+            }
+        };
+
+        self.decode(flags, &payload, start + HEADER_LEN as u64)
+            .map(Some)
+    }
+
+    /// Reads the rest of the header into `frame`, returning the flags.
+    fn read_header(&mut self, frame: &mut Vec<u8>) -> Result<u8> {
+        if frame.len() < FRAME_MAGIC.len() {
+            return Err(XbfError::UnexpectedEof);
+        }
+        if frame[..] != FRAME_MAGIC {
+            return Err(InvalidFrameError::BadMagic.into());
+        }
+        self.fill(frame, HEADER_LEN - FRAME_MAGIC.len())?;
+        if frame.len() < HEADER_LEN {
+            return Err(XbfError::UnexpectedEof);
+        }
+        let version = frame[FRAME_MAGIC.len()];
+        if version != FRAME_VERSION {
+            return Err(InvalidFrameError::UnsupportedVersion(version).into());
+        }
+        let flags = frame[FRAME_MAGIC.len() + 1];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(InvalidFrameError::UnknownFlags(flags).into());
+        }
+        Ok(flags)
+    }
+
+    /// Reads the payload and checksum into `frame`, returning the payload once checked.
+    fn read_payload(&mut self, frame: &mut Vec<u8>, flags: u8) -> Result<Vec<u8>> {
+        let len = LittleEndian::read_u32(&frame[HEADER_LEN - 4..HEADER_LEN]) as usize;
+        let limit = self.config.limits().max_total_bytes();
+        if len as u64 > limit {
+            return Err(InvalidFrameError::TooLong { len, limit }.into());
+        }
+        let checksum_len = if flags & FRAME_FLAG_CHECKSUM != 0 {
+            CHECKSUM_LEN
+        } else {
+            0
+        };
+        self.fill(frame, len + checksum_len)?;
+        if frame.len() < HEADER_LEN + len + checksum_len {
+            return Err(XbfError::UnexpectedEof);
+        }
+        let payload = &frame[HEADER_LEN..HEADER_LEN + len];
+        if checksum_len != 0 {
+            let expected = LittleEndian::read_u32(&frame[HEADER_LEN + len..]);
+            let actual = crc32fast::hash(&frame[FRAME_MAGIC.len()..HEADER_LEN + len]);
+            if expected != actual {
+                return Err(InvalidFrameError::ChecksumMismatch { expected, actual }.into());
+            }
+        }
+        Ok(payload.to_vec())
+    }
+
+    /// Decodes the value of a payload starting at the given offset.
+    fn decode(&mut self, flags: u8, payload: &[u8], offset: u64) -> Result<XbfType> {
+        let payload = if flags & FRAME_FLAG_COMPRESSED != 0 {
+            decompress(payload, self.config.limits().max_total_bytes())
+                .map_err(|e| e.at_offset(offset))?
+        } else {
+            payload.to_vec()
+        };
+        let mut reader = payload.as_slice();
+        let mut context = ReadContext::new(&mut reader, &self.config);
+        let value = self
+            .decode_value(flags, &mut context)
+            .map_err(|e| e.at_offset(offset + context.bytes_read()))?;
+        if !reader.is_empty() {
+            let err = XbfError::from(InvalidFrameError::TrailingBytes(reader.len()));
+            return Err(err.at_offset(offset + (payload.len() - reader.len()) as u64));
+        }
+        Ok(value)
+    }
+
+    fn decode_value(&mut self, flags: u8, reader: &mut ReadContext<impl Read>) -> Result<XbfType> {
+        if flags & FRAME_FLAG_SCHEMA != 0 {
+            // forget the old schema first, so frames after a schema that fails to decode aren't
+            // read with the wrong one
+            self.metadata = None;
+            self.metadata = Some(XbfMetadata::deserialize_base_metadata_with_context(reader)?);
+        }
+        let metadata = self
+            .metadata
+            .as_ref()
+            .ok_or(InvalidFrameError::MissingSchema)?;
+        XbfType::deserialize_base_type_with_context(metadata, reader)
+    }
+
+    /// Appends up to `n` more bytes to `frame`, stopping early only if the input ends.
+    ///
+    /// If the reader fails, everything in `frame` is given back so the frame can be read again
+    /// from its start.
+    fn fill(&mut self, frame: &mut Vec<u8>, n: usize) -> Result<()> {
+        let from_pending = n.min(self.pending.len());
+        frame.extend(self.pending.drain(..from_pending));
+        let rest = (n - from_pending) as u64;
+        let len = frame.len();
+        let read = self.reader.by_ref().take(rest).read_to_end(frame);
+        self.bytes_read += (from_pending + frame.len() - len) as u64;
+        if let Err(e) = read {
+            self.give_back(frame);
+            frame.clear();
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Moves past everything up to and including the next [`FRAME_MAGIC`], returning whether it
+    /// was found before the input ended.
+    fn find_magic(&mut self) -> Result<bool> {
+        let mut window = [0; FRAME_MAGIC.len()];
+        let mut seen = 0;
+        let mut byte = vec![];
+        while seen < window.len() || window != FRAME_MAGIC {
+            byte.clear();
+            if let Err(e) = self.fill(&mut byte, 1) {
+                // keep what may be the start of the magic for the next search
+                let kept = seen.min(window.len());
+                self.give_back(&window[window.len() - kept..]);
+                return Err(e);
+            }
+            let Some(&byte) = byte.first() else {
+                return Ok(false);
+            };
+            window.rotate_left(1);
+            window[window.len() - 1] = byte;
+            seen += 1;
+        }
+        Ok(true)
+    }
+
+    /// Gives back bytes to be read again.
+    fn give_back(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter().rev() {
+            self.pending.push_front(byte);
+        }
+        self.bytes_read -= bytes.len() as u64;
+    }
+
+    /// Gives back all but the first byte of a corrupt frame to be searched for the start of the
+    /// next frame, and forgets the metadata in case the corrupt frame changed it.
+    fn skip_corrupt(&mut self, frame: &[u8]) {
+        self.give_back(&frame[1..]);
+        self.resync = true;
+        self.metadata = None;
+    }
+}
+
+impl<R> FrameReader<R> {
+    /// Returns the metadata sent by the most recent frame that had a schema, if any, unless a
+    /// corrupt frame was skipped since or that schema failed to decode.
+    pub fn metadata(&self) -> Option<&XbfMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// Any bytes of a corrupt frame that were read but not yet searched are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = Result<XbfType>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+/// Compresses a payload, returning `None` if that doesn't make it smaller.
+#[cfg(feature = "compression")]
+fn compress(payload: &[u8]) -> Option<Vec<u8>> {
+    let len = u32::try_from(payload.len()).ok()?;
+    let mut compressed = len.to_le_bytes().to_vec();
+    compressed.extend_from_slice(&lz4_flex::block::compress(payload));
+    (compressed.len() < payload.len()).then_some(compressed)
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress(_payload: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "compression")]
+fn decompress(payload: &[u8], limit: u64) -> Result<Vec<u8>> {
+    if payload.len() < 4 {
+        return Err(InvalidFrameError::Decompress.into());
+    }
+    let (len, block) = payload.split_at(4);
+    let len = LittleEndian::read_u32(len) as usize;
+    if len as u64 > limit {
+        return Err(InvalidFrameError::TooLong { len, limit }.into());
+    }
+    lz4_flex::block::decompress(block, len).map_err(|_| InvalidFrameError::Decompress.into())
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_payload: &[u8], _limit: u64) -> Result<Vec<u8>> {
+    Err(InvalidFrameError::CompressionUnsupported.into())
+}
+
+/// The ways a frame read by a [`FrameReader`] can be invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidFrameError {
+    /// The frame did not start with [`FRAME_MAGIC`].
+    BadMagic,
+    /// The frame was of a version this reader does not know.
+    UnsupportedVersion(u8),
+    /// The flags of the frame had bits set that this reader does not know.
+    UnknownFlags(u8),
+    /// The payload was longer than allowed by the configured limits.
+    TooLong { len: usize, limit: u64 },
+    /// The checksum sent with the frame did not match its contents.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The frame had no schema, and no frame before it had one either.
+    MissingSchema,
+    /// The payload had bytes left over after its value.
+    TrailingBytes(usize),
+    /// The payload could not be decompressed.
+    Decompress,
+    /// The payload was compressed, but the `compression` feature is not enabled.
+    CompressionUnsupported,
+}
+
+impl Display for InvalidFrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidFrameError::BadMagic => write!(f, "frame does not start with the frame magic"),
+            InvalidFrameError::UnsupportedVersion(x) => write!(f, "unsupported frame version {x}"),
+            InvalidFrameError::UnknownFlags(x) => write!(f, "unknown frame flags {x:#010b}"),
+            InvalidFrameError::TooLong { len, limit } => {
+                write!(f, "frame payload has {len} bytes, limit is {limit}")
+            }
+            InvalidFrameError::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "frame checksum is {actual:#010x}, expected {expected:#010x}"
+                )
+            }
+            InvalidFrameError::MissingSchema => {
+                write!(f, "frame has no schema and none was sent before it")
+            }
+            InvalidFrameError::TrailingBytes(x) => {
+                write!(f, "frame payload has {x} bytes left over after its value")
+            }
+            InvalidFrameError::Decompress => write!(f, "frame payload could not be decompressed"),
+            InvalidFrameError::CompressionUnsupported => {
+                write!(
+                    f,
+                    "frame payload is compressed, enable the compression feature"
+                )
+            }
+        }
+    }
+}
+
+impl Error for InvalidFrameError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        DecodeLimits, XbfPrimitive, XbfPrimitiveMetadata, XbfStruct, XbfStructMetadata, XbfVec,
+        XbfVecMetadata,
+    };
+    use std::io::Cursor;
+
+    fn point(x: i32, name: &str) -> XbfType {
+        let metadata = XbfStructMetadata::new(
+            "point".to_string(),
+            vec![
+                ("x".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+            ],
+        );
+        XbfStruct::new(
+            metadata,
+            vec![
+                XbfPrimitive::I32(x).into(),
+                XbfPrimitive::String(name.to_string()).into(),
+            ],
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn frames(writer: FrameWriter<Vec<u8>>, values: &[XbfType]) -> Vec<u8> {
+        let mut writer = writer;
+        for value in values {
+            writer.write_frame(value).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    fn read_all(bytes: &[u8]) -> Vec<Result<XbfType>> {
+        FrameReader::new(bytes).collect()
+    }
+
+    #[test]
+    fn frame_layout_is_correct() {
+        let value = XbfType::from(XbfPrimitive::U16(0x0102));
+        let bytes = frames(FrameWriter::new(vec![]).with_checksum(false), &[value]);
+
+        let mut expected = FRAME_MAGIC.to_vec();
+        expected.extend_from_slice(&[FRAME_VERSION, FRAME_FLAG_SCHEMA]);
+        expected.extend_from_slice(&3u32.to_le_bytes());
+        expected.extend_from_slice(&[XbfPrimitiveMetadata::U16 as u8, 0x02, 0x01]);
+
+        assert_eq!(bytes, expected);
+
+        let bytes = frames(
+            FrameWriter::new(vec![]),
+            &[XbfPrimitive::U16(0x0102).into()],
+        );
+        expected[5] |= FRAME_FLAG_CHECKSUM;
+        let checksum = crc32fast::hash(&expected[4..]);
+        expected.extend_from_slice(&checksum.to_le_bytes());
+
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn schema_is_only_sent_when_it_changes() {
+        let values = [point(1, "a"), point(2, "b"), XbfPrimitive::U8(3).into()];
+        let bytes = frames(FrameWriter::new(vec![]), &values);
+
+        let mut reader = FrameReader::new(bytes.as_slice());
+        let mut flags = vec![];
+        let mut offset = 0;
+        while offset < bytes.len() {
+            flags.push(bytes[offset + 5]);
+            let len = LittleEndian::read_u32(&bytes[offset + 6..offset + 10]) as usize;
+            offset += HEADER_LEN + len + CHECKSUM_LEN;
+        }
+        let schema = FRAME_FLAG_SCHEMA | FRAME_FLAG_CHECKSUM;
+        assert_eq!(flags, [schema, FRAME_FLAG_CHECKSUM, schema]);
+
+        assert_eq!(reader.next().unwrap().unwrap(), values[0]);
+        assert_eq!(reader.metadata(), Some(&XbfMetadata::from(&values[0])));
+        let rest = reader.collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(rest, values[1..]);
+    }
+
+    #[test]
+    fn reader_resyncs_after_checksum_mismatch() {
+        let values: Vec<_> = (0..5).map(|i| point(i, "name")).collect();
+        let writer = FrameWriter::new(vec![]).with_schema_in_every_frame(true);
+        let mut bytes = frames(writer, &values);
+        // corrupt the length of the second frame, so its end can't be trusted
+        let frame_len = bytes.len() / 5;
+        bytes[frame_len + 7] ^= 0x10;
+
+        let results = read_all(&bytes);
+
+        assert_eq!(results.len(), 5);
+        let err = results[1].as_ref().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidFrame(InvalidFrameError::ChecksumMismatch { .. })
+                | XbfError::UnexpectedEof
+        ));
+        assert_eq!(err.offset(), Some(frame_len as u64));
+        let ok: Vec<_> = results.into_iter().filter_map(|x| x.ok()).collect();
+        assert_eq!(ok, [&values[..1], &values[2..]].concat());
+    }
+
+    #[test]
+    fn reader_skips_garbage_between_frames() {
+        let mut bytes = frames(FrameWriter::new(vec![]), &[point(1, "a")]);
+        bytes.extend_from_slice(b"XBF garbage XB");
+        let len = bytes.len();
+        bytes.extend_from_slice(&frames(FrameWriter::new(vec![]), &[point(2, "b")]));
+
+        let mut reader = FrameReader::new(bytes.as_slice());
+
+        assert_eq!(reader.next().unwrap().unwrap(), point(1, "a"));
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidFrame(InvalidFrameError::BadMagic)
+        ));
+        assert_eq!(err.offset(), Some(len as u64 - 14));
+        assert_eq!(reader.next().unwrap().unwrap(), point(2, "b"));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn schema_change_lost_to_corruption_is_not_guessed() {
+        let values = [
+            point(1, "a"),
+            XbfPrimitive::U8(2).into(),
+            XbfPrimitive::U8(3).into(),
+        ];
+        let mut bytes = frames(FrameWriter::new(vec![]), &values);
+        // corrupt the payload of the second frame, which is the one carrying the new schema
+        let second = bytes.len() - 2 * (HEADER_LEN + 1 + CHECKSUM_LEN) - 1;
+        bytes[second + HEADER_LEN] ^= 1;
+
+        let mut reader = FrameReader::new(bytes.as_slice());
+
+        assert_eq!(reader.next().unwrap().unwrap(), values[0]);
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidFrame(InvalidFrameError::ChecksumMismatch { .. })
+        ));
+        assert_eq!(err.offset(), Some(second as u64));
+        assert_eq!(reader.metadata(), None);
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidFrame(InvalidFrameError::MissingSchema)
+        ));
+        assert!(reader.next().is_none());
+
+        let writer = FrameWriter::new(vec![]).with_schema_in_every_frame(true);
+        let mut bytes = frames(writer, &values);
+        let second = bytes.len() - 2 * (HEADER_LEN + 2 + CHECKSUM_LEN);
+        bytes[second + HEADER_LEN] ^= 1;
+
+        let results = read_all(&bytes);
+
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap(), &values[2]);
+    }
+
+    /// Hands out bytes one at a time, failing once with [`std::io::ErrorKind::WouldBlock`] just
+    /// before the byte at `fail_at`.
+    struct WouldBlockOnce<'a> {
+        bytes: &'a [u8],
+        position: usize,
+        fail_at: Option<usize>,
+    }
+
+    impl Read for WouldBlockOnce<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.fail_at == Some(self.position) {
+                self.fail_at = None;
+                return Err(std::io::ErrorKind::WouldBlock.into());
+            }
+            let Some(&byte) = self.bytes.get(self.position) else {
+                return Ok(0);
+            };
+            match buf.first_mut() {
+                Some(x) => *x = byte,
+                None => return Ok(0),
+            }
+            self.position += 1;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn reader_errors_do_not_lose_frames() {
+        let values = [point(1, "a"), point(2, "b")];
+        let mut bytes = frames(FrameWriter::new(vec![]), &values);
+        // garbage in front makes the reader search for the first frame
+        bytes.splice(..0, *b"XB!");
+
+        for fail_at in 0..bytes.len() {
+            let reader = FrameReader::new(WouldBlockOnce {
+                bytes: &bytes,
+                position: 0,
+                fail_at: Some(fail_at),
+            });
+            let mut read = vec![];
+            let mut would_block = 0;
+            for result in reader {
+                match result {
+                    Ok(x) => read.push(x),
+                    Err(e) if matches!(e.root_cause(), XbfError::Io(_)) => would_block += 1,
+                    Err(e) => assert!(
+                        matches!(
+                            e.root_cause(),
+                            XbfError::InvalidFrame(InvalidFrameError::BadMagic)
+                        ),
+                        "{e}"
+                    ),
+                }
+            }
+            assert_eq!(read, values, "failing at byte {fail_at}");
+            assert_eq!(would_block, 1, "failing at byte {fail_at}");
+        }
+    }
+
+    #[test]
+    fn truncated_frame_is_told_apart_from_end_of_input() {
+        let bytes = frames(FrameWriter::new(vec![]), &[point(1, "a"), point(2, "b")]);
+
+        let results = read_all(&bytes[..bytes.len() - 1]);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap(), &point(1, "a"));
+        assert!(matches!(
+            results[1].as_ref().unwrap_err().root_cause(),
+            XbfError::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn bad_payload_does_not_lose_the_next_frame() {
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into());
+        let value = XbfType::from(XbfVec::new_unchecked(
+            metadata,
+            vec![XbfPrimitive::U8(0).into(); 100],
+        ));
+        let bytes = frames(FrameWriter::new(vec![]), &[value, point(1, "a")]);
+
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_vec_elements(10));
+        let mut reader = FrameReader::with_config(Cursor::new(bytes), &config);
+
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::LimitExceeded(_)));
+        assert_eq!(err.offset(), Some(HEADER_LEN as u64 + 4));
+        assert_eq!(reader.next().unwrap().unwrap(), point(1, "a"));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn schema_that_fails_to_decode_is_not_replaced_by_the_last_one() {
+        let metadata = XbfStructMetadata::new(
+            "wide".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::U8.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::U8.into()),
+                ("c".to_string(), XbfPrimitiveMetadata::U8.into()),
+            ],
+        );
+        let wide =
+            XbfType::from(XbfStruct::new(metadata, vec![XbfPrimitive::U8(1).into(); 3]).unwrap());
+        let bytes = frames(
+            FrameWriter::new(vec![]),
+            &[point(1, "a"), wide.clone(), wide],
+        );
+
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_struct_fields(2));
+        let mut reader = FrameReader::with_config(bytes.as_slice(), &config);
+
+        assert_eq!(reader.next().unwrap().unwrap(), point(1, "a"));
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::LimitExceeded(_)));
+        assert_eq!(reader.metadata(), None);
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidFrame(InvalidFrameError::MissingSchema)
+        ));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let bytes = frames(FrameWriter::new(vec![]), &[point(1, "a")]);
+
+        let mut missing_schema = bytes.clone();
+        missing_schema[5] &= !FRAME_FLAG_SCHEMA;
+        let checksum_start = missing_schema.len() - CHECKSUM_LEN;
+        let checksum = crc32fast::hash(&missing_schema[4..checksum_start]);
+        missing_schema[checksum_start..].copy_from_slice(&checksum.to_le_bytes());
+
+        let mut unknown_flags = bytes.clone();
+        unknown_flags[5] |= 0x80;
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+
+        for (bytes, expected) in [
+            (missing_schema, InvalidFrameError::MissingSchema),
+            (unknown_flags, InvalidFrameError::UnknownFlags(0x85)),
+            (version, InvalidFrameError::UnsupportedVersion(2)),
+        ] {
+            let err = FrameReader::new(bytes.as_slice()).read_frame().unwrap_err();
+            assert!(
+                matches!(err.root_cause(), XbfError::InvalidFrame(x) if *x == expected),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn frame_length_limit_is_enforced() {
+        let value = XbfType::from(XbfPrimitive::Bytes(vec![0; 100]));
+        let bytes = frames(FrameWriter::new(vec![]), &[value]);
+
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_total_bytes(64));
+        let err = FrameReader::with_config(bytes.as_slice(), &config)
+            .read_frame()
+            .unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidFrame(InvalidFrameError::TooLong {
+                len: 103,
+                limit: 64
+            })
+        ));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compression_round_trip_works() {
+        let values = [
+            XbfType::from(XbfPrimitive::String("a".repeat(1000))),
+            XbfPrimitive::U8(1).into(),
+        ];
+        let writer = FrameWriter::new(vec![]).with_compression(true);
+        let bytes = frames(writer, &values);
+
+        assert_ne!(bytes[5] & FRAME_FLAG_COMPRESSED, 0);
+        assert!(bytes.len() < 1000);
+
+        let read = read_all(&bytes).into_iter().collect::<Result<Vec<_>>>();
+        assert_eq!(read.unwrap(), values);
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn compressed_frames_need_the_compression_feature() {
+        let mut bytes = FRAME_MAGIC.to_vec();
+        bytes.extend_from_slice(&[FRAME_VERSION, FRAME_FLAG_COMPRESSED]);
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+
+        let err = FrameReader::new(bytes.as_slice()).read_frame().unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidFrame(InvalidFrameError::CompressionUnsupported)
+        ));
+    }
+}
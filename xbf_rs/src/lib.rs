@@ -2,16 +2,49 @@
 //!
 //! The format itself is described [here](https://github.com/XtensibleBinaryFormat/XBF/blob/main/docs/specification.md)
 
+#[cfg(feature = "tokio")]
+mod async_io;
 mod base_metadata;
 mod base_type;
-mod prelude;
+mod base_type_ref;
+mod config;
+mod decoder;
+mod error;
+mod frame;
+mod native;
+pub mod prelude;
+mod stream;
 mod util;
+mod xbf_array;
+mod xbf_enum;
+mod xbf_map;
+mod xbf_optional;
 mod xbf_primitive;
+#[cfg(feature = "serde")]
+mod xbf_serde;
 mod xbf_struct;
 mod xbf_vec;
 
+#[cfg(feature = "tokio")]
+pub use async_io::*;
 pub use base_metadata::*;
 pub use base_type::*;
+pub use base_type_ref::*;
+pub use config::*;
+pub use decoder::*;
+pub use error::*;
+pub use frame::*;
+pub use native::*;
+pub use stream::*;
+pub use xbf_array::*;
+pub use xbf_enum::*;
+pub use xbf_map::*;
+pub use xbf_optional::*;
 pub use xbf_primitive::*;
+#[cfg(feature = "serde")]
+pub use xbf_serde::*;
 pub use xbf_struct::*;
 pub use xbf_vec::*;
+
+#[cfg(feature = "derive")]
+pub use xbf_rs_derive::Xbf;
@@ -0,0 +1,524 @@
+use crate::{
+    bf16, f16,
+    util::{
+        preallocate, read_bytes, read_string, write_bytes, write_len, write_string, ReadContext,
+    },
+    Result, XbfConfig, XbfError, XbfMetadata, XbfNumeric, XbfNumericVec, XbfOptional,
+    XbfOptionalMetadata, XbfPrimitive, XbfPrimitiveMetadata, XbfType, XbfVec, XbfVecMetadata, I256,
+    U256,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::{
+    io::{Read, Write},
+    ops::{Deref, DerefMut},
+};
+
+/// A native Rust type with a fixed XBF shape.
+///
+/// Types implementing this trait can be converted to and from [`XbfType`], and can also be
+/// serialized and deserialized directly without building an [`XbfType`] first. It is implemented
+/// for `bool`, the integer and float types, `char`, `String`, [`XbfBytes`], and for `Vec<T>` and
+/// `Option<T>` of any type implementing it. Structs can implement it with `#[derive(Xbf)]` when the
+/// `derive` feature is enabled.
+///
+/// A `Vec<u8>` is a vector of [`U8`](XbfPrimitiveMetadata::U8) like any other `Vec<T>`. Wrap it
+/// in [`XbfBytes`] to send it as [`Bytes`](XbfPrimitiveMetadata::Bytes) instead.
+///
+/// Implementing the trait by hand only needs [`Self::xbf_metadata`], [`Self::to_xbf_type`],
+/// [`Self::from_xbf_type`] and [`Self::serialize_xbf_with_config`]. Deserializing then goes
+/// through [`XbfType`] and [`Self::from_xbf_type`].
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::Xbf;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfVecMetadata;
+///
+/// let scores = vec![1u32, 2, 3];
+/// let mut writer = vec![];
+/// scores.serialize_xbf(&mut writer).unwrap();
+///
+/// assert_eq!(
+///     Vec::<u32>::xbf_metadata(),
+///     XbfVecMetadata::new(XbfPrimitiveMetadata::U32.into()).into()
+/// );
+/// assert_eq!(scores.to_xbf_type().encoded_len(), writer.len());
+/// assert_eq!(
+///     Vec::<u32>::deserialize_xbf(&mut std::io::Cursor::new(writer)).unwrap(),
+///     scores
+/// );
+/// ```
+pub trait Xbf: Sized {
+    /// Returns the metadata describing every value of this type.
+    fn xbf_metadata() -> XbfMetadata;
+
+    /// Converts the value to the equivalent [`XbfType`].
+    fn to_xbf_type(&self) -> XbfType;
+
+    /// Converts an [`XbfType`] back to a value of this type.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XbfError::TypeMismatch`] if the value is not described by
+    /// [`Self::xbf_metadata`].
+    fn from_xbf_type(value: XbfType) -> Result<Self>;
+
+    /// Serialize the value directly, writing the same bytes as serializing
+    /// [`Self::to_xbf_type`] would.
+    fn serialize_xbf(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_xbf_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize the value directly using the given [`XbfConfig`].
+    fn serialize_xbf_with_config(&self, writer: &mut impl Write, config: &XbfConfig) -> Result<()>;
+
+    /// Deserialize a value directly, without building an [`XbfType`] first.
+    fn deserialize_xbf(reader: &mut impl Read) -> Result<Self> {
+        Self::deserialize_xbf_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize a value directly using the given [`XbfConfig`].
+    fn deserialize_xbf_with_config(reader: &mut impl Read, config: &XbfConfig) -> Result<Self> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_xbf_with_context(reader)
+        })
+    }
+
+    /// Called by the other deserialize functions. The derive and the implementations in this
+    /// crate override it to skip building an [`XbfType`], which needs the crate's private reader.
+    #[doc(hidden)]
+    fn deserialize_xbf_with_context(reader: &mut ReadContext<impl Read>) -> Result<Self> {
+        XbfType::deserialize_base_type_with_context(&Self::xbf_metadata(), reader)
+            .and_then(Self::from_xbf_type)
+    }
+}
+
+fn type_mismatch(expected: &XbfMetadata, actual: &XbfType) -> XbfError {
+    let actual = XbfMetadata::from(actual);
+    XbfError::TypeMismatch(format!("expected {expected:?}, found {actual:?}"))
+}
+
+macro_rules! impl_Xbf_for_primitive {
+    ($ty:ty, $xbf_type:tt) => {
+        impl Xbf for $ty {
+            fn xbf_metadata() -> XbfMetadata {
+                XbfPrimitiveMetadata::$xbf_type.into()
+            }
+
+            fn to_xbf_type(&self) -> XbfType {
+                XbfPrimitive::$xbf_type(*self).into()
+            }
+
+            fn from_xbf_type(value: XbfType) -> Result<Self> {
+                match value {
+                    XbfType::Primitive(XbfPrimitive::$xbf_type(x)) => Ok(x),
+                    x => Err(type_mismatch(&Self::xbf_metadata(), &x)),
+                }
+            }
+
+            fn serialize_xbf_with_config(
+                &self,
+                writer: &mut impl Write,
+                config: &XbfConfig,
+            ) -> Result<()> {
+                XbfPrimitive::$xbf_type(*self).serialize_primitive_type_with_config(writer, config)
+            }
+
+            fn deserialize_xbf_with_context(reader: &mut ReadContext<impl Read>) -> Result<Self> {
+                match XbfPrimitive::deserialize_primitive_type_with_context(
+                    &XbfPrimitiveMetadata::$xbf_type,
+                    reader,
+                )? {
+                    XbfPrimitive::$xbf_type(x) => Ok(x),
+                    _ => unreachable!("deserialized the requested primitive"),
+                }
+            }
+        }
+    };
+}
+
+impl_Xbf_for_primitive!(bool, Bool);
+impl_Xbf_for_primitive!(u8, U8);
+impl_Xbf_for_primitive!(u16, U16);
+impl_Xbf_for_primitive!(u32, U32);
+impl_Xbf_for_primitive!(u64, U64);
+impl_Xbf_for_primitive!(u128, U128);
+impl_Xbf_for_primitive!(U256, U256);
+impl_Xbf_for_primitive!(i8, I8);
+impl_Xbf_for_primitive!(i16, I16);
+impl_Xbf_for_primitive!(i32, I32);
+impl_Xbf_for_primitive!(i64, I64);
+impl_Xbf_for_primitive!(i128, I128);
+impl_Xbf_for_primitive!(I256, I256);
+impl_Xbf_for_primitive!(f32, F32);
+impl_Xbf_for_primitive!(f64, F64);
+impl_Xbf_for_primitive!(char, Char);
+impl_Xbf_for_primitive!(f16, F16);
+impl_Xbf_for_primitive!(bf16, BF16);
+
+impl Xbf for String {
+    fn xbf_metadata() -> XbfMetadata {
+        XbfPrimitiveMetadata::String.into()
+    }
+
+    fn to_xbf_type(&self) -> XbfType {
+        XbfPrimitive::String(self.clone()).into()
+    }
+
+    fn from_xbf_type(value: XbfType) -> Result<Self> {
+        match value {
+            XbfType::Primitive(XbfPrimitive::String(x)) => Ok(x),
+            x => Err(type_mismatch(&Self::xbf_metadata(), &x)),
+        }
+    }
+
+    fn serialize_xbf_with_config(&self, writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+        write_string(self, writer, config)
+    }
+
+    fn deserialize_xbf_with_context(reader: &mut ReadContext<impl Read>) -> Result<Self> {
+        read_string(reader)
+    }
+}
+
+/// A byte buffer sent as the [`Bytes`](XbfPrimitiveMetadata::Bytes) primitive.
+///
+/// A `Vec<u8>` implements [`Xbf`] like every other `Vec<T>`, as a vector of
+/// [`U8`](XbfPrimitiveMetadata::U8), and that is what a derived struct gets for a `Vec<u8>` field.
+/// Use `XbfBytes` for the field instead to match a schema that uses `Bytes`, which is also what
+/// converting a `Vec<u8>` to an [`XbfPrimitive`] gives. Like `serde_bytes`, it is a wrapper rather
+/// than an attribute so that it works inside other types too, such as `Option<XbfBytes>`. Both
+/// are written as a length followed by the bytes, so only the metadata differs.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::Xbf;
+/// use xbf_rs::XbfBytes;
+/// use xbf_rs::XbfPrimitiveMetadata;
+///
+/// assert_eq!(XbfBytes::xbf_metadata(), XbfPrimitiveMetadata::Bytes.into());
+///
+/// let bytes = XbfBytes::from(vec![1, 2, 3]);
+/// let mut writer = vec![];
+/// bytes.serialize_xbf(&mut writer).unwrap();
+///
+/// assert_eq!(writer, [3, 0, 1, 2, 3]);
+/// assert_eq!(*XbfBytes::deserialize_xbf(&mut writer.as_slice()).unwrap(), [1, 2, 3]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct XbfBytes(pub Vec<u8>);
+
+impl Deref for XbfBytes {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for XbfBytes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<Vec<u8>> for XbfBytes {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<XbfBytes> for Vec<u8> {
+    fn from(value: XbfBytes) -> Self {
+        value.0
+    }
+}
+
+impl Xbf for XbfBytes {
+    fn xbf_metadata() -> XbfMetadata {
+        XbfPrimitiveMetadata::Bytes.into()
+    }
+
+    fn to_xbf_type(&self) -> XbfType {
+        XbfPrimitive::Bytes(self.0.clone()).into()
+    }
+
+    fn from_xbf_type(value: XbfType) -> Result<Self> {
+        match value {
+            XbfType::Primitive(XbfPrimitive::Bytes(x)) => Ok(Self(x)),
+            x => Err(type_mismatch(&Self::xbf_metadata(), &x)),
+        }
+    }
+
+    fn serialize_xbf_with_config(&self, writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+        write_bytes(self, writer, config)
+    }
+
+    fn deserialize_xbf_with_context(reader: &mut ReadContext<impl Read>) -> Result<Self> {
+        read_bytes(reader).map(Self)
+    }
+}
+
+impl<T: Xbf> Xbf for Vec<T> {
+    fn xbf_metadata() -> XbfMetadata {
+        XbfVecMetadata::new(T::xbf_metadata()).into()
+    }
+
+    fn to_xbf_type(&self) -> XbfType {
+        XbfVec::new_unchecked(
+            XbfVecMetadata::new(T::xbf_metadata()),
+            self.iter().map(T::to_xbf_type).collect(),
+        )
+        .into()
+    }
+
+    fn from_xbf_type(value: XbfType) -> Result<Self> {
+        match value {
+            XbfType::Vec(x) if *x.metadata.inner_type == T::xbf_metadata() => x
+                .into_iter()
+                .enumerate()
+                .map(|(i, x)| T::from_xbf_type(x).map_err(|e| e.within(format_args!("[{i}]"))))
+                .collect(),
+            x => Err(type_mismatch(&Self::xbf_metadata(), &x)),
+        }
+    }
+
+    fn serialize_xbf_with_config(&self, writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+        write_len(self.len(), writer, config)?;
+        self.iter().enumerate().try_for_each(|(i, x)| {
+            x.serialize_xbf_with_config(writer, config)
+                .map_err(|e| e.within(format_args!("[{i}]")))
+        })
+    }
+
+    fn deserialize_xbf_with_context(reader: &mut ReadContext<impl Read>) -> Result<Self> {
+        reader.nested(|reader| {
+            let len = reader.read_elements_len()?;
+            let mut elements = preallocate(len);
+            for i in 0..len {
+                elements.push(
+                    T::deserialize_xbf_with_context(reader)
+                        .map_err(|e| e.within(format_args!("[{i}]")))?,
+                );
+            }
+            Ok(elements)
+        })
+    }
+}
+
+impl<T: XbfNumeric> Xbf for XbfNumericVec<T> {
+    fn xbf_metadata() -> XbfMetadata {
+        XbfVecMetadata::new(T::METADATA.into()).into()
+    }
+
+    fn to_xbf_type(&self) -> XbfType {
+        self.into()
+    }
+
+    fn from_xbf_type(value: XbfType) -> Result<Self> {
+        match value {
+            XbfType::Vec(x) => Self::try_from(x).map_err(|e| XbfError::TypeMismatch(e.to_string())),
+            x => Err(type_mismatch(&Self::xbf_metadata(), &x)),
+        }
+    }
+
+    fn serialize_xbf_with_config(&self, writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+        self.serialize_numeric_vec_type_with_config(writer, config)
+    }
+
+    fn deserialize_xbf_with_context(reader: &mut ReadContext<impl Read>) -> Result<Self> {
+        Self::deserialize_numeric_vec_type_with_context(reader)
+    }
+}
+
+impl<T: Xbf> Xbf for Option<T> {
+    fn xbf_metadata() -> XbfMetadata {
+        XbfOptionalMetadata::new(T::xbf_metadata()).into()
+    }
+
+    fn to_xbf_type(&self) -> XbfType {
+        XbfOptional::new_unchecked(
+            XbfOptionalMetadata::new(T::xbf_metadata()),
+            self.as_ref().map(T::to_xbf_type),
+        )
+        .into()
+    }
+
+    fn from_xbf_type(value: XbfType) -> Result<Self> {
+        match value {
+            XbfType::Optional(x) if *x.metadata.inner_type == T::xbf_metadata() => {
+                x.into_value().map(T::from_xbf_type).transpose()
+            }
+            x => Err(type_mismatch(&Self::xbf_metadata(), &x)),
+        }
+    }
+
+    fn serialize_xbf_with_config(&self, writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+        match self {
+            Some(x) => {
+                writer.write_u8(1)?;
+                x.serialize_xbf_with_config(writer, config)
+            }
+            None => Ok(writer.write_u8(0)?),
+        }
+    }
+
+    fn deserialize_xbf_with_context(reader: &mut ReadContext<impl Read>) -> Result<Self> {
+        reader.nested(|reader| match reader.read_u8()? {
+            0 => Ok(None),
+            1 => T::deserialize_xbf_with_context(reader).map(Some),
+            x => Err(XbfError::InvalidPresenceByte(x)),
+        })
+    }
+}
+
+/// Items used by the code generated by `#[derive(Xbf)]`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::util::ReadContext;
+    use crate::{Result, Xbf, XbfError, XbfMetadata, XbfType};
+    use std::fmt::Display;
+
+    pub fn within(error: XbfError, segment: impl Display) -> XbfError {
+        error.within(segment)
+    }
+
+    /// Returns the field values of a struct, checking it is described by the given metadata.
+    pub fn struct_fields(
+        value: XbfType,
+        expected: &XbfMetadata,
+    ) -> Result<std::vec::IntoIter<XbfType>> {
+        match value {
+            XbfType::Struct(x) if XbfMetadata::from(&x.metadata) == *expected => Ok(x
+                .into_fields()
+                .into_iter()
+                .map(|(_, x)| x)
+                .collect::<Vec<_>>()
+                .into_iter()),
+            x => Err(super::type_mismatch(expected, &x)),
+        }
+    }
+
+    /// Converts the next field value of a struct returned by [`struct_fields`].
+    pub fn field<T: Xbf>(fields: &mut std::vec::IntoIter<XbfType>, name: &str) -> Result<T> {
+        let value = fields
+            .next()
+            .expect("the metadata has already been checked");
+        T::from_xbf_type(value).map_err(|e| e.within(format_args!(".{name}")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn round_trip<T: Xbf + PartialEq + std::fmt::Debug>(value: T) {
+        let mut direct = vec![];
+        value.serialize_xbf(&mut direct).unwrap();
+        let mut dynamic = vec![];
+        value
+            .to_xbf_type()
+            .serialize_base_type(&mut dynamic)
+            .unwrap();
+        assert_eq!(direct, dynamic);
+
+        let deserialized = T::deserialize_xbf(&mut Cursor::new(&direct)).unwrap();
+        assert_eq!(deserialized, value);
+        let converted = T::from_xbf_type(value.to_xbf_type()).unwrap();
+        assert_eq!(converted, value);
+        assert_eq!(XbfMetadata::from(&value.to_xbf_type()), T::xbf_metadata());
+    }
+
+    #[test]
+    fn native_types_round_trip() {
+        round_trip(true);
+        round_trip(42u8);
+        round_trip(-42i64);
+        round_trip(u128::MAX);
+        round_trip(4.2f32);
+        round_trip(bf16::from_f32(4.2));
+        round_trip('🦀');
+        round_trip("hello".to_string());
+        round_trip(vec![vec![1u16, 2], vec![]]);
+        round_trip(vec![Some("a".to_string()), None]);
+        round_trip(XbfNumericVec::new(vec![0.5f64, -1.0]));
+        round_trip(XbfBytes(vec![1, 2, 3]));
+        round_trip(Some(XbfBytes(vec![])));
+    }
+
+    #[test]
+    fn bytes_differ_from_a_vec_of_u8_only_in_metadata() {
+        let mut bytes = vec![];
+        XbfBytes(vec![1, 2]).serialize_xbf(&mut bytes).unwrap();
+        let mut vec = vec![];
+        vec![1u8, 2].serialize_xbf(&mut vec).unwrap();
+
+        assert_eq!(bytes, vec);
+        assert_eq!(XbfBytes::xbf_metadata(), XbfPrimitiveMetadata::Bytes.into());
+        let primitive = XbfPrimitive::from(vec![1u8, 2]);
+        assert_eq!(
+            XbfBytes::xbf_metadata(),
+            XbfMetadata::from(&XbfType::from(primitive))
+        );
+    }
+
+    /// Implemented by hand with only the required methods.
+    #[derive(Debug, PartialEq)]
+    struct Meters(f32);
+
+    impl Xbf for Meters {
+        fn xbf_metadata() -> XbfMetadata {
+            f32::xbf_metadata()
+        }
+
+        fn to_xbf_type(&self) -> XbfType {
+            self.0.to_xbf_type()
+        }
+
+        fn from_xbf_type(value: XbfType) -> Result<Self> {
+            f32::from_xbf_type(value).map(Meters)
+        }
+
+        fn serialize_xbf_with_config(
+            &self,
+            writer: &mut impl Write,
+            config: &XbfConfig,
+        ) -> Result<()> {
+            self.0.serialize_xbf_with_config(writer, config)
+        }
+    }
+
+    #[test]
+    fn hand_written_implementations_round_trip() {
+        round_trip(Meters(1.5));
+        round_trip(vec![Some(Meters(2.0)), None]);
+    }
+
+    #[test]
+    fn from_xbf_type_checks_the_type() {
+        let err = u8::from_xbf_type(XbfPrimitive::U16(1).into()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected Primitive(U8), found Primitive(U16)"
+        );
+
+        let err = Vec::<u8>::from_xbf_type(vec![1u16].to_xbf_type()).unwrap_err();
+        assert!(matches!(err, XbfError::TypeMismatch(_)));
+
+        let err = Option::<u8>::from_xbf_type(XbfPrimitive::U8(1).into()).unwrap_err();
+        assert!(matches!(err, XbfError::TypeMismatch(_)));
+    }
+
+    #[test]
+    fn deserialize_xbf_records_path() {
+        let mut reader = Cursor::new([2, 0, 1, 0, b'a', 1, 0, 0xff]);
+
+        let err = Vec::<String>::deserialize_xbf(&mut reader).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::InvalidUtf8));
+        assert_eq!(err.path(), Some("root[1]"));
+    }
+}
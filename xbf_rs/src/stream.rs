@@ -0,0 +1,428 @@
+use crate::{util::ReadContext, Result, XbfConfig, XbfError, XbfMetadata, XbfType};
+use std::{
+    io::{self, Read, Write},
+    iter::FusedIterator,
+};
+
+/// Writes a stream of values that all share the same metadata.
+///
+/// The metadata is written once as a header when the writer is created, and every value after it
+/// is written on its own with nothing in between, so the stream can be read back with an
+/// [`XbfStreamReader`] using the same [`XbfConfig`].
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfStreamReader;
+/// use xbf_rs::XbfStreamWriter;
+///
+/// let mut writer = XbfStreamWriter::new(vec![], XbfPrimitiveMetadata::U16.into()).unwrap();
+/// writer.write(&XbfPrimitive::U16(1).into()).unwrap();
+/// writer.write(&XbfPrimitive::U16(2).into()).unwrap();
+/// assert!(writer.write(&XbfPrimitive::I32(3).into()).is_err());
+/// let bytes = writer.into_inner();
+///
+/// assert_eq!(bytes, [XbfPrimitiveMetadata::U16 as u8, 1, 0, 2, 0]);
+///
+/// let reader = XbfStreamReader::new(bytes.as_slice()).unwrap();
+/// let values = reader.collect::<Result<Vec<_>, _>>().unwrap();
+///
+/// assert_eq!(values, [XbfPrimitive::U16(1).into(), XbfPrimitive::U16(2).into()]);
+/// ```
+#[derive(Debug)]
+pub struct XbfStreamWriter<W> {
+    writer: W,
+    metadata: XbfMetadata,
+    config: XbfConfig,
+    written: u64,
+}
+
+impl<W: Write> XbfStreamWriter<W> {
+    /// Creates a new [`XbfStreamWriter`], writing the metadata as the header of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns an error if the metadata is not valid, see
+    /// [`XbfMetadata::validate`], or if its values take up no bytes at all, since a reader could
+    /// not tell how many of them there are.
+    pub fn new(writer: W, metadata: XbfMetadata) -> Result<Self> {
+        Self::with_config(writer, metadata, &XbfConfig::default())
+    }
+
+    /// Creates a new [`XbfStreamWriter`] that writes using the given [`XbfConfig`].
+    ///
+    /// See [`Self::new`] for more details.
+    pub fn with_config(mut writer: W, metadata: XbfMetadata, config: &XbfConfig) -> Result<Self> {
+        metadata.validate_with_config(config)?;
+        check_not_empty(&metadata)?;
+        metadata.serialize_base_metadata_with_config(&mut writer, config)?;
+        Ok(Self {
+            writer,
+            metadata,
+            config: *config,
+            written: 0,
+        })
+    }
+
+    /// Appends a value to the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XbfError::TypeMismatch`] without writing anything if the value does not match
+    /// the metadata of the stream. Errors from serializing the value are wrapped in
+    /// [`XbfError::At`] with the index of the value, such as `root[3].name`, and may leave part
+    /// of the value written.
+    pub fn write(&mut self, value: &XbfType) -> Result<()> {
+        let index = self.written;
+        let actual = XbfMetadata::from(value);
+        if actual != self.metadata {
+            return Err(XbfError::TypeMismatch(format!(
+                "expected {:?}, found {actual:?}",
+                self.metadata
+            ))
+            .within(format_args!("[{index}]")));
+        }
+        value
+            .serialize_base_type_with_config(&mut self.writer, &self.config)
+            .map_err(|e| e.within(format_args!("[{index}]")))?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+impl<W> XbfStreamWriter<W> {
+    /// Returns the metadata every value in the stream must match.
+    pub fn metadata(&self) -> &XbfMetadata {
+        &self.metadata
+    }
+
+    /// Returns the number of values written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly will corrupt the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the underlying writer, without flushing it.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads a stream of values written by an [`XbfStreamWriter`].
+///
+/// The header is read when the reader is created, after which the reader is an [`Iterator`]
+/// over the values in the stream. It ends once the input ends cleanly between two values. If the
+/// input ends in the middle of a value it instead yields [`XbfError::UnexpectedEof`], and after
+/// any error the iterator ends, since there's no telling where the next value would start.
+///
+/// The [`DecodeLimits`](crate::DecodeLimits) of the config apply to the header and to every value
+/// separately, so a stream may hold any number of values. Byte offsets in errors are counted from
+/// the start of the stream.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::XbfError;
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfStreamReader;
+///
+/// // a u16 header followed by one value and then half of another
+/// let bytes = [XbfPrimitiveMetadata::U16 as u8, 1, 0, 2];
+/// let mut reader = XbfStreamReader::new(bytes.as_slice()).unwrap();
+///
+/// assert_eq!(reader.metadata(), &XbfPrimitiveMetadata::U16.into());
+/// assert_eq!(reader.next().unwrap().unwrap(), XbfPrimitive::U16(1).into());
+///
+/// let err = reader.next().unwrap().unwrap_err();
+/// assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+/// assert_eq!(err.path(), Some("root[1]"));
+/// assert_eq!(err.offset(), Some(4));
+///
+/// assert!(reader.next().is_none());
+/// ```
+#[derive(Debug)]
+pub struct XbfStreamReader<R> {
+    reader: R,
+    metadata: XbfMetadata,
+    config: XbfConfig,
+    read: u64,
+    bytes_read: u64,
+    done: bool,
+}
+
+impl<R: Read> XbfStreamReader<R> {
+    /// Creates a new [`XbfStreamReader`], reading the header of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the reader, returns an error if the header is not valid
+    /// metadata, including [`XbfError::UnexpectedEof`] if the input is empty, or if its values
+    /// take up no bytes at all.
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_config(reader, &XbfConfig::default())
+    }
+
+    /// Creates a new [`XbfStreamReader`] that reads using the given [`XbfConfig`].
+    ///
+    /// See [`Self::new`] for more details.
+    pub fn with_config(mut reader: R, config: &XbfConfig) -> Result<Self> {
+        let mut context = ReadContext::new(&mut reader, config);
+        let metadata = XbfMetadata::deserialize_base_metadata_with_context(&mut context)
+            .and_then(|x| check_not_empty(&x).map(|_| x))
+            .map_err(|e| e.at_offset(context.bytes_read()))?;
+        let bytes_read = context.bytes_read();
+        Ok(Self {
+            reader,
+            metadata,
+            config: *config,
+            read: 0,
+            bytes_read,
+            done: false,
+        })
+    }
+
+    /// Reads the first byte of the next value, or returns `None` if the input has ended.
+    fn read_first_byte(&mut self) -> Result<Option<u8>> {
+        let mut byte = 0;
+        loop {
+            match self.reader.read(std::slice::from_mut(&mut byte)) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte)),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn read_value(&mut self) -> Option<Result<XbfType>> {
+        let start = self.bytes_read;
+        let first = match self.read_first_byte() {
+            Ok(Some(x)) => x,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e.at_offset(start))),
+        };
+        let first = [first];
+        let mut context = ReadContext::new(first.as_slice().chain(&mut self.reader), &self.config);
+        let result = XbfType::deserialize_base_type_with_context(&self.metadata, &mut context)
+            .map_err(|e| e.at_offset(start + context.bytes_read()));
+        self.bytes_read = start + context.bytes_read();
+        Some(result)
+    }
+}
+
+impl<R> XbfStreamReader<R> {
+    /// Returns the metadata read from the header of the stream.
+    pub fn metadata(&self) -> &XbfMetadata {
+        &self.metadata
+    }
+
+    /// Returns the number of values read so far.
+    pub fn read(&self) -> u64 {
+        self.read
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for XbfStreamReader<R> {
+    type Item = Result<XbfType>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let index = self.read;
+        let result = self.read_value();
+        match &result {
+            Some(Ok(_)) => self.read += 1,
+            Some(Err(_)) | None => self.done = true,
+        }
+        result.map(|x| x.map_err(|e| e.within(format_args!("[{index}]"))))
+    }
+}
+
+impl<R: Read> FusedIterator for XbfStreamReader<R> {}
+
+/// Fails if values of the metadata are always zero bytes long, since a stream of them would have
+/// no way of saying how many there are.
+pub(crate) fn check_not_empty(metadata: &XbfMetadata) -> Result<()> {
+    if metadata.fixed_size() == Some(0) {
+        return Err(XbfError::TypeMismatch(format!(
+            "values of {metadata:?} are empty, so can't be counted in a stream"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        DecodeLimits, LengthEncoding, LimitExceededError, XbfPrimitive, XbfPrimitiveMetadata,
+        XbfStruct, XbfStructMetadata, XbfVec, XbfVecMetadata,
+    };
+
+    fn point_metadata() -> XbfStructMetadata {
+        XbfStructMetadata::new(
+            "point".to_string(),
+            vec![
+                ("x".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+            ],
+        )
+    }
+
+    fn point(x: i32, name: &str) -> XbfType {
+        XbfStruct::new(
+            point_metadata(),
+            vec![
+                XbfPrimitive::I32(x).into(),
+                XbfPrimitive::String(name.to_string()).into(),
+            ],
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn stream_round_trip_works() {
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::Varint);
+        let values: Vec<_> = (0..100).map(|i| point(i, &i.to_string())).collect();
+
+        let mut writer =
+            XbfStreamWriter::with_config(vec![], point_metadata().into(), &config).unwrap();
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        assert_eq!(writer.written(), 100);
+        let bytes = writer.into_inner();
+
+        let mut header = vec![];
+        XbfMetadata::from(point_metadata())
+            .serialize_base_metadata_with_config(&mut header, &config)
+            .unwrap();
+        assert!(bytes.starts_with(&header));
+
+        let mut reader = XbfStreamReader::with_config(bytes.as_slice(), &config).unwrap();
+        assert_eq!(reader.metadata(), &point_metadata().into());
+        let read = reader.by_ref().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(read, values);
+        assert_eq!(reader.read(), 100);
+    }
+
+    #[test]
+    fn empty_stream_has_no_values() {
+        let bytes = XbfStreamWriter::new(vec![], point_metadata().into())
+            .unwrap()
+            .into_inner();
+
+        let mut reader = XbfStreamReader::new(bytes.as_slice()).unwrap();
+        assert!(reader.next().is_none());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn missing_header_fails() {
+        let err = XbfStreamReader::new([].as_slice()).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(err.offset(), Some(0));
+    }
+
+    #[test]
+    fn truncated_value_is_told_apart_from_end_of_stream() {
+        let mut writer = XbfStreamWriter::new(vec![], point_metadata().into()).unwrap();
+        writer.write(&point(1, "a")).unwrap();
+        writer.write(&point(2, "bcd")).unwrap();
+        let mut bytes = writer.into_inner();
+        let len = bytes.len();
+        bytes.pop();
+
+        let mut reader = XbfStreamReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), point(1, "a"));
+
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(err.path(), Some("root[1].name"));
+        assert_eq!(err.offset(), Some(len as u64 - 1));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn limits_apply_to_each_value() {
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_total_bytes(64));
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into());
+        let value = XbfType::from(XbfVec::new_unchecked(
+            metadata.clone(),
+            vec![XbfPrimitive::U8(0).into(); 40],
+        ));
+
+        let mut writer = XbfStreamWriter::with_config(vec![], metadata.into(), &config).unwrap();
+        for _ in 0..10 {
+            writer.write(&value).unwrap();
+        }
+        let bytes = writer.into_inner();
+
+        let reader = XbfStreamReader::with_config(bytes.as_slice(), &config).unwrap();
+        assert_eq!(reader.map(Result::unwrap).count(), 10);
+
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_total_bytes(32));
+        let mut reader = XbfStreamReader::with_config(bytes.as_slice(), &config).unwrap();
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::TotalBytes { limit: 32 })
+        ));
+    }
+
+    #[test]
+    fn mismatched_value_is_not_written() {
+        let mut writer = XbfStreamWriter::new(vec![], point_metadata().into()).unwrap();
+        let header_len = writer.get_ref().len();
+
+        let err = writer.write(&XbfPrimitive::I32(1).into()).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+        assert_eq!(err.path(), Some("root[0]"));
+        assert_eq!(writer.get_ref().len(), header_len);
+        assert_eq!(writer.written(), 0);
+    }
+
+    #[test]
+    fn empty_values_are_rejected() {
+        let metadata = XbfMetadata::from(XbfStructMetadata::new("unit".to_string(), vec![]));
+
+        assert!(XbfStreamWriter::new(vec![], metadata.clone()).is_err());
+
+        let mut bytes = vec![];
+        metadata.serialize_base_metadata(&mut bytes).unwrap();
+        let err = XbfStreamReader::new(bytes.as_slice()).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+    }
+}
@@ -1,26 +1,460 @@
+use crate::{DecodeLimits, LengthEncoding, LimitExceededError, Result, XbfConfig, XbfError};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{self, Write};
+use std::io::{self, Read, Seek, Write};
 
-pub fn write_string(string: &str, writer: &mut impl Write) -> io::Result<()> {
-    writer.write_u16::<LittleEndian>(string.len() as u16)?;
-    writer.write_all(string.as_bytes())
+/// The maximum number of bytes an unsigned LEB128 encoded `u64` may take up.
+pub const MAX_VARINT_LEN: usize = 10;
+
+/// The most elements or bytes allocated up front for a length read from the wire.
+///
+/// Anything longer grows as the data actually arrives, so a peer lying about a length can't make
+/// us allocate more than it sends.
+pub const MAX_PREALLOCATION: usize = 4096;
+
+/// State shared by every deserialize function for the duration of a single call.
+///
+/// Wraps the reader to count the bytes read and tracks how deeply types are nested, so that the
+/// [`DecodeLimits`] in the config can be enforced across recursive calls.
+pub struct ReadContext<R> {
+    reader: R,
+    config: XbfConfig,
+    depth: usize,
+    bytes_read: u64,
+    /// A copy of every byte read while [`Self::record`] runs.
+    recording: Option<Vec<u8>>,
+}
+
+impl<R: Read> ReadContext<R> {
+    pub fn new(reader: R, config: &XbfConfig) -> Self {
+        Self {
+            reader,
+            config: *config,
+            depth: 0,
+            bytes_read: 0,
+            recording: None,
+        }
+    }
+
+    pub fn config(&self) -> &XbfConfig {
+        &self.config
+    }
+
+    pub fn limits(&self) -> &DecodeLimits {
+        self.config.limits()
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Runs a whole deserialize call, recording the byte offset in any error it returns.
+    pub fn decode<T>(
+        reader: R,
+        config: &XbfConfig,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let mut context = Self::new(reader, config);
+        f(&mut context).map_err(|e| e.at_offset(context.bytes_read))
+    }
+
+    /// Runs `f` one nesting level deeper, failing if that exceeds the depth limit.
+    pub fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let limit = self.limits().max_depth();
+        if self.depth >= limit {
+            return Err(LimitExceededError::Depth { limit }.into());
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Runs `f`, returning the bytes it read along with its result.
+    pub fn record<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<(T, Vec<u8>)> {
+        let outer = self.recording.replace(vec![]);
+        let result = f(self);
+        let bytes = std::mem::replace(&mut self.recording, outer).unwrap_or_default();
+        if let Some(outer) = &mut self.recording {
+            outer.extend_from_slice(&bytes);
+        }
+        Ok((result?, bytes))
+    }
+
+    /// Reads the length of a vector, map or array, checking it against the element limit.
+    pub fn read_elements_len(&mut self) -> Result<usize> {
+        let actual = read_len(self)?;
+        let limit = self.limits().max_vec_elements();
+        if actual > limit {
+            return Err(LimitExceededError::VecElements { limit, actual }.into());
+        }
+        Ok(actual)
+    }
+
+    /// Reads the length of a string or bytes, checking it against the string length limit.
+    pub fn read_bytes_len(&mut self) -> Result<usize> {
+        let actual = read_len(self)?;
+        let limit = self.limits().max_string_len();
+        if actual > limit {
+            return Err(LimitExceededError::StringLength { limit, actual }.into());
+        }
+        Ok(actual)
+    }
+
+    /// Reads the number of fields in a struct or variants in an enum, checking it against the
+    /// field limit.
+    pub fn read_fields_len(&mut self) -> Result<usize> {
+        let actual = read_len(self)?;
+        let limit = self.limits().max_struct_fields();
+        if actual > limit {
+            return Err(LimitExceededError::StructFields { limit, actual }.into());
+        }
+        Ok(actual)
+    }
+}
+
+impl<R: Read> Read for ReadContext<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let limit = self.limits().max_total_bytes();
+        let remaining = limit - self.bytes_read;
+        if remaining == 0 && !buf.is_empty() {
+            return Err(LimitExceededError::TotalBytes { limit }.into());
+        }
+        let max = usize::try_from(remaining).map_or(buf.len(), |x| x.min(buf.len()));
+        let read = self.reader.read(&mut buf[..max])?;
+        self.bytes_read += read as u64;
+        if let Some(recording) = &mut self.recording {
+            recording.extend_from_slice(&buf[..read]);
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Skip> ReadContext<R> {
+    /// Moves past `n` bytes without returning them, counting them towards the total byte limit.
+    pub fn skip(&mut self, n: u64) -> Result<()> {
+        let limit = self.limits().max_total_bytes();
+        if n > limit - self.bytes_read {
+            return Err(LimitExceededError::TotalBytes { limit }.into());
+        }
+        self.reader.skip(n)?;
+        self.bytes_read += n;
+        Ok(())
+    }
+}
+
+impl<'a> ReadContext<&'a [u8]> {
+    /// Like [`Self::decode`], but reads from a slice and advances it past the bytes read, so that
+    /// the result may borrow from it.
+    pub fn decode_slice<T>(
+        input: &mut &'a [u8],
+        config: &XbfConfig,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let mut context = Self::new(*input, config);
+        let result = f(&mut context).map_err(|e| e.at_offset(context.bytes_read));
+        *input = context.reader;
+        result
+    }
+
+    /// Like [`read_bytes`], but borrows the bytes from the input rather than copying them.
+    pub fn read_borrowed_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.read_bytes_len()?;
+        let limit = self.limits().max_total_bytes();
+        if len as u64 > limit - self.bytes_read {
+            return Err(LimitExceededError::TotalBytes { limit }.into());
+        }
+        if len > self.reader.len() {
+            return Err(XbfError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.reader.split_at(len);
+        self.reader = rest;
+        self.bytes_read += len as u64;
+        Ok(bytes)
+    }
+
+    /// Like [`read_string`], but borrows the string from the input rather than copying it.
+    pub fn read_borrowed_str(&mut self) -> Result<&'a str> {
+        let bytes = self.read_borrowed_bytes()?;
+        std::str::from_utf8(bytes).map_err(|_| XbfError::InvalidUtf8)
+    }
+}
+
+/// A reader that can move past bytes without returning them.
+pub trait Skip: Read {
+    /// Moves past exactly `n` bytes, failing with [`io::ErrorKind::UnexpectedEof`] if the input
+    /// ends first.
+    fn skip(&mut self, n: u64) -> io::Result<()>;
+}
+
+/// Skips by reading bytes and throwing them away.
+pub struct ReadSkip<R>(pub R);
+
+impl<R: Read> Read for ReadSkip<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Read> Skip for ReadSkip<R> {
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut self.0.by_ref().take(n), &mut io::sink())?;
+        if skipped != n {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+/// Skips by seeking past bytes.
+pub struct SeekSkip<R>(pub R);
+
+impl<R: Read> Read for SeekSkip<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Read + Seek> Skip for SeekSkip<R> {
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+        // seeking past the end isn't an error, so read the last byte to make sure it's there
+        let offset = i64::try_from(n - 1).map_err(|_| io::ErrorKind::UnexpectedEof)?;
+        self.0.seek_relative(offset)?;
+        self.0.read_exact(&mut [0])
+    }
+}
+
+/// Creates a vector for `len` elements without trusting `len` for the allocation size.
+pub fn preallocate<T>(len: usize) -> Vec<T> {
+    Vec::with_capacity(len.min(MAX_PREALLOCATION))
+}
+
+pub fn write_len(len: usize, writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+    let encoding = config.length_encoding();
+    let overflow = |_| XbfError::LengthOverflow { len, encoding };
+    match encoding {
+        LengthEncoding::U16 => {
+            writer.write_u16::<LittleEndian>(u16::try_from(len).map_err(overflow)?)?
+        }
+        LengthEncoding::U32 => {
+            writer.write_u32::<LittleEndian>(u32::try_from(len).map_err(overflow)?)?
+        }
+        LengthEncoding::Varint => write_varint(len as u64, writer)?,
+    }
+    Ok(())
+}
+
+/// Returns the number of bytes [`write_len`] writes for `len`.
+pub fn len_encoded_len(len: usize, config: &XbfConfig) -> usize {
+    match config.length_encoding() {
+        LengthEncoding::U16 => 2,
+        LengthEncoding::U32 => 4,
+        LengthEncoding::Varint => (64 - (len as u64 | 1).leading_zeros() as usize).div_ceil(7),
+    }
+}
+
+pub fn read_len(reader: &mut ReadContext<impl Read>) -> Result<usize> {
+    match reader.config().length_encoding() {
+        LengthEncoding::U16 => Ok(reader.read_u16::<LittleEndian>().map(usize::from)?),
+        LengthEncoding::U32 => Ok(reader.read_u32::<LittleEndian>().map(|x| x as usize)?),
+        LengthEncoding::Varint => {
+            usize::try_from(read_varint(reader)?).map_err(|_| XbfError::InvalidVarint)
+        }
+    }
+}
+
+fn write_varint(mut value: u64, writer: &mut impl Write) -> io::Result<()> {
+    let mut buf = [0; MAX_VARINT_LEN];
+    let mut i = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[i] = byte;
+            return writer.write_all(&buf[..=i]);
+        }
+        buf[i] = byte | 0x80;
+        i += 1;
+    }
 }
 
-pub fn read_string(reader: &mut impl io::Read) -> io::Result<String> {
-    let len = reader.read_u16::<LittleEndian>()?;
-    let mut buf = vec![0; len as usize];
-    reader.read_exact(&mut buf)?;
-    String::from_utf8(buf).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid utf8"))
+fn read_varint(reader: &mut impl Read) -> Result<u64> {
+    let mut value = 0u64;
+    for i in 0..MAX_VARINT_LEN {
+        let byte = reader.read_u8()?;
+        let bits = u64::from(byte & 0x7f);
+        if i == MAX_VARINT_LEN - 1 && bits > 1 {
+            break;
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(XbfError::InvalidVarint)
 }
 
-pub fn write_bytes(bytes: &[u8], writer: &mut impl Write) -> io::Result<()> {
-    writer.write_u16::<LittleEndian>(bytes.len() as u16)?;
-    writer.write_all(bytes)
+pub fn write_string(string: &str, writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+    write_bytes(string.as_bytes(), writer, config)
 }
 
-pub fn read_bytes(reader: &mut impl io::Read) -> io::Result<Vec<u8>> {
-    let len = reader.read_u16::<LittleEndian>()?;
-    let mut buf = vec![0; len as usize];
-    reader.read_exact(&mut buf)?;
+pub fn read_string(reader: &mut ReadContext<impl Read>) -> Result<String> {
+    let buf = read_bytes(reader)?;
+    String::from_utf8(buf).map_err(|_| XbfError::InvalidUtf8)
+}
+
+pub fn write_bytes(bytes: &[u8], writer: &mut impl Write, config: &XbfConfig) -> Result<()> {
+    write_len(bytes.len(), writer, config)?;
+    Ok(writer.write_all(bytes)?)
+}
+
+/// Returns the number of bytes [`write_bytes`] and [`write_string`] write for `len` bytes.
+pub fn bytes_encoded_len(len: usize, config: &XbfConfig) -> usize {
+    len_encoded_len(len, config) + len
+}
+
+pub fn read_bytes(reader: &mut ReadContext<impl Read>) -> Result<Vec<u8>> {
+    let len = reader.read_bytes_len()?;
+    let mut buf = preallocate(len);
+    reader.by_ref().take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(XbfError::UnexpectedEof);
+    }
     Ok(buf)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn varint_config() -> XbfConfig {
+        XbfConfig::new().with_length_encoding(LengthEncoding::Varint)
+    }
+
+    #[test]
+    fn write_len_checks_u16_overflow() {
+        let mut writer = vec![];
+        write_len(u16::MAX as usize, &mut writer, &XbfConfig::default()).unwrap();
+        assert_eq!(writer, u16::MAX.to_le_bytes());
+
+        let err = write_len(u16::MAX as usize + 1, &mut writer, &XbfConfig::default()).unwrap_err();
+        assert!(matches!(err, XbfError::LengthOverflow { len: 65536, .. }));
+    }
+
+    #[test]
+    fn len_encoded_len_matches_write_len() {
+        for config in [
+            XbfConfig::default(),
+            XbfConfig::new().with_length_encoding(LengthEncoding::U32),
+            varint_config(),
+        ] {
+            for len in [0, 1, 127, 128, 16_383, 16_384, u16::MAX as usize] {
+                let mut writer = vec![];
+                write_len(len, &mut writer, &config).unwrap();
+                assert_eq!(len_encoded_len(len, &config), writer.len());
+            }
+        }
+        assert_eq!(
+            len_encoded_len(usize::MAX, &varint_config()),
+            MAX_VARINT_LEN
+        );
+    }
+
+    #[test]
+    fn u32_len_serde_works() {
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::U32);
+        let mut writer = vec![];
+
+        write_len(100_000, &mut writer, &config).unwrap();
+
+        assert_eq!(writer, 100_000u32.to_le_bytes());
+        assert_eq!(
+            read_len(&mut ReadContext::new(Cursor::new(writer), &config)).unwrap(),
+            100_000
+        );
+    }
+
+    #[test]
+    fn varint_len_serde_works() {
+        for (len, expected) in [
+            (0usize, vec![0x00]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (300, vec![0xac, 0x02]),
+            (70_000, vec![0xf0, 0xa2, 0x04]),
+        ] {
+            let mut writer = vec![];
+            write_len(len, &mut writer, &varint_config()).unwrap();
+            assert_eq!(writer, expected);
+            assert_eq!(
+                read_len(&mut ReadContext::new(Cursor::new(writer), &varint_config())).unwrap(),
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn total_bytes_limit_is_enforced() {
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_total_bytes(4));
+        let mut reader = ReadContext::new(Cursor::new([0; 8]), &config);
+
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 0);
+        let err = reader.read_u8().unwrap_err();
+        assert_eq!(
+            err.get_ref().unwrap().downcast_ref::<LimitExceededError>(),
+            Some(&LimitExceededError::TotalBytes { limit: 4 })
+        );
+    }
+
+    #[test]
+    fn string_length_limit_is_enforced() {
+        let mut writer = vec![];
+        write_bytes(&[1, 2, 3], &mut writer, &XbfConfig::default()).unwrap();
+
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_string_len(2));
+        let err = read_bytes(&mut ReadContext::new(Cursor::new(writer), &config)).unwrap_err();
+        assert!(matches!(
+            err,
+            XbfError::LimitExceeded(LimitExceededError::StringLength {
+                limit: 2,
+                actual: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn lying_bytes_length_fails() {
+        let config = XbfConfig::new()
+            .with_length_encoding(LengthEncoding::U32)
+            .with_limits(DecodeLimits::unlimited());
+        let mut data = u32::MAX.to_le_bytes().to_vec();
+        data.extend_from_slice(&[1, 2, 3]);
+
+        let err = read_bytes(&mut ReadContext::new(Cursor::new(data), &config)).unwrap_err();
+        assert!(matches!(err, XbfError::UnexpectedEof));
+    }
+
+    #[test]
+    fn varint_max_value_round_trips() {
+        let mut writer = vec![];
+        write_varint(u64::MAX, &mut writer).unwrap();
+        assert_eq!(writer.len(), MAX_VARINT_LEN);
+        assert_eq!(read_varint(&mut Cursor::new(writer)).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn varint_too_long_fails() {
+        let mut data = vec![0xff; MAX_VARINT_LEN - 1];
+        data.push(0x02);
+        let err = read_varint(&mut Cursor::new(data)).unwrap_err();
+        assert!(matches!(err, XbfError::InvalidVarint));
+
+        let data = vec![0x80; MAX_VARINT_LEN + 1];
+        let err = read_varint(&mut Cursor::new(data)).unwrap_err();
+        assert!(matches!(err, XbfError::InvalidVarint));
+    }
+}
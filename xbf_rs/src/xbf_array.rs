@@ -0,0 +1,7 @@
+//! A fixed-length array as defined by the XBF specification.
+
+mod array;
+mod array_metadata;
+
+pub use array::*;
+pub use array_metadata::*;
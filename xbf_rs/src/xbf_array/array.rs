@@ -0,0 +1,354 @@
+use crate::{
+    util::{preallocate, ReadContext},
+    Result, XbfArrayMetadata, XbfConfig, XbfError, XbfType, XbfTypeUpcast,
+};
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{Read, Write},
+};
+
+/// A fixed-length array type as defined by the XBF specification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XbfArray {
+    pub(crate) metadata: XbfArrayMetadata,
+    elements: Vec<XbfType>,
+}
+
+impl XbfArray {
+    /// Tries to create a new array based on the supplied metadata.
+    ///
+    /// # Errors
+    ///
+    /// If the number of elements is not the length specified in the metadata, or all elements are
+    /// not the same XBF type as what's specified in the metadata, returns an
+    /// [`ArrayElementsError`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArray;
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 3);
+    /// let position = XbfArray::new(
+    ///     metadata.clone(),
+    ///     vec![
+    ///         XbfPrimitive::F32(1.0).into(),
+    ///         XbfPrimitive::F32(2.0).into(),
+    ///         XbfPrimitive::F32(3.0).into(),
+    ///     ],
+    /// );
+    ///
+    /// assert!(position.is_ok());
+    ///
+    /// let too_short = XbfArray::new(metadata, vec![XbfPrimitive::F32(1.0).into()]);
+    ///
+    /// assert!(too_short.is_err());
+    /// ```
+    pub fn new(
+        metadata: XbfArrayMetadata,
+        elements: Vec<XbfType>,
+    ) -> Result<Self, ArrayElementsError> {
+        if elements.len() != metadata.len() {
+            return Err(ArrayElementsError::LengthMismatch {
+                expected: metadata.len(),
+                actual: elements.len(),
+            });
+        }
+        let all_same_type = elements.iter().all(|x| *metadata.inner_type == x.into());
+        if !all_same_type {
+            return Err(ArrayElementsError::NotHomogenous);
+        }
+        Ok(Self { metadata, elements })
+    }
+
+    /// Creates a new array with the supplied metadata and elements without checking the length or
+    /// homogeneity of the elements.
+    ///
+    /// An array whose number of elements does not match its metadata will fail to serialize.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArray;
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 1);
+    /// let array = XbfArray::new_unchecked(metadata, vec![XbfPrimitive::F32(1.0).into()]);
+    /// ```
+    pub fn new_unchecked(metadata: XbfArrayMetadata, elements: Vec<XbfType>) -> Self {
+        Self { metadata, elements }
+    }
+
+    /// Serialize an array as defined by the XBF specification.
+    ///
+    /// Only the elements are written, the length of the array is carried by its metadata.
+    ///
+    /// This function **does not** write out the metadata of the type. If you want to write out the
+    /// metadata, convert this type to a [`XbfArrayMetadata`] and call
+    /// [`XbfArrayMetadata::serialize_array_metadata`].
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns
+    /// [`XbfError::TypeMismatch`](crate::XbfError::TypeMismatch) if the number of elements does not
+    /// match the length in the metadata. Nothing is written in this case.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArray;
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let array = XbfArray::new(
+    ///     XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 2),
+    ///     vec![XbfPrimitive::U8(4).into(), XbfPrimitive::U8(2).into()],
+    /// )
+    /// .unwrap();
+    /// let mut writer = vec![];
+    /// array.serialize_array_type(&mut writer).unwrap();
+    ///
+    /// assert_eq!(writer, [4, 2]);
+    /// ```
+    pub fn serialize_array_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_array_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize an array using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_array_type`] for more details.
+    pub fn serialize_array_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        if self.elements.len() != self.metadata.len() {
+            return Err(XbfError::TypeMismatch(
+                ArrayElementsError::LengthMismatch {
+                    expected: self.metadata.len(),
+                    actual: self.elements.len(),
+                }
+                .to_string(),
+            ));
+        }
+        self.elements.iter().enumerate().try_for_each(|(i, e)| {
+            e.serialize_base_type_with_config(writer, config)
+                .map_err(|e| e.within(format_args!("[{i}]")))
+        })
+    }
+
+    /// Deserialize an array as defined by the XBF specification.
+    ///
+    /// Exactly as many elements as the length in the metadata are read.
+    ///
+    /// This function **does not** read the metadata of the type from the reader. It is
+    /// expected that to call this function the metadata for a type is already known, be
+    /// that from reading it from the reader with
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata)
+    /// or having it in some other manner.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArray;
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 2);
+    /// let mut reader = std::io::Cursor::new(vec![4, 2]);
+    ///
+    /// let array = XbfArray::deserialize_array_type(&metadata, &mut reader).unwrap();
+    /// ```
+    pub fn deserialize_array_type(
+        metadata: &XbfArrayMetadata,
+        reader: &mut impl Read,
+    ) -> Result<XbfArray> {
+        Self::deserialize_array_type_with_config(metadata, reader, &XbfConfig::default())
+    }
+
+    /// Deserialize an array using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_array_type`] for more details.
+    pub fn deserialize_array_type_with_config(
+        metadata: &XbfArrayMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfArray> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_array_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_array_type_with_context(
+        metadata: &XbfArrayMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfArray> {
+        reader.nested(|reader| {
+            let inner_type = &metadata.inner_type;
+            let mut elements = preallocate(metadata.len());
+            for i in 0..metadata.len() {
+                elements.push(
+                    XbfType::deserialize_base_type_with_context(inner_type, reader)
+                        .map_err(|e| e.within(format_args!("[{i}]")))?,
+                );
+            }
+            Ok(XbfArray::new_unchecked(metadata.clone(), elements))
+        })
+    }
+
+    /// Returns the metadata of the array.
+    ///
+    /// Getting the metadata returns an owned [`XbfArrayMetadata`], which only
+    /// clones the reference counted pointers it holds.
+    pub fn get_metadata(&self) -> XbfArrayMetadata {
+        self.metadata.clone()
+    }
+
+    /// Returns the number of bytes [`Self::serialize_array_type`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_array_type_with_config`] would write using
+    /// the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        self.elements
+            .iter()
+            .map(|x| x.encoded_len_with_config(config))
+            .sum()
+    }
+}
+
+impl XbfTypeUpcast for XbfArray {}
+
+/// Error type for [`XbfArray`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArrayElementsError {
+    /// The number of elements was not the length specified in the metadata.
+    LengthMismatch { expected: usize, actual: usize },
+    /// Not all elements were the same type as the inner type in the metadata.
+    NotHomogenous,
+}
+
+impl Display for ArrayElementsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArrayElementsError::LengthMismatch { expected, actual } => {
+                write!(f, "array has {actual} elements, expected {expected}")
+            }
+            ArrayElementsError::NotHomogenous => {
+                write!(f, "array elements do not match the inner type")
+            }
+        }
+    }
+}
+
+impl Error for ArrayElementsError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{XbfMetadata, XbfPrimitive, XbfPrimitiveMetadata};
+    use std::io::Cursor;
+
+    #[test]
+    fn array_new_fails_with_wrong_length() {
+        let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 2);
+        let err = XbfArray::new(metadata, vec![XbfPrimitive::U8(1).into()]).unwrap_err();
+        assert_eq!(
+            err,
+            ArrayElementsError::LengthMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    fn array_new_fails_with_not_homogenous_data() {
+        let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 2);
+        let err = XbfArray::new(
+            metadata,
+            vec![XbfPrimitive::U8(1).into(), XbfPrimitive::I8(1).into()],
+        )
+        .unwrap_err();
+        assert_eq!(err, ArrayElementsError::NotHomogenous);
+    }
+
+    #[test]
+    fn array_serde_works() {
+        let row_metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 2);
+        let matrix_metadata = XbfArrayMetadata::new(row_metadata.clone().into(), 2);
+        let row = |a: f32, b: f32| {
+            XbfArray::new(
+                row_metadata.clone(),
+                vec![XbfPrimitive::F32(a).into(), XbfPrimitive::F32(b).into()],
+            )
+            .unwrap()
+        };
+        let matrix = XbfArray::new(
+            matrix_metadata.clone(),
+            vec![row(1.0, 0.0).into(), row(0.0, 1.0).into()],
+        )
+        .unwrap();
+        let mut writer = vec![];
+
+        matrix.serialize_array_type(&mut writer).unwrap();
+
+        let expected = [1.0f32, 0.0, 0.0, 1.0]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+        let deserialized =
+            XbfType::deserialize_base_type(&XbfMetadata::Array(matrix_metadata), &mut reader)
+                .unwrap();
+
+        assert_eq!(deserialized, matrix.into());
+    }
+
+    #[test]
+    fn serialize_wrong_length_fails() {
+        let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 3);
+        let array = XbfArray::new_unchecked(metadata, vec![XbfPrimitive::U8(1).into()]);
+        let mut writer = vec![];
+
+        let err = array.serialize_array_type(&mut writer).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn deserialize_short_input_fails() {
+        let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 3);
+        let mut reader = Cursor::new(vec![1, 2]);
+
+        let err = XbfArray::deserialize_array_type(&metadata, &mut reader).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+    }
+
+    #[test]
+    fn upcast_works() {
+        let array = XbfArray::new(
+            XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 1),
+            vec![XbfPrimitive::U8(1).into()],
+        )
+        .unwrap();
+        let array_ref = &array;
+
+        assert_eq!(XbfType::Array(array.clone()), array_ref.to_base_type());
+        assert_eq!(XbfType::Array(array.clone()), array.into_base_type());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    util::{len_encoded_len, write_len, ReadContext},
+    Result, XbfArray, XbfConfig, XbfMetadata, XbfMetadataUpcast, MAP_METADATA_DISCRIMINANT,
+};
+use byteorder::WriteBytesExt;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+/// The metadata discriminant for an Array type.
+///
+/// This is the same for all arrays regardless of their contents or length. It's value should
+/// always be equal to the discriminant value of the map type plus one.
+pub const ARRAY_METADATA_DISCRIMINANT: u8 = MAP_METADATA_DISCRIMINANT + 1;
+
+/// Metadata for an Array type.
+///
+/// Unlike a vector, the number of elements in an array is part of its metadata and is not sent
+/// with every value. Internally the inner type is stored on the heap to avoid having a recursive,
+/// infinitely sized type on the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XbfArrayMetadata {
+    pub(crate) inner_type: Arc<XbfMetadata>,
+    pub(crate) len: usize,
+}
+
+impl XbfArrayMetadata {
+    /// Creates a new Array metadata with the given inner type and number of elements.
+    ///
+    /// This will cause a heap allocation with the moved inner type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// // an array of three floats, such as a position in 3D space
+    /// let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 3);
+    /// ```
+    pub fn new(inner_type: XbfMetadata, len: usize) -> Self {
+        Self {
+            inner_type: Arc::new(inner_type),
+            len,
+        }
+    }
+
+    /// Returns the number of elements every array with this metadata contains.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if arrays with this metadata contain no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Serialize Array metadata as defined by the XBF specification.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns
+    /// [`XbfError::LengthOverflow`](crate::XbfError::LengthOverflow) if the length does not fit in
+    /// an unsigned 16-bit integer. Use [`Self::serialize_array_metadata_with_config`] to describe
+    /// longer arrays.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::ARRAY_METADATA_DISCRIMINANT;
+    ///
+    /// let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 3);
+    /// let mut writer = Vec::new();
+    /// metadata.serialize_array_metadata(&mut writer).unwrap();
+    ///
+    /// assert_eq!(
+    ///     writer,
+    ///     [ARRAY_METADATA_DISCRIMINANT, 3, 0, XbfPrimitiveMetadata::F32 as u8]
+    /// );
+    /// ```
+    pub fn serialize_array_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_array_metadata_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize Array metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_array_metadata`] for more details.
+    pub fn serialize_array_metadata_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        writer.write_u8(ARRAY_METADATA_DISCRIMINANT)?;
+        write_len(self.len, writer, config)?;
+        self.inner_type
+            .serialize_base_metadata_with_config(writer, config)
+            .map_err(|e| e.within("[]"))
+    }
+
+    /// Deserialize Array metadata as defined by the XBF specification.
+    ///
+    /// This method assumes that you know for a fact you are about to receive Array metadata. If
+    /// you do not know what sort of metadata you are receiving, use
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfArrayMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let data = vec![3, 0, XbfPrimitiveMetadata::F32 as u8];
+    /// let mut reader = std::io::Cursor::new(data);
+    ///
+    /// let metadata = XbfArrayMetadata::deserialize_array_metadata(&mut reader).unwrap();
+    ///
+    /// assert_eq!(metadata, XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 3));
+    /// ```
+    pub fn deserialize_array_metadata(reader: &mut impl Read) -> Result<XbfArrayMetadata> {
+        Self::deserialize_array_metadata_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize Array metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_array_metadata`] for more details.
+    pub fn deserialize_array_metadata_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfArrayMetadata> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_array_metadata_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_array_metadata_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfArrayMetadata> {
+        reader.nested(|reader| {
+            let len = reader.read_elements_len()?;
+            let inner_type = XbfMetadata::deserialize_base_metadata_with_context(reader)
+                .map_err(|e| e.within("[]"))?;
+            Ok(XbfArrayMetadata::new(inner_type, len))
+        })
+    }
+
+    /// Returns the number of bytes [`Self::serialize_array_metadata`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_array_metadata_with_config`] would write using the given
+    /// [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        1 + len_encoded_len(self.len, config) + self.inner_type.encoded_len_with_config(config)
+    }
+}
+
+impl XbfMetadataUpcast for XbfArrayMetadata {}
+
+impl From<&XbfArray> for XbfArrayMetadata {
+    fn from(value: &XbfArray) -> Self {
+        value.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        LengthEncoding, XbfError, XbfPrimitiveMetadata, XbfVecMetadata, VEC_METADATA_DISCRIMINANT,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn array_metadata_serde_works() {
+        let matrix_metadata = XbfArrayMetadata::new(
+            XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 4).into(),
+            4,
+        );
+        let array_of_vec_metadata = XbfArrayMetadata::new(
+            XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into()).into(),
+            300,
+        );
+        let mut writer = vec![];
+
+        matrix_metadata
+            .serialize_array_metadata(&mut writer)
+            .unwrap();
+        array_of_vec_metadata
+            .serialize_array_metadata(&mut writer)
+            .unwrap();
+
+        let mut expected = vec![ARRAY_METADATA_DISCRIMINANT];
+        expected.extend_from_slice(&4u16.to_le_bytes());
+        expected.push(ARRAY_METADATA_DISCRIMINANT);
+        expected.extend_from_slice(&4u16.to_le_bytes());
+        expected.push(XbfPrimitiveMetadata::F32 as u8);
+        expected.push(ARRAY_METADATA_DISCRIMINANT);
+        expected.extend_from_slice(&300u16.to_le_bytes());
+        expected.push(VEC_METADATA_DISCRIMINANT);
+        expected.push(XbfPrimitiveMetadata::U8 as u8);
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+
+        let deserialized_matrix = XbfMetadata::deserialize_base_metadata(&mut reader).unwrap();
+        let deserialized_array_of_vec =
+            XbfMetadata::deserialize_base_metadata(&mut reader).unwrap();
+
+        assert_eq!(deserialized_matrix, matrix_metadata.into());
+        assert_eq!(deserialized_array_of_vec, array_of_vec_metadata.into());
+    }
+
+    #[test]
+    fn long_array_metadata_needs_wider_lengths() {
+        let metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::U8.into(), 70_000);
+        let mut writer = vec![];
+        let err = metadata.serialize_array_metadata(&mut writer).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LengthOverflow { len: 70_000, .. }
+        ));
+
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::Varint);
+        let mut writer = vec![];
+        metadata
+            .serialize_array_metadata_with_config(&mut writer, &config)
+            .unwrap();
+        let mut reader = Cursor::new(writer);
+        let deserialized =
+            XbfMetadata::deserialize_base_metadata_with_config(&mut reader, &config).unwrap();
+
+        assert_eq!(deserialized, metadata.into());
+    }
+
+    #[test]
+    fn arrays_of_different_lengths_are_different_types() {
+        let three = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 3);
+        let four = XbfArrayMetadata::new(XbfPrimitiveMetadata::F32.into(), 4);
+
+        assert_ne!(three, four);
+    }
+
+    #[test]
+    fn upcast_works() {
+        let array_metadata = XbfArrayMetadata::new(XbfPrimitiveMetadata::I32.into(), 2);
+        let array_metadata_ref = &array_metadata;
+
+        assert_eq!(
+            XbfMetadata::Array(array_metadata.clone()),
+            array_metadata_ref.to_base_metadata()
+        );
+        assert_eq!(
+            XbfMetadata::Array(array_metadata.clone()),
+            array_metadata.into_base_metadata()
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! A tagged union as defined by the XBF specification.
+
+mod enum_metadata;
+mod enum_value;
+
+pub use enum_metadata::*;
+pub use enum_value::*;
@@ -0,0 +1,379 @@
+use crate::{
+    base_metadata::{check_name_lens, check_names, check_variant_count},
+    util::{
+        bytes_encoded_len, len_encoded_len, preallocate, read_string, write_len, write_string,
+        ReadContext,
+    },
+    InvalidMetadataError, Result, XbfConfig, XbfEnumValue, XbfMetadata, XbfMetadataUpcast,
+    OPTIONAL_METADATA_DISCRIMINANT,
+};
+use byteorder::WriteBytesExt;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+/// The metadata discriminant for an Enum type.
+///
+/// This is the same for all enums regardless of their contents. It's value should always be
+/// equal to the discriminant value of the optional type plus one.
+pub const ENUM_METADATA_DISCRIMINANT: u8 = OPTIONAL_METADATA_DISCRIMINANT + 1;
+
+/// Metadata for an Enum type.
+///
+/// An enum has a name and a list of named variants, each of which carries a payload of the given
+/// type. A value of the enum is exactly one of these variants.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct XbfEnumMetadata {
+    name: Arc<str>,
+    pub(crate) variants: Arc<[(String, XbfMetadata)]>,
+}
+
+impl XbfEnumMetadata {
+    /// Creates a new [`XbfEnumMetadata`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "command".to_string(),
+    ///     vec![
+    ///         ("move".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("say".to_string(), XbfPrimitiveMetadata::String.into()),
+    ///     ],
+    /// );
+    /// ```
+    pub fn new(name: String, variants: Vec<(String, XbfMetadata)>) -> Self {
+        Self {
+            name: name.into(),
+            variants: variants.into(),
+        }
+    }
+
+    /// Tries to create a new [`XbfEnumMetadata`], checking that every variant has a name that is
+    /// not empty and not used by another variant, that every name fits in the length prefix of
+    /// the default [`XbfConfig`], and that there are no more variants than a `u16` index can
+    /// pick from.
+    ///
+    /// Only the variants of this enum are checked, use
+    /// [`XbfMetadata::validate`](crate::XbfMetadata::validate) to check the types nested inside
+    /// them as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidMetadataError`] describing the first problem found.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::InvalidMetadataError;
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::try_new(
+    ///     "command".to_string(),
+    ///     vec![
+    ///         ("move".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("".to_string(), XbfPrimitiveMetadata::String.into()),
+    ///     ],
+    /// );
+    ///
+    /// assert_eq!(metadata, Err(InvalidMetadataError::EmptyName { index: 1 }));
+    /// ```
+    pub fn try_new(
+        name: String,
+        variants: Vec<(String, XbfMetadata)>,
+    ) -> Result<Self, InvalidMetadataError> {
+        check_variant_count(&variants)?;
+        check_name_lens(&name, &variants)?;
+        check_names(&variants)?;
+        Ok(Self::new(name, variants))
+    }
+
+    /// Returns the name of the enum.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the index of the variant with the given name, if there is one.
+    ///
+    /// Variants past the first 65536 can't be written, since the index is sent as a `u16`, so
+    /// this returns `None` for them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "command".to_string(),
+    ///     vec![
+    ///         ("move".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("say".to_string(), XbfPrimitiveMetadata::String.into()),
+    ///     ],
+    /// );
+    ///
+    /// assert_eq!(metadata.variant_index("say"), Some(1));
+    /// assert_eq!(metadata.variant_index("jump"), None);
+    /// ```
+    pub fn variant_index(&self, name: &str) -> Option<u16> {
+        self.variants
+            .iter()
+            .position(|(variant_name, _)| variant_name == name)
+            .and_then(|i| u16::try_from(i).ok())
+    }
+
+    /// Serialize enum metadata as defined by the XBF specification.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::ENUM_METADATA_DISCRIMINANT;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "e".to_string(),
+    ///     vec![("a".to_string(), XbfPrimitiveMetadata::I32.into())],
+    /// );
+    /// let mut writer = vec![];
+    ///
+    /// metadata.serialize_enum_metadata(&mut writer).unwrap();
+    ///
+    /// let mut expected = vec![ENUM_METADATA_DISCRIMINANT];
+    /// expected.extend_from_slice(&1u16.to_le_bytes());
+    /// expected.extend_from_slice(b"e");
+    /// expected.extend_from_slice(&1u16.to_le_bytes());
+    /// expected.extend_from_slice(&1u16.to_le_bytes());
+    /// expected.extend_from_slice(b"a");
+    /// expected.push(XbfPrimitiveMetadata::I32 as u8);
+    ///
+    /// assert_eq!(writer, expected);
+    /// ```
+    pub fn serialize_enum_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_enum_metadata_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize enum metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_enum_metadata`] for more details.
+    pub fn serialize_enum_metadata_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        writer.write_u8(ENUM_METADATA_DISCRIMINANT)?;
+        write_string(&self.name, writer, config)?;
+        write_len(self.variants.len(), writer, config)?;
+        self.variants.iter().try_for_each(|(name, type_)| {
+            write_string(name, writer, config)
+                .and_then(|_| type_.serialize_base_metadata_with_config(writer, config))
+                .map_err(|e| e.within(format_args!("::{name}")))
+        })
+    }
+
+    /// Deserialize enum metadata as defined by the XBF specification.
+    ///
+    /// This method assumes that you know for a fact you are about to receive Enum metadata. If you
+    /// do not know what sort of metadata you are receiving, use
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let mut reader = vec![];
+    /// reader.extend_from_slice(&1u16.to_le_bytes());
+    /// reader.extend_from_slice(b"e");
+    /// reader.extend_from_slice(&1u16.to_le_bytes());
+    /// reader.extend_from_slice(&1u16.to_le_bytes());
+    /// reader.extend_from_slice(b"a");
+    /// reader.push(XbfPrimitiveMetadata::I32 as u8);
+    /// let mut reader = std::io::Cursor::new(reader);
+    ///
+    /// let metadata = XbfEnumMetadata::deserialize_enum_metadata(&mut reader).unwrap();
+    ///
+    /// assert_eq!(metadata, XbfEnumMetadata::new(
+    ///     "e".to_string(),
+    ///     vec![("a".to_string(), XbfPrimitiveMetadata::I32.into())],
+    /// ));
+    /// ```
+    pub fn deserialize_enum_metadata(reader: &mut impl Read) -> Result<XbfEnumMetadata> {
+        Self::deserialize_enum_metadata_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize enum metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_enum_metadata`] for more details.
+    pub fn deserialize_enum_metadata_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfEnumMetadata> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_enum_metadata_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_enum_metadata_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfEnumMetadata> {
+        reader.nested(|reader| {
+            let name = read_string(reader)?;
+            let len = reader.read_fields_len()?;
+            let mut variants = preallocate(len);
+            for i in 0..len {
+                let variant_name =
+                    read_string(reader).map_err(|e| e.within(format_args!("::<variant {i}>")))?;
+                let variant_type = XbfMetadata::deserialize_base_metadata_with_context(reader)
+                    .map_err(|e| e.within(format_args!("::{variant_name}")))?;
+                variants.push((variant_name, variant_type))
+            }
+            check_variant_count(&variants)?;
+            check_names(&variants)?;
+            Ok(XbfEnumMetadata::new(name, variants))
+        })
+    }
+
+    /// Returns the number of bytes [`Self::serialize_enum_metadata`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_enum_metadata_with_config`] would write using the given
+    /// [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        1 + bytes_encoded_len(self.name.len(), config)
+            + len_encoded_len(self.variants.len(), config)
+            + self
+                .variants
+                .iter()
+                .map(|(name, x)| {
+                    bytes_encoded_len(name.len(), config) + x.encoded_len_with_config(config)
+                })
+                .sum::<usize>()
+    }
+}
+
+impl XbfMetadataUpcast for XbfEnumMetadata {}
+
+impl From<&XbfEnumValue> for XbfEnumMetadata {
+    fn from(value: &XbfEnumValue) -> Self {
+        value.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        DecodeLimits, LengthEncoding, XbfError, XbfPrimitiveMetadata, XbfStructMetadata,
+        XbfVecMetadata,
+    };
+    use byteorder::LittleEndian;
+    use std::io::Cursor;
+
+    #[test]
+    fn metadata_serde_works() {
+        let metadata = XbfEnumMetadata::new(
+            "test".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+                (
+                    "b".to_string(),
+                    XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into()).into(),
+                ),
+                (
+                    "c".to_string(),
+                    XbfStructMetadata::new("inner".to_string(), vec![]).into(),
+                ),
+            ],
+        );
+
+        let mut writer = Vec::new();
+        metadata.serialize_enum_metadata(&mut writer).unwrap();
+
+        let mut expected = Vec::new();
+        // discriminant
+        expected.write_u8(ENUM_METADATA_DISCRIMINANT).unwrap();
+        // name
+        write_string("test", &mut expected, &XbfConfig::default()).unwrap();
+        // num of variants
+        expected.write_u16::<LittleEndian>(3).unwrap();
+        // variant a
+        write_string("a", &mut expected, &XbfConfig::default()).unwrap();
+        XbfPrimitiveMetadata::I32
+            .serialize_primitive_metadata(&mut expected)
+            .unwrap();
+        // variant b
+        write_string("b", &mut expected, &XbfConfig::default()).unwrap();
+        XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into())
+            .serialize_vec_metadata(&mut expected)
+            .unwrap();
+        // variant c
+        write_string("c", &mut expected, &XbfConfig::default()).unwrap();
+        XbfStructMetadata::new("inner".to_string(), vec![])
+            .serialize_struct_metadata(&mut expected)
+            .unwrap();
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+        let deserialized = XbfMetadata::deserialize_base_metadata(&mut reader).unwrap();
+        assert_eq!(XbfMetadata::Enum(metadata), deserialized);
+    }
+
+    #[test]
+    fn too_many_variants_are_rejected() {
+        let variants: Vec<_> = (0..=u16::MAX as usize + 1)
+            .map(|i| (i.to_string(), XbfPrimitiveMetadata::U8.into()))
+            .collect();
+
+        assert_eq!(
+            XbfEnumMetadata::try_new("big".to_string(), variants.clone()),
+            Err(InvalidMetadataError::TooManyVariants(65537))
+        );
+        let unchecked = XbfEnumMetadata::new("big".to_string(), variants.clone());
+        assert_eq!(unchecked.variant_index("65535"), Some(u16::MAX));
+        assert_eq!(unchecked.variant_index("65536"), None);
+
+        let config = XbfConfig::new()
+            .with_length_encoding(LengthEncoding::U32)
+            .with_limits(DecodeLimits::unlimited());
+        let mut writer = vec![];
+        XbfEnumMetadata::new("big".to_string(), variants)
+            .serialize_enum_metadata_with_config(&mut writer, &config)
+            .unwrap();
+        let err =
+            XbfMetadata::deserialize_base_metadata_with_config(&mut Cursor::new(writer), &config)
+                .unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidMetadata(InvalidMetadataError::TooManyVariants(65537))
+        ));
+    }
+
+    #[test]
+    fn upcast_works() {
+        let enum_metadata = XbfEnumMetadata::new(
+            "test_enum".to_string(),
+            vec![("variant1".to_string(), XbfPrimitiveMetadata::I32.into())],
+        );
+        let enum_metadata_ref = &enum_metadata;
+
+        assert_eq!(
+            XbfMetadata::Enum(enum_metadata.clone()),
+            enum_metadata_ref.to_base_metadata()
+        );
+        assert_eq!(
+            XbfMetadata::Enum(enum_metadata.clone()),
+            enum_metadata.into_base_metadata()
+        );
+    }
+}
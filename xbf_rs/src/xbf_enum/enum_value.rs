@@ -0,0 +1,414 @@
+use crate::{
+    util::ReadContext, Result, XbfConfig, XbfEnumMetadata, XbfError, XbfMetadata, XbfType,
+    XbfTypeUpcast,
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{Read, Write},
+};
+
+/// An enum value as defined by the XBF specification.
+///
+/// An enum value is exactly one of the variants listed in its metadata, along with that
+/// variant's payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XbfEnumValue {
+    pub(crate) metadata: XbfEnumMetadata,
+    variant: u16,
+    value: Box<XbfType>,
+}
+
+impl XbfEnumValue {
+    /// Tries to create a new [`XbfEnumValue`] holding the named variant.
+    ///
+    /// # Errors
+    ///
+    /// If the metadata has no variant with the given name, or the value is not the same XBF type
+    /// as what's specified for that variant, returns an [`EnumVariantError`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumValue;
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "command".to_string(),
+    ///     vec![
+    ///         ("move".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("say".to_string(), XbfPrimitiveMetadata::String.into()),
+    ///     ],
+    /// );
+    ///
+    /// let say = XbfEnumValue::new(
+    ///     metadata.clone(),
+    ///     "say",
+    ///     XbfPrimitive::String("hello".to_string()).into(),
+    /// );
+    /// assert!(say.is_ok());
+    ///
+    /// let wrong_payload = XbfEnumValue::new(metadata.clone(), "move", XbfPrimitive::U8(1).into());
+    /// assert!(wrong_payload.is_err());
+    ///
+    /// let unknown_variant = XbfEnumValue::new(metadata, "jump", XbfPrimitive::I32(1).into());
+    /// assert!(unknown_variant.is_err());
+    /// ```
+    pub fn new(
+        metadata: XbfEnumMetadata,
+        variant: &str,
+        value: XbfType,
+    ) -> Result<Self, EnumVariantError> {
+        let index = metadata
+            .variant_index(variant)
+            .ok_or_else(|| EnumVariantError::unknown_variant(variant))?;
+        let expected_type = &metadata.variants[index as usize].1;
+        let actual_type = XbfMetadata::from(&value);
+        if *expected_type != actual_type {
+            return Err(EnumVariantError::type_mismatch(
+                variant,
+                expected_type,
+                &actual_type,
+            ));
+        }
+        Ok(Self::new_unchecked(metadata, index, value))
+    }
+
+    /// Creates a new [`XbfEnumValue`] holding the variant at the given index without checking that
+    /// the variant exists or that the value is the correct type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumValue;
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "command".to_string(),
+    ///     vec![("move".to_string(), XbfPrimitiveMetadata::I32.into())],
+    /// );
+    ///
+    /// let value = XbfEnumValue::new_unchecked(metadata, 0, XbfPrimitive::I32(5).into());
+    /// ```
+    pub fn new_unchecked(metadata: XbfEnumMetadata, variant: u16, value: XbfType) -> Self {
+        Self {
+            metadata,
+            variant,
+            value: Box::new(value),
+        }
+    }
+
+    /// Returns the index of the variant held by this value.
+    pub fn variant_index(&self) -> u16 {
+        self.variant
+    }
+
+    /// Returns the name of the variant held by this value, if the index refers to a variant in
+    /// the metadata.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumValue;
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "command".to_string(),
+    ///     vec![("move".to_string(), XbfPrimitiveMetadata::I32.into())],
+    /// );
+    /// let value = XbfEnumValue::new(metadata, "move", XbfPrimitive::I32(5).into()).unwrap();
+    ///
+    /// assert_eq!(value.variant_name(), Some("move"));
+    /// ```
+    pub fn variant_name(&self) -> Option<&str> {
+        self.metadata
+            .variants
+            .get(self.variant as usize)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns a reference to the payload of the variant held by this value.
+    pub fn value(&self) -> &XbfType {
+        &self.value
+    }
+
+    /// Serialize an enum value as defined by the XBF specification.
+    ///
+    /// The index of the variant is written as an unsigned 16-bit integer, followed by the payload
+    /// of the variant.
+    ///
+    /// This function **does not** write out the metadata of the type. If you want to write out the
+    /// metadata, get the metadata with [`Self::get_metadata`] and serialize that with
+    /// [`XbfEnumMetadata::serialize_enum_metadata`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumValue;
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "command".to_string(),
+    ///     vec![
+    ///         ("move".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("stop".to_string(), XbfPrimitiveMetadata::U8.into()),
+    ///     ],
+    /// );
+    /// let value = XbfEnumValue::new(metadata, "stop", XbfPrimitive::U8(9).into()).unwrap();
+    /// let mut writer = vec![];
+    /// value.serialize_enum_type(&mut writer).unwrap();
+    ///
+    /// assert_eq!(writer, [1, 0, 9]);
+    /// ```
+    pub fn serialize_enum_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_enum_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize an enum value using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_enum_type`] for more details.
+    pub fn serialize_enum_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        writer.write_u16::<LittleEndian>(self.variant)?;
+        self.value
+            .serialize_base_type_with_config(writer, config)
+            .map_err(|e| e.within(format_args!("::{}", self.variant_name().unwrap_or("?"))))
+    }
+
+    /// Deserialize an enum value as defined by the XBF specification.
+    ///
+    /// This function **does not** read the metadata of the type from the reader. It is expected
+    /// that to call this function the metadata for a type is already known, be that from reading
+    /// it from the reader with
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata)
+    /// or having it in some other manner.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the reader, returns
+    /// [`XbfError::UnknownVariant`](crate::XbfError::UnknownVariant) if the variant index is out of
+    /// range.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfEnumValue;
+    /// use xbf_rs::XbfEnumMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfEnumMetadata::new(
+    ///     "command".to_string(),
+    ///     vec![("move".to_string(), XbfPrimitiveMetadata::I32.into())],
+    /// );
+    /// let mut reader = vec![];
+    /// reader.extend_from_slice(&0u16.to_le_bytes());
+    /// reader.extend_from_slice(&5i32.to_le_bytes());
+    /// let mut reader = std::io::Cursor::new(reader);
+    ///
+    /// let value = XbfEnumValue::deserialize_enum_type(&metadata, &mut reader).unwrap();
+    /// assert_eq!(value.variant_name(), Some("move"));
+    /// ```
+    pub fn deserialize_enum_type(
+        metadata: &XbfEnumMetadata,
+        reader: &mut impl Read,
+    ) -> Result<XbfEnumValue> {
+        Self::deserialize_enum_type_with_config(metadata, reader, &XbfConfig::default())
+    }
+
+    /// Deserialize an enum value using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_enum_type`] for more details.
+    pub fn deserialize_enum_type_with_config(
+        metadata: &XbfEnumMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfEnumValue> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_enum_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_enum_type_with_context(
+        metadata: &XbfEnumMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfEnumValue> {
+        reader.nested(|reader| {
+            let variant = reader.read_u16::<LittleEndian>()?;
+            let (variant_name, variant_type) = metadata
+                .variants
+                .get(variant as usize)
+                .ok_or(XbfError::UnknownVariant(variant))?;
+            let value = XbfType::deserialize_base_type_with_context(variant_type, reader)
+                .map_err(|e| e.within(format_args!("::{variant_name}")))?;
+            Ok(Self::new_unchecked(metadata.clone(), variant, value))
+        })
+    }
+
+    /// Returns the metadata of the enum.
+    ///
+    /// Getting the metadata returns an owned [`XbfEnumMetadata`], which only
+    /// clones the reference counted pointers it holds.
+    pub fn get_metadata(&self) -> XbfEnumMetadata {
+        self.metadata.clone()
+    }
+
+    /// Returns the number of bytes [`Self::serialize_enum_type`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_enum_type_with_config`] would write using
+    /// the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        2 + self.value.encoded_len_with_config(config)
+    }
+}
+
+impl XbfTypeUpcast for XbfEnumValue {}
+
+/// Error type for [`XbfEnumValue`]
+#[derive(Debug)]
+pub struct EnumVariantError(String);
+
+impl EnumVariantError {
+    fn unknown_variant(variant_name: &str) -> EnumVariantError {
+        EnumVariantError(format!("enum has no variant named {variant_name}"))
+    }
+
+    fn type_mismatch(
+        variant_name: &str,
+        expected_type: &XbfMetadata,
+        actual_type: &XbfMetadata,
+    ) -> EnumVariantError {
+        let s = format!("provided value for variant {variant_name} is of type {actual_type:?}, expected {expected_type:?}");
+        EnumVariantError(s)
+    }
+}
+
+impl Display for EnumVariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for EnumVariantError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{XbfPrimitive, XbfPrimitiveMetadata, XbfStruct, XbfStructMetadata};
+    use std::io::Cursor;
+
+    fn command_metadata() -> XbfEnumMetadata {
+        XbfEnumMetadata::new(
+            "command".to_string(),
+            vec![
+                ("move".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("say".to_string(), XbfPrimitiveMetadata::String.into()),
+                (
+                    "error".to_string(),
+                    XbfStructMetadata::new(
+                        "error".to_string(),
+                        vec![("code".to_string(), XbfPrimitiveMetadata::U16.into())],
+                    )
+                    .into(),
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn enum_new_failure_works() {
+        let unknown =
+            XbfEnumValue::new(command_metadata(), "jump", XbfPrimitive::I32(1).into()).unwrap_err();
+        assert_eq!(
+            unknown.to_string(),
+            EnumVariantError::unknown_variant("jump").to_string()
+        );
+
+        let mismatch =
+            XbfEnumValue::new(command_metadata(), "say", XbfPrimitive::I32(1).into()).unwrap_err();
+        assert_eq!(
+            mismatch.to_string(),
+            EnumVariantError::type_mismatch(
+                "say",
+                &XbfPrimitiveMetadata::String.into(),
+                &XbfPrimitiveMetadata::I32.into()
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn enum_serde_works() {
+        let error_struct = XbfStruct::new(
+            XbfStructMetadata::new(
+                "error".to_string(),
+                vec![("code".to_string(), XbfPrimitiveMetadata::U16.into())],
+            ),
+            vec![XbfPrimitive::U16(404).into()],
+        )
+        .unwrap();
+        let say = XbfEnumValue::new(
+            command_metadata(),
+            "say",
+            XbfPrimitive::String("hi".to_string()).into(),
+        )
+        .unwrap();
+        let error = XbfEnumValue::new(command_metadata(), "error", error_struct.into()).unwrap();
+        let mut writer = vec![];
+
+        say.serialize_enum_type(&mut writer).unwrap();
+        error.serialize_enum_type(&mut writer).unwrap();
+
+        let mut expected = vec![];
+        expected.extend_from_slice(&1u16.to_le_bytes());
+        expected.extend_from_slice(&2u16.to_le_bytes());
+        expected.extend_from_slice(b"hi");
+        expected.extend_from_slice(&2u16.to_le_bytes());
+        expected.extend_from_slice(&404u16.to_le_bytes());
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+        let metadata = XbfMetadata::Enum(command_metadata());
+
+        let deserialized_say = XbfType::deserialize_base_type(&metadata, &mut reader).unwrap();
+        let deserialized_error = XbfType::deserialize_base_type(&metadata, &mut reader).unwrap();
+
+        assert_eq!(deserialized_say, say.into());
+        assert_eq!(deserialized_error, error.into());
+    }
+
+    #[test]
+    fn deserialize_unknown_variant_index_fails() {
+        let mut reader = Cursor::new(3u16.to_le_bytes());
+
+        let err =
+            XbfEnumValue::deserialize_enum_type(&command_metadata(), &mut reader).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::UnknownVariant(3)));
+    }
+
+    #[test]
+    fn upcast_works() {
+        let value =
+            XbfEnumValue::new(command_metadata(), "move", XbfPrimitive::I32(3).into()).unwrap();
+        let value_ref = &value;
+
+        assert_eq!(XbfType::Enum(value.clone()), value_ref.to_base_type());
+        assert_eq!(XbfType::Enum(value.clone()), value.into_base_type());
+    }
+}
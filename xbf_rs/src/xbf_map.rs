@@ -0,0 +1,7 @@
+//! A map as defined by the XBF specification.
+
+mod map;
+mod map_metadata;
+
+pub use map::*;
+pub use map_metadata::*;
@@ -0,0 +1,550 @@
+use crate::{
+    util::{len_encoded_len, preallocate, write_len, ReadContext},
+    Result, XbfConfig, XbfError, XbfMapMetadata, XbfMetadata, XbfPrimitive, XbfPrimitiveMetadata,
+    XbfPrimitiveRef, XbfType, XbfTypeUpcast,
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    io::{Read, Write},
+};
+
+/// A map type as defined by the XBF specification.
+///
+/// Entries are kept in the order they were supplied, which is also the order they are
+/// serialized in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XbfMap {
+    pub(crate) metadata: XbfMapMetadata,
+    entries: Vec<(XbfPrimitive, XbfType)>,
+}
+
+impl XbfMap {
+    /// Tries to create a new map based on the supplied metadata.
+    ///
+    /// # Errors
+    ///
+    /// If any key or value is not the same XBF type as what's specified in the metadata, or if
+    /// the same key appears more than once, returns a [`MapEntryError`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMap;
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfMapMetadata::new(
+    ///     XbfPrimitiveMetadata::String,
+    ///     XbfPrimitiveMetadata::I32.into(),
+    /// );
+    /// let entries = vec![
+    ///     (XbfPrimitive::String("a".to_string()), XbfPrimitive::I32(1).into()),
+    ///     (XbfPrimitive::String("b".to_string()), XbfPrimitive::I32(2).into()),
+    /// ];
+    /// let map = XbfMap::new(metadata.clone(), entries);
+    ///
+    /// assert!(map.is_ok());
+    ///
+    /// let duplicate_entries = vec![
+    ///     (XbfPrimitive::String("a".to_string()), XbfPrimitive::I32(1).into()),
+    ///     (XbfPrimitive::String("a".to_string()), XbfPrimitive::I32(2).into()),
+    /// ];
+    /// let map2 = XbfMap::new(metadata, duplicate_entries);
+    ///
+    /// assert!(map2.is_err());
+    /// ```
+    pub fn new(
+        metadata: XbfMapMetadata,
+        entries: Vec<(XbfPrimitive, XbfType)>,
+    ) -> Result<Self, MapEntryError> {
+        for (key, value) in entries.iter() {
+            if metadata.key_type != XbfPrimitiveMetadata::from(key) {
+                return Err(MapEntryError::KeyNotHomogenous);
+            }
+            if *metadata.value_type != XbfMetadata::from(value) {
+                return Err(MapEntryError::ValueNotHomogenous);
+            }
+        }
+        if has_duplicate_keys(entries.iter().map(|(k, _)| k.into())) {
+            return Err(MapEntryError::DuplicateKey);
+        }
+        Ok(Self { metadata, entries })
+    }
+
+    /// Creates a new map with the supplied metadata and entries without checking the types of
+    /// the entries or the uniqueness of the keys.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMap;
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfMapMetadata::new(
+    ///     XbfPrimitiveMetadata::String,
+    ///     XbfPrimitiveMetadata::I32.into(),
+    /// );
+    /// let map = XbfMap::new_unchecked(
+    ///     metadata,
+    ///     vec![(XbfPrimitive::U8(1), XbfPrimitive::U8(2).into())],
+    /// );
+    /// ```
+    pub fn new_unchecked(metadata: XbfMapMetadata, entries: Vec<(XbfPrimitive, XbfType)>) -> Self {
+        Self { metadata, entries }
+    }
+
+    /// Returns a reference to the value stored for the given key, if there is one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMap;
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfType;
+    ///
+    /// let map = XbfMap::new(
+    ///     XbfMapMetadata::new(XbfPrimitiveMetadata::U8, XbfPrimitiveMetadata::I32.into()),
+    ///     vec![(XbfPrimitive::U8(1), XbfPrimitive::I32(42).into())],
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(
+    ///     map.get(&XbfPrimitive::U8(1)),
+    ///     Some(&XbfType::Primitive(XbfPrimitive::I32(42)))
+    /// );
+    /// assert_eq!(map.get(&XbfPrimitive::U8(2)), None);
+    /// ```
+    pub fn get(&self, key: &XbfPrimitive) -> Option<&XbfType> {
+        let key = HashableKey(key.into());
+        self.entries
+            .iter()
+            .find(|(k, _)| HashableKey(k.into()) == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Serialize a map as defined by the XBF specification.
+    ///
+    /// The number of entries is written as an unsigned 16-bit integer, followed by each key and
+    /// its value in turn.
+    ///
+    /// This function **does not** write out the metadata of the type. If you want to write out the
+    /// metadata, convert this type to a [`XbfMapMetadata`] and call
+    /// [`XbfMapMetadata::serialize_map_metadata`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMap;
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let map = XbfMap::new(
+    ///     XbfMapMetadata::new(XbfPrimitiveMetadata::U8, XbfPrimitiveMetadata::I32.into()),
+    ///     vec![(XbfPrimitive::U8(1), XbfPrimitive::I32(42).into())],
+    /// )
+    /// .unwrap();
+    /// let mut writer = vec![];
+    /// map.serialize_map_type(&mut writer).unwrap();
+    ///
+    /// let mut expected = vec![];
+    /// expected.extend_from_slice(&1u16.to_le_bytes());
+    /// expected.push(1);
+    /// expected.extend_from_slice(&42i32.to_le_bytes());
+    /// assert_eq!(writer, expected);
+    /// ```
+    pub fn serialize_map_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_map_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize a map using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_map_type`] for more details.
+    pub fn serialize_map_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        write_len(self.entries.len(), writer, config)?;
+        self.entries.iter().enumerate().try_for_each(|(i, (k, v))| {
+            k.serialize_primitive_type_with_config(writer, config)
+                .and_then(|_| v.serialize_base_type_with_config(writer, config))
+                .map_err(|e| e.within(format_args!("[{i}]")))
+        })
+    }
+
+    /// Deserialize a map as defined by the XBF specification.
+    ///
+    /// This function **does not** read the metadata of the type from the reader. It is
+    /// expected that to call this function the metadata for a type is already known, be
+    /// that from reading it from the reader with
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata)
+    /// or having it in some other manner.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the reader, returns
+    /// [`XbfError::DuplicateKey`](crate::XbfError::DuplicateKey) if the same key appears more than
+    /// once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMap;
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata =
+    ///     XbfMapMetadata::new(XbfPrimitiveMetadata::U8, XbfPrimitiveMetadata::I32.into());
+    /// let mut reader = vec![];
+    /// reader.extend_from_slice(&1u16.to_le_bytes());
+    /// reader.push(1);
+    /// reader.extend_from_slice(&42i32.to_le_bytes());
+    /// let mut reader = std::io::Cursor::new(reader);
+    ///
+    /// let map = XbfMap::deserialize_map_type(&metadata, &mut reader).unwrap();
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn deserialize_map_type(
+        metadata: &XbfMapMetadata,
+        reader: &mut impl Read,
+    ) -> Result<XbfMap> {
+        Self::deserialize_map_type_with_config(metadata, reader, &XbfConfig::default())
+    }
+
+    /// Deserialize a map using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_map_type`] for more details.
+    pub fn deserialize_map_type_with_config(
+        metadata: &XbfMapMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfMap> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_map_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_map_type_with_context(
+        metadata: &XbfMapMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfMap> {
+        reader.nested(|reader| {
+            let len = reader.read_elements_len()?;
+            let mut entries = preallocate(len);
+            for i in 0..len {
+                let entry = XbfPrimitive::deserialize_primitive_type_with_context(
+                    &metadata.key_type,
+                    reader,
+                )
+                .and_then(|key| {
+                    XbfType::deserialize_base_type_with_context(&metadata.value_type, reader)
+                        .map(|value| (key, value))
+                })
+                .map_err(|e| e.within(format_args!("[{i}]")))?;
+                entries.push(entry);
+            }
+            if has_duplicate_keys(entries.iter().map(|(k, _)| k.into())) {
+                return Err(XbfError::DuplicateKey);
+            }
+            Ok(XbfMap::new_unchecked(metadata.clone(), entries))
+        })
+    }
+
+    /// Returns the metadata of the map.
+    ///
+    /// Getting the metadata returns an owned [`XbfMapMetadata`], which only
+    /// clones the reference counted pointers it holds.
+    pub fn get_metadata(&self) -> XbfMapMetadata {
+        self.metadata.clone()
+    }
+
+    /// Returns the number of bytes [`Self::serialize_map_type`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_map_type_with_config`] would write using the
+    /// given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        len_encoded_len(self.entries.len(), config)
+            + self
+                .entries
+                .iter()
+                .map(|(k, v)| k.encoded_len_with_config(config) + v.encoded_len_with_config(config))
+                .sum::<usize>()
+    }
+}
+
+impl XbfTypeUpcast for XbfMap {}
+
+/// Error type for [`XbfMap`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum MapEntryError {
+    /// A key was not the same type as the key type in the metadata.
+    KeyNotHomogenous,
+    /// A value was not the same type as the value type in the metadata.
+    ValueNotHomogenous,
+    /// The same key appeared more than once.
+    DuplicateKey,
+}
+
+impl Display for MapEntryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapEntryError::KeyNotHomogenous => write!(f, "map key does not match the key type"),
+            MapEntryError::ValueNotHomogenous => {
+                write!(f, "map value does not match the value type")
+            }
+            MapEntryError::DuplicateKey => write!(f, "map contains a duplicate key"),
+        }
+    }
+}
+
+impl Error for MapEntryError {}
+
+/// Returns whether any of the keys are equal to each other.
+pub(crate) fn has_duplicate_keys<'a>(
+    keys: impl ExactSizeIterator<Item = XbfPrimitiveRef<'a>>,
+) -> bool {
+    let mut seen = HashSet::with_capacity(keys.len());
+    !keys.into_iter().all(|k| seen.insert(HashableKey(k)))
+}
+
+/// A map key compared by its encoded value, so that floats can be hashed and compared bitwise.
+struct HashableKey<'a>(XbfPrimitiveRef<'a>);
+
+impl PartialEq for HashableKey<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.0, other.0) {
+            (XbfPrimitiveRef::F32(a), XbfPrimitiveRef::F32(b)) => a.to_bits() == b.to_bits(),
+            (XbfPrimitiveRef::F64(a), XbfPrimitiveRef::F64(b)) => a.to_bits() == b.to_bits(),
+            (XbfPrimitiveRef::F16(a), XbfPrimitiveRef::F16(b)) => a.to_bits() == b.to_bits(),
+            (XbfPrimitiveRef::BF16(a), XbfPrimitiveRef::BF16(b)) => a.to_bits() == b.to_bits(),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Eq for HashableKey<'_> {}
+
+impl Hash for HashableKey<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(&self.0).hash(state);
+        match self.0 {
+            XbfPrimitiveRef::Bool(x) => x.hash(state),
+            XbfPrimitiveRef::U8(x) => x.hash(state),
+            XbfPrimitiveRef::U16(x) => x.hash(state),
+            XbfPrimitiveRef::U32(x) => x.hash(state),
+            XbfPrimitiveRef::U64(x) => x.hash(state),
+            XbfPrimitiveRef::U128(x) => x.hash(state),
+            XbfPrimitiveRef::U256(x) => x.hash(state),
+            XbfPrimitiveRef::I8(x) => x.hash(state),
+            XbfPrimitiveRef::I16(x) => x.hash(state),
+            XbfPrimitiveRef::I32(x) => x.hash(state),
+            XbfPrimitiveRef::I64(x) => x.hash(state),
+            XbfPrimitiveRef::I128(x) => x.hash(state),
+            XbfPrimitiveRef::I256(x) => x.hash(state),
+            XbfPrimitiveRef::F32(x) => x.to_bits().hash(state),
+            XbfPrimitiveRef::F64(x) => x.to_bits().hash(state),
+            XbfPrimitiveRef::Bytes(x) => x.hash(state),
+            XbfPrimitiveRef::String(x) => x.hash(state),
+            XbfPrimitiveRef::Char(x) => x.hash(state),
+            XbfPrimitiveRef::F16(x) => x.to_bits().hash(state),
+            XbfPrimitiveRef::BF16(x) => x.to_bits().hash(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{LengthEncoding, XbfVec, XbfVecMetadata};
+    use std::io::Cursor;
+
+    fn string_to_i32_metadata() -> XbfMapMetadata {
+        XbfMapMetadata::new(
+            XbfPrimitiveMetadata::String,
+            XbfPrimitiveMetadata::I32.into(),
+        )
+    }
+
+    #[test]
+    fn map_new_fails_with_wrong_key_type() {
+        let err = XbfMap::new(
+            string_to_i32_metadata(),
+            vec![(XbfPrimitive::U8(1), XbfPrimitive::I32(1).into())],
+        )
+        .unwrap_err();
+        assert_eq!(err, MapEntryError::KeyNotHomogenous);
+    }
+
+    #[test]
+    fn map_new_fails_with_wrong_value_type() {
+        let err = XbfMap::new(
+            string_to_i32_metadata(),
+            vec![(
+                XbfPrimitive::String("a".to_string()),
+                XbfPrimitive::I64(1).into(),
+            )],
+        )
+        .unwrap_err();
+        assert_eq!(err, MapEntryError::ValueNotHomogenous);
+    }
+
+    #[test]
+    fn map_new_fails_with_duplicate_keys() {
+        let err = XbfMap::new(
+            string_to_i32_metadata(),
+            vec![
+                (
+                    XbfPrimitive::String("a".to_string()),
+                    XbfPrimitive::I32(1).into(),
+                ),
+                (
+                    XbfPrimitive::String("b".to_string()),
+                    XbfPrimitive::I32(2).into(),
+                ),
+                (
+                    XbfPrimitive::String("a".to_string()),
+                    XbfPrimitive::I32(3).into(),
+                ),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err, MapEntryError::DuplicateKey);
+    }
+
+    #[test]
+    fn map_serde_works() {
+        let value_metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into());
+        let metadata =
+            XbfMapMetadata::new(XbfPrimitiveMetadata::U16, value_metadata.clone().into());
+        let map = XbfMap::new(
+            metadata.clone(),
+            vec![
+                (
+                    XbfPrimitive::U16(2),
+                    XbfVec::new(value_metadata.clone(), vec![XbfPrimitive::U8(7).into()])
+                        .unwrap()
+                        .into(),
+                ),
+                (
+                    XbfPrimitive::U16(1),
+                    XbfVec::new(value_metadata, vec![]).unwrap().into(),
+                ),
+            ],
+        )
+        .unwrap();
+        let mut writer = vec![];
+
+        map.serialize_map_type(&mut writer).unwrap();
+
+        let mut expected = vec![];
+        expected.extend_from_slice(&2u16.to_le_bytes());
+        expected.extend_from_slice(&2u16.to_le_bytes());
+        expected.extend_from_slice(&1u16.to_le_bytes());
+        expected.push(7);
+        expected.extend_from_slice(&1u16.to_le_bytes());
+        expected.extend_from_slice(&0u16.to_le_bytes());
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+        let deserialized = XbfType::deserialize_base_type(&metadata.into(), &mut reader).unwrap();
+
+        assert_eq!(deserialized, map.into());
+    }
+
+    #[test]
+    fn deserialize_duplicate_keys_fails() {
+        let mut data = vec![];
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&2i32.to_le_bytes());
+        let mut reader = Cursor::new(data);
+        let metadata =
+            XbfMapMetadata::new(XbfPrimitiveMetadata::U8, XbfPrimitiveMetadata::I32.into());
+
+        let err = XbfMap::deserialize_map_type(&metadata, &mut reader).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::DuplicateKey));
+    }
+
+    #[test]
+    fn float_keys_are_compared_bitwise() {
+        let metadata =
+            XbfMapMetadata::new(XbfPrimitiveMetadata::F64, XbfPrimitiveMetadata::U8.into());
+
+        let distinct_zeros = XbfMap::new(
+            metadata.clone(),
+            vec![
+                (XbfPrimitive::F64(0.0), XbfPrimitive::U8(1).into()),
+                (XbfPrimitive::F64(-0.0), XbfPrimitive::U8(2).into()),
+            ],
+        );
+        assert!(distinct_zeros.is_ok());
+
+        let duplicate_nans = XbfMap::new(
+            metadata,
+            vec![
+                (XbfPrimitive::F64(f64::NAN), XbfPrimitive::U8(1).into()),
+                (XbfPrimitive::F64(f64::NAN), XbfPrimitive::U8(2).into()),
+            ],
+        );
+        assert_eq!(duplicate_nans.unwrap_err(), MapEntryError::DuplicateKey);
+    }
+
+    #[test]
+    fn large_map_round_trips_with_u32_lengths() {
+        let metadata =
+            XbfMapMetadata::new(XbfPrimitiveMetadata::U32, XbfPrimitiveMetadata::U8.into());
+        let entries = (0..70_000u32)
+            .map(|i| (XbfPrimitive::U32(i), XbfPrimitive::U8(i as u8).into()))
+            .collect();
+        let map = XbfMap::new(metadata.clone(), entries).unwrap();
+
+        let mut writer = vec![];
+        let err = map.serialize_map_type(&mut writer).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::LengthOverflow { .. }));
+
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::U32);
+        let mut writer = vec![];
+        map.serialize_map_type_with_config(&mut writer, &config)
+            .unwrap();
+        let mut reader = Cursor::new(writer);
+        let deserialized =
+            XbfMap::deserialize_map_type_with_config(&metadata, &mut reader, &config).unwrap();
+
+        assert_eq!(deserialized, map);
+    }
+
+    #[test]
+    fn upcast_works() {
+        let map = XbfMap::new(string_to_i32_metadata(), vec![]).unwrap();
+        let map_ref = &map;
+
+        assert_eq!(XbfType::Map(map.clone()), map_ref.to_base_type());
+        assert_eq!(XbfType::Map(map.clone()), map.into_base_type());
+    }
+}
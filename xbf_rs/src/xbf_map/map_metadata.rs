@@ -0,0 +1,230 @@
+use crate::{
+    util::ReadContext, Result, XbfConfig, XbfError, XbfMap, XbfMetadata, XbfMetadataUpcast,
+    XbfPrimitiveMetadata, ENUM_METADATA_DISCRIMINANT,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+/// The metadata discriminant for a Map type.
+///
+/// This is the same for all maps regardless of their contents. It's value should always be
+/// equal to the discriminant value of the enum type plus one.
+pub const MAP_METADATA_DISCRIMINANT: u8 = ENUM_METADATA_DISCRIMINANT + 1;
+
+/// Metadata for a Map type.
+///
+/// Keys of a map are always primitives, while values may be of any type. Internally the value
+/// metadata is stored on the heap to avoid having a recursive, infinitely sized type on the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XbfMapMetadata {
+    pub(crate) key_type: XbfPrimitiveMetadata,
+    pub(crate) value_type: Arc<XbfMetadata>,
+}
+
+impl XbfMapMetadata {
+    /// Creates a new Map metadata.
+    ///
+    /// This will cause a heap allocation with the moved value type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfMapMetadata::new(
+    ///     XbfPrimitiveMetadata::String,
+    ///     XbfPrimitiveMetadata::I32.into(),
+    /// );
+    /// ```
+    pub fn new(key_type: XbfPrimitiveMetadata, value_type: XbfMetadata) -> Self {
+        Self {
+            key_type,
+            value_type: Arc::new(value_type),
+        }
+    }
+
+    /// Serialize Map metadata as defined by the XBF specification.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::MAP_METADATA_DISCRIMINANT;
+    ///
+    /// let metadata = XbfMapMetadata::new(
+    ///     XbfPrimitiveMetadata::String,
+    ///     XbfPrimitiveMetadata::I32.into(),
+    /// );
+    /// let mut writer = Vec::new();
+    /// metadata.serialize_map_metadata(&mut writer).unwrap();
+    ///
+    /// assert_eq!(
+    ///     writer,
+    ///     [
+    ///         MAP_METADATA_DISCRIMINANT,
+    ///         XbfPrimitiveMetadata::String as u8,
+    ///         XbfPrimitiveMetadata::I32 as u8,
+    ///     ]
+    /// );
+    /// ```
+    pub fn serialize_map_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_map_metadata_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize Map metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_map_metadata`] for more details.
+    pub fn serialize_map_metadata_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        writer.write_u8(MAP_METADATA_DISCRIMINANT)?;
+        self.key_type.serialize_primitive_metadata(writer)?;
+        self.value_type
+            .serialize_base_metadata_with_config(writer, config)
+            .map_err(|e| e.within("[]"))
+    }
+
+    /// Deserialize Map metadata as defined by the XBF specification.
+    ///
+    /// This method assumes that you know for a fact you are about to receive Map metadata. If you
+    /// do not know what sort of metadata you are receiving, use
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata).
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the reader, returns
+    /// [`XbfError::InvalidMapKey`](crate::XbfError::InvalidMapKey) if the key type is not a
+    /// primitive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfMapMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let data = vec![XbfPrimitiveMetadata::String as u8, XbfPrimitiveMetadata::I32 as u8];
+    /// let mut reader = std::io::Cursor::new(data);
+    ///
+    /// let metadata = XbfMapMetadata::deserialize_map_metadata(&mut reader).unwrap();
+    ///
+    /// assert_eq!(
+    ///     metadata,
+    ///     XbfMapMetadata::new(XbfPrimitiveMetadata::String, XbfPrimitiveMetadata::I32.into())
+    /// );
+    /// ```
+    pub fn deserialize_map_metadata(reader: &mut impl Read) -> Result<XbfMapMetadata> {
+        Self::deserialize_map_metadata_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize Map metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_map_metadata`] for more details.
+    pub fn deserialize_map_metadata_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfMapMetadata> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_map_metadata_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_map_metadata_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfMapMetadata> {
+        reader.nested(|reader| {
+            let key = reader.read_u8()?;
+            let key_type =
+                XbfPrimitiveMetadata::try_from(key).map_err(|_| XbfError::InvalidMapKey(key))?;
+            let value_type = XbfMetadata::deserialize_base_metadata_with_context(reader)
+                .map_err(|e| e.within("[]"))?;
+            Ok(XbfMapMetadata::new(key_type, value_type))
+        })
+    }
+
+    /// Returns the number of bytes [`Self::serialize_map_metadata`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_map_metadata_with_config`] would write using the given
+    /// [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        1 + self.key_type.encoded_len() + self.value_type.encoded_len_with_config(config)
+    }
+}
+
+impl XbfMetadataUpcast for XbfMapMetadata {}
+
+impl From<&XbfMap> for XbfMapMetadata {
+    fn from(value: &XbfMap) -> Self {
+        value.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{XbfVecMetadata, VEC_METADATA_DISCRIMINANT};
+    use std::io::Cursor;
+
+    #[test]
+    fn map_metadata_serde_works() {
+        let metadata = XbfMapMetadata::new(
+            XbfPrimitiveMetadata::U32,
+            XbfVecMetadata::new(XbfPrimitiveMetadata::String.into()).into(),
+        );
+        let mut writer = vec![];
+
+        metadata.serialize_map_metadata(&mut writer).unwrap();
+
+        assert_eq!(
+            writer,
+            vec![
+                MAP_METADATA_DISCRIMINANT,
+                XbfPrimitiveMetadata::U32 as u8,
+                VEC_METADATA_DISCRIMINANT,
+                XbfPrimitiveMetadata::String as u8,
+            ]
+        );
+
+        let mut reader = Cursor::new(writer);
+        let deserialized = XbfMetadata::deserialize_base_metadata(&mut reader).unwrap();
+
+        assert_eq!(deserialized, metadata.into());
+    }
+
+    #[test]
+    fn map_metadata_with_non_primitive_key_fails() {
+        let mut reader = Cursor::new(vec![VEC_METADATA_DISCRIMINANT, 0]);
+
+        let err = XbfMapMetadata::deserialize_map_metadata(&mut reader).unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidMapKey(VEC_METADATA_DISCRIMINANT)
+        ));
+    }
+
+    #[test]
+    fn upcast_works() {
+        let map_metadata =
+            XbfMapMetadata::new(XbfPrimitiveMetadata::U8, XbfPrimitiveMetadata::I32.into());
+        let map_metadata_ref = &map_metadata;
+
+        assert_eq!(
+            XbfMetadata::Map(map_metadata.clone()),
+            map_metadata_ref.to_base_metadata()
+        );
+        assert_eq!(
+            XbfMetadata::Map(map_metadata.clone()),
+            map_metadata.into_base_metadata()
+        );
+    }
+}
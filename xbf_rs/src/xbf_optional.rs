@@ -0,0 +1,7 @@
+//! An optional type as defined by the XBF specification.
+
+mod optional;
+mod optional_metadata;
+
+pub use optional::*;
+pub use optional_metadata::*;
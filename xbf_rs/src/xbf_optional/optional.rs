@@ -0,0 +1,381 @@
+use crate::{
+    util::ReadContext, Result, XbfConfig, XbfError, XbfMetadata, XbfOptionalMetadata, XbfType,
+    XbfTypeUpcast,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{Read, Write},
+};
+
+/// An optional type as defined by the XBF specification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XbfOptional {
+    pub(crate) metadata: XbfOptionalMetadata,
+    value: Option<Box<XbfType>>,
+}
+
+impl XbfOptional {
+    /// Tries to create a new optional based on the supplied metadata.
+    ///
+    /// # Errors
+    ///
+    /// If a value is present and is not the same XBF type as what's specified in the metadata,
+    /// returns an [`OptionalTypeMismatchError`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptional;
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+    ///
+    /// let some = XbfOptional::new(metadata.clone(), Some(XbfPrimitive::I32(42).into()));
+    /// let none = XbfOptional::new(metadata.clone(), None);
+    /// assert!(some.is_ok());
+    /// assert!(none.is_ok());
+    ///
+    /// let wrong = XbfOptional::new(metadata, Some(XbfPrimitive::U64(42).into()));
+    /// assert!(wrong.is_err());
+    /// ```
+    pub fn new(
+        metadata: XbfOptionalMetadata,
+        value: Option<XbfType>,
+    ) -> Result<Self, OptionalTypeMismatchError> {
+        if let Some(val) = &value {
+            let actual_type = XbfMetadata::from(val);
+            if *metadata.inner_type != actual_type {
+                return Err(OptionalTypeMismatchError::new(
+                    &metadata.inner_type,
+                    &actual_type,
+                ));
+            }
+        }
+        Ok(Self::new_unchecked(metadata, value))
+    }
+
+    /// Creates a new optional with the supplied metadata and value without checking if the value
+    /// is the correct type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptional;
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+    /// let optional = XbfOptional::new_unchecked(metadata, Some(XbfPrimitive::U64(42).into()));
+    /// ```
+    pub fn new_unchecked(metadata: XbfOptionalMetadata, value: Option<XbfType>) -> Self {
+        Self {
+            metadata,
+            value: value.map(Box::new),
+        }
+    }
+
+    /// Returns a reference to the contained value, if there is one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptional;
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfType;
+    ///
+    /// let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+    /// let optional = XbfOptional::new(metadata, Some(XbfPrimitive::I32(42).into())).unwrap();
+    ///
+    /// assert_eq!(optional.value(), Some(&XbfType::Primitive(XbfPrimitive::I32(42))));
+    /// ```
+    pub fn value(&self) -> Option<&XbfType> {
+        self.value.as_deref()
+    }
+
+    /// Consumes the optional, returning the contained value if there is one.
+    pub fn into_value(self) -> Option<XbfType> {
+        self.value.map(|x| *x)
+    }
+
+    /// Serialize an optional as defined by the XBF specification.
+    ///
+    /// A single presence byte is written first, `1` if a value is present and `0` otherwise,
+    /// followed by the value itself if there is one.
+    ///
+    /// This function **does not** write out the metadata of the type. If you want to write out the
+    /// metadata, convert this type to a [`XbfOptionalMetadata`] and call
+    /// [`XbfOptionalMetadata::serialize_optional_metadata`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptional;
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+    /// let some = XbfOptional::new(metadata.clone(), Some(XbfPrimitive::I32(42).into())).unwrap();
+    /// let none = XbfOptional::new(metadata, None).unwrap();
+    /// let mut writer = vec![];
+    /// some.serialize_optional_type(&mut writer).unwrap();
+    /// none.serialize_optional_type(&mut writer).unwrap();
+    ///
+    /// let mut expected = vec![1];
+    /// expected.extend_from_slice(&42i32.to_le_bytes());
+    /// expected.push(0);
+    /// assert_eq!(writer, expected);
+    /// ```
+    pub fn serialize_optional_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_optional_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize an optional using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_optional_type`] for more details.
+    pub fn serialize_optional_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        match &self.value {
+            Some(x) => {
+                writer.write_u8(1)?;
+                x.serialize_base_type_with_config(writer, config)
+            }
+            None => Ok(writer.write_u8(0)?),
+        }
+    }
+
+    /// Deserialize an optional as defined by the XBF specification.
+    ///
+    /// This function **does not** read the metadata of the type from the reader. It is
+    /// expected that to call this function the metadata for a type is already known, be
+    /// that from reading it from the reader with
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata)
+    /// or having it in some other manner.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the reader, returns
+    /// [`XbfError::InvalidPresenceByte`](crate::XbfError::InvalidPresenceByte) if the presence byte
+    /// is not `0` or `1`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptional;
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+    /// let mut reader = vec![1];
+    /// reader.extend_from_slice(&42i32.to_le_bytes());
+    /// let mut reader = std::io::Cursor::new(reader);
+    ///
+    /// let optional = XbfOptional::deserialize_optional_type(&metadata, &mut reader).unwrap();
+    /// ```
+    pub fn deserialize_optional_type(
+        metadata: &XbfOptionalMetadata,
+        reader: &mut impl Read,
+    ) -> Result<XbfOptional> {
+        Self::deserialize_optional_type_with_config(metadata, reader, &XbfConfig::default())
+    }
+
+    /// Deserialize an optional using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_optional_type`] for more details.
+    pub fn deserialize_optional_type_with_config(
+        metadata: &XbfOptionalMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfOptional> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_optional_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_optional_type_with_context(
+        metadata: &XbfOptionalMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfOptional> {
+        reader.nested(|reader| {
+            let value = match reader.read_u8()? {
+                0 => None,
+                1 => Some(XbfType::deserialize_base_type_with_context(
+                    &metadata.inner_type,
+                    reader,
+                )?),
+                x => return Err(XbfError::InvalidPresenceByte(x)),
+            };
+            Ok(XbfOptional::new_unchecked(metadata.clone(), value))
+        })
+    }
+
+    /// Returns the metadata of the optional.
+    ///
+    /// Getting the metadata returns an owned [`XbfOptionalMetadata`], which only
+    /// clones the reference counted pointers it holds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptional;
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+    /// let optional = XbfOptional::new(metadata.clone(), None).unwrap();
+    ///
+    /// assert_eq!(optional.get_metadata(), metadata);
+    /// ```
+    pub fn get_metadata(&self) -> XbfOptionalMetadata {
+        self.metadata.clone()
+    }
+
+    /// Returns the number of bytes [`Self::serialize_optional_type`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_optional_type_with_config`] would write
+    /// using the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        1 + self
+            .value
+            .as_ref()
+            .map_or(0, |x| x.encoded_len_with_config(config))
+    }
+}
+
+impl XbfTypeUpcast for XbfOptional {}
+
+/// Error type for [`XbfOptional`]
+#[derive(Debug)]
+pub struct OptionalTypeMismatchError(String);
+
+impl OptionalTypeMismatchError {
+    fn new(expected_type: &XbfMetadata, actual_type: &XbfMetadata) -> OptionalTypeMismatchError {
+        let s = format!(
+            "provided value for optional is of type {actual_type:?}, expected {expected_type:?}"
+        );
+        OptionalTypeMismatchError(s)
+    }
+}
+
+impl Display for OptionalTypeMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for OptionalTypeMismatchError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{XbfPrimitive, XbfPrimitiveMetadata, XbfVec, XbfVecMetadata};
+    use std::io::Cursor;
+
+    #[test]
+    fn optional_new_fails_with_wrong_type() {
+        let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+        let err = XbfOptional::new(metadata, Some(XbfPrimitive::U32(69).into())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            OptionalTypeMismatchError::new(
+                &XbfPrimitiveMetadata::I32.into(),
+                &XbfPrimitiveMetadata::U32.into()
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn optional_serde_works() {
+        const TEST_NUM: i32 = 42;
+        let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+        let some =
+            XbfOptional::new(metadata.clone(), Some(XbfPrimitive::I32(TEST_NUM).into())).unwrap();
+        let none = XbfOptional::new(metadata.clone(), None).unwrap();
+        let mut writer = vec![];
+
+        some.serialize_optional_type(&mut writer).unwrap();
+        none.serialize_optional_type(&mut writer).unwrap();
+
+        let mut expected = vec![1];
+        expected.extend_from_slice(&TEST_NUM.to_le_bytes());
+        expected.push(0);
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+        let base_metadata = XbfMetadata::Optional(metadata);
+
+        let deserialized_some =
+            XbfType::deserialize_base_type(&base_metadata, &mut reader).unwrap();
+        let deserialized_none =
+            XbfType::deserialize_base_type(&base_metadata, &mut reader).unwrap();
+
+        assert_eq!(deserialized_some, some.into());
+        assert_eq!(deserialized_none, none.into());
+    }
+
+    #[test]
+    fn optional_of_vec_serde_works() {
+        let vec_metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into());
+        let metadata = XbfOptionalMetadata::new(vec_metadata.clone().into());
+        let vec = XbfVec::new(vec_metadata, vec![XbfPrimitive::U8(7).into()]).unwrap();
+        let optional = XbfOptional::new(metadata.clone(), Some(vec.into())).unwrap();
+        let mut writer = vec![];
+
+        optional.serialize_optional_type(&mut writer).unwrap();
+
+        let mut expected = vec![1];
+        expected.extend_from_slice(&1u16.to_le_bytes());
+        expected.push(7);
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+        let deserialized = XbfOptional::deserialize_optional_type(&metadata, &mut reader).unwrap();
+
+        assert_eq!(deserialized, optional);
+    }
+
+    #[test]
+    fn deserialize_invalid_presence_byte_fails() {
+        let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::U8.into());
+        let mut reader = Cursor::new(vec![2, 42]);
+
+        let err = XbfOptional::deserialize_optional_type(&metadata, &mut reader).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::InvalidPresenceByte(2)));
+    }
+
+    #[test]
+    fn upcast_works() {
+        let optional = XbfOptional::new(
+            XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into()),
+            Some(XbfPrimitive::I32(42).into()),
+        )
+        .unwrap();
+        let optional_ref = &optional;
+
+        assert_eq!(
+            XbfType::Optional(optional.clone()),
+            optional_ref.to_base_type()
+        );
+        assert_eq!(
+            XbfType::Optional(optional.clone()),
+            optional.into_base_type()
+        );
+    }
+}
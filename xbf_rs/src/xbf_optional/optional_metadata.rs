@@ -0,0 +1,215 @@
+use crate::{
+    util::ReadContext, Result, XbfConfig, XbfMetadata, XbfMetadataUpcast, XbfOptional,
+    STRUCT_METADATA_DISCRIMINANT,
+};
+use byteorder::WriteBytesExt;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+/// The metadata discriminant for an Optional type.
+///
+/// This is the same for all optionals regardless of their contents. It's value should always be
+/// equal to the discriminant value of the struct type plus one.
+pub const OPTIONAL_METADATA_DISCRIMINANT: u8 = STRUCT_METADATA_DISCRIMINANT + 1;
+
+/// Metadata for an Optional type.
+///
+/// Internally the metadata is stored on the heap to avoid having a recursive, infinitely sized
+/// type on the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XbfOptionalMetadata {
+    pub(crate) inner_type: Arc<XbfMetadata>,
+}
+
+impl XbfOptionalMetadata {
+    /// Creates a new Optional metadata.
+    ///
+    /// This will cause a heap allocation with the moved inner type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let inner_type = XbfPrimitiveMetadata::I32.into();
+    /// let metadata = XbfOptionalMetadata::new(inner_type);
+    /// ```
+    pub fn new(inner_type: XbfMetadata) -> Self {
+        Self {
+            inner_type: Arc::new(inner_type),
+        }
+    }
+
+    /// Creates a new Optional metadata from an already allocated inner type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let inner_type = XbfPrimitiveMetadata::I32.into();
+    /// let boxed_inner_type = Box::new(inner_type);
+    /// let metadata = XbfOptionalMetadata::from_boxed_type(boxed_inner_type);
+    /// ```
+    pub fn from_boxed_type(inner_type: Box<XbfMetadata>) -> Self {
+        Self {
+            inner_type: inner_type.into(),
+        }
+    }
+
+    /// Serialize Optional metadata as defined by the XBF specification.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfOptionalMetadata;
+    /// use xbf_rs::OPTIONAL_METADATA_DISCRIMINANT;
+    ///
+    /// let metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+    /// let mut writer = Vec::new();
+    /// metadata.serialize_optional_metadata(&mut writer).unwrap();
+    ///
+    /// assert_eq!(writer, [OPTIONAL_METADATA_DISCRIMINANT, XbfPrimitiveMetadata::I32 as u8]);
+    /// ```
+    pub fn serialize_optional_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_optional_metadata_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize Optional metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_optional_metadata`] for more details.
+    pub fn serialize_optional_metadata_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        writer.write_u8(OPTIONAL_METADATA_DISCRIMINANT)?;
+        self.inner_type
+            .serialize_base_metadata_with_config(writer, config)
+    }
+
+    /// Deserialize Optional metadata as defined by the XBF specification.
+    ///
+    /// This method assumes that you know for a fact you are about to receive Optional metadata.
+    /// If you do not know what sort of metadata you are receiving, use
+    /// [`deserialize_base_metadata`](crate::XbfMetadata::deserialize_base_metadata).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfOptionalMetadata;
+    ///
+    /// let data = vec![XbfPrimitiveMetadata::I32 as u8];
+    /// let mut reader = std::io::Cursor::new(data);
+    ///
+    /// let metadata = XbfOptionalMetadata::deserialize_optional_metadata(&mut reader).unwrap();
+    ///
+    /// assert_eq!(metadata, XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into()));
+    /// ```
+    pub fn deserialize_optional_metadata(reader: &mut impl Read) -> Result<XbfOptionalMetadata> {
+        Self::deserialize_optional_metadata_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize Optional metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_optional_metadata`] for more details.
+    pub fn deserialize_optional_metadata_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfOptionalMetadata> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_optional_metadata_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_optional_metadata_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfOptionalMetadata> {
+        reader.nested(|reader| {
+            let inner_type = XbfMetadata::deserialize_base_metadata_with_context(reader)?;
+            Ok(XbfOptionalMetadata::new(inner_type))
+        })
+    }
+
+    /// Returns the number of bytes [`Self::serialize_optional_metadata`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_optional_metadata_with_config`] would write using the given
+    /// [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        1 + self.inner_type.encoded_len_with_config(config)
+    }
+}
+
+impl XbfMetadataUpcast for XbfOptionalMetadata {}
+
+impl From<&XbfOptional> for XbfOptionalMetadata {
+    fn from(value: &XbfOptional) -> Self {
+        value.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{XbfPrimitiveMetadata, XbfVecMetadata, VEC_METADATA_DISCRIMINANT};
+    use std::io::Cursor;
+
+    #[test]
+    fn optional_metadata_serde_works() {
+        let optional_i32_metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+        let optional_vec_metadata = XbfOptionalMetadata::new(
+            XbfVecMetadata::new(XbfPrimitiveMetadata::String.into()).into(),
+        );
+        let mut writer = vec![];
+
+        optional_i32_metadata
+            .serialize_optional_metadata(&mut writer)
+            .unwrap();
+        optional_vec_metadata
+            .serialize_optional_metadata(&mut writer)
+            .unwrap();
+
+        assert_eq!(
+            writer,
+            vec![
+                OPTIONAL_METADATA_DISCRIMINANT,
+                XbfPrimitiveMetadata::I32 as u8,
+                OPTIONAL_METADATA_DISCRIMINANT,
+                VEC_METADATA_DISCRIMINANT,
+                XbfPrimitiveMetadata::String as u8,
+            ]
+        );
+
+        let mut reader = Cursor::new(writer);
+
+        let deserialized_i32 = XbfMetadata::deserialize_base_metadata(&mut reader).unwrap();
+        let deserialized_vec = XbfMetadata::deserialize_base_metadata(&mut reader).unwrap();
+
+        assert_eq!(deserialized_i32, optional_i32_metadata.into());
+        assert_eq!(deserialized_vec, optional_vec_metadata.into());
+    }
+
+    #[test]
+    fn upcast_works() {
+        let optional_metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+        let optional_metadata_ref = &optional_metadata;
+
+        assert_eq!(
+            XbfMetadata::Optional(optional_metadata.clone()),
+            optional_metadata_ref.to_base_metadata()
+        );
+        assert_eq!(
+            XbfMetadata::Optional(optional_metadata.clone()),
+            optional_metadata.into_base_metadata()
+        );
+    }
+}
@@ -1,7 +1,13 @@
 //! A primitive type as defined by the XBF specification.
 
+mod int256;
 mod primitive;
 mod primitive_metadata;
+mod primitive_ref;
 
+pub use int256::*;
 pub use primitive::*;
 pub use primitive_metadata::*;
+pub use primitive_ref::*;
+
+pub use half::{bf16, f16};
@@ -0,0 +1,987 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Debug, Display, LowerHex, UpperHex},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+    str::FromStr,
+};
+
+const SIGN_BIT: u64 = 1 << 63;
+
+/// An unsigned 256 bit integer, used for [`XbfPrimitive::U256`](crate::XbfPrimitive::U256).
+///
+/// The value is stored as four `u64` words with the least significant word first, which is also
+/// the order they are sent in. Parsing accepts decimal, or hexadecimal with a `0x` prefix, and
+/// the usual checked, wrapping and overflowing arithmetic is available. The arithmetic operators
+/// panic on overflow or division by zero.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::U256;
+///
+/// let balance: U256 = "115792089237316195423570985008687907853269984665640564039457"
+///     .parse()
+///     .unwrap();
+/// let fee = U256::from(1_000_000u128);
+///
+/// assert_eq!(balance, "0x12725dd1d243aba0e75fe645cc4873f9e65afe688c928e1f21".parse().unwrap());
+/// assert_eq!(
+///     (balance - fee).to_string(),
+///     "115792089237316195423570985008687907853269984665640563039457"
+/// );
+/// assert_eq!(balance.checked_mul(balance), None);
+/// assert_eq!(u128::try_from(fee), Ok(1_000_000));
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct U256([u64; 4]);
+
+/// A signed 256 bit integer, used for [`XbfPrimitive::I256`](crate::XbfPrimitive::I256).
+///
+/// The value is stored in two's complement as four `u64` words with the least significant word
+/// first, which is also the order they are sent in. Otherwise it behaves like [`U256`].
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::I256;
+///
+/// let debt: I256 = "-42".parse().unwrap();
+///
+/// assert!(debt.is_negative());
+/// assert_eq!(debt.to_words(), [u64::MAX - 41, u64::MAX, u64::MAX, u64::MAX]);
+/// assert_eq!(debt, I256::from(-42i128));
+/// assert_eq!(format!("{:x}", I256::from(-1i8)), "f".repeat(64));
+/// assert_eq!(I256::MIN.checked_neg(), None);
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct I256([u64; 4]);
+
+impl U256 {
+    /// The smallest value that can be represented, zero.
+    pub const MIN: Self = Self([0; 4]);
+    /// The largest value that can be represented, `2^256 - 1`.
+    pub const MAX: Self = Self([u64::MAX; 4]);
+    /// The value zero.
+    pub const ZERO: Self = Self([0; 4]);
+    /// The value one.
+    pub const ONE: Self = Self([1, 0, 0, 0]);
+
+    /// Creates a value from its words, least significant first.
+    pub const fn from_words(words: [u64; 4]) -> Self {
+        Self(words)
+    }
+
+    /// Returns the words of the value, least significant first.
+    pub const fn to_words(self) -> [u64; 4] {
+        self.0
+    }
+
+    /// Creates a value from its little endian bytes, the same bytes it is sent as.
+    pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+        Self(words_from_le_bytes(bytes))
+    }
+
+    /// Returns the little endian bytes of the value, the same bytes it is sent as.
+    pub fn to_le_bytes(self) -> [u8; 32] {
+        words_to_le_bytes(self.0)
+    }
+
+    /// Creates a value from its big endian bytes.
+    pub fn from_be_bytes(mut bytes: [u8; 32]) -> Self {
+        bytes.reverse();
+        Self::from_le_bytes(bytes)
+    }
+
+    /// Returns the big endian bytes of the value.
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = self.to_le_bytes();
+        bytes.reverse();
+        bytes
+    }
+
+    /// Returns `true` if the value is zero.
+    pub fn is_zero(self) -> bool {
+        self == Self::ZERO
+    }
+
+    /// Returns the number of leading zeros in the binary representation of the value.
+    pub fn leading_zeros(self) -> u32 {
+        let mut zeros = 0;
+        for word in self.0.into_iter().rev() {
+            zeros += word.leading_zeros();
+            if word != 0 {
+                break;
+            }
+        }
+        zeros
+    }
+
+    /// Parses a value from a string of digits in the given radix, with an optional `+` sign.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range from 2 to 36, the same as [`u64::from_str_radix`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::U256;
+    ///
+    /// assert_eq!(U256::from_str_radix("ff", 16), Ok(U256::from(255u8)));
+    /// assert!(U256::from_str_radix("-1", 10).is_err());
+    /// ```
+    pub fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseInt256Error> {
+        check_radix(radix);
+        parse_magnitude(src.strip_prefix('+').unwrap_or(src), radix)
+    }
+
+    /// Calculates `self + rhs`, returning whether the addition overflowed.
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let mut words = [0; 4];
+        let mut carry = false;
+        for (i, word) in words.iter_mut().enumerate() {
+            let (sum, carry1) = self.0[i].overflowing_add(rhs.0[i]);
+            let (sum, carry2) = sum.overflowing_add(u64::from(carry));
+            *word = sum;
+            carry = carry1 || carry2;
+        }
+        (Self(words), carry)
+    }
+
+    /// Calculates `self - rhs`, returning whether the subtraction overflowed.
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let mut words = [0; 4];
+        let mut borrow = false;
+        for (i, word) in words.iter_mut().enumerate() {
+            let (diff, borrow1) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (diff, borrow2) = diff.overflowing_sub(u64::from(borrow));
+            *word = diff;
+            borrow = borrow1 || borrow2;
+        }
+        (Self(words), borrow)
+    }
+
+    /// Calculates `self * rhs`, returning whether the multiplication overflowed.
+    pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        let mut words = [0; 4];
+        let mut overflow = false;
+        for i in 0..4 {
+            let mut carry = 0;
+            for j in 0..4 - i {
+                let sum =
+                    u128::from(self.0[i]) * u128::from(rhs.0[j]) + u128::from(words[i + j]) + carry;
+                words[i + j] = sum as u64;
+                carry = sum >> 64;
+            }
+            // anything left over, or any product of the words past the end, doesn't fit
+            overflow |= carry != 0 || (self.0[i] != 0 && rhs.0[4 - i..].iter().any(|x| *x != 0));
+        }
+        (Self(words), overflow)
+    }
+
+    /// Checked addition. Returns [`None`] if the result overflows.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        checked(self.overflowing_add(rhs))
+    }
+
+    /// Checked subtraction. Returns [`None`] if the result would be negative.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        checked(self.overflowing_sub(rhs))
+    }
+
+    /// Checked multiplication. Returns [`None`] if the result overflows.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        checked(self.overflowing_mul(rhs))
+    }
+
+    /// Checked division. Returns [`None`] if `rhs` is zero.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.checked_div_rem(rhs).map(|(quotient, _)| quotient)
+    }
+
+    /// Checked remainder. Returns [`None`] if `rhs` is zero.
+    pub fn checked_rem(self, rhs: Self) -> Option<Self> {
+        self.checked_div_rem(rhs).map(|(_, remainder)| remainder)
+    }
+
+    /// Wrapping addition, wrapping around at the boundary of the type.
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        self.overflowing_add(rhs).0
+    }
+
+    /// Wrapping subtraction, wrapping around at the boundary of the type.
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        self.overflowing_sub(rhs).0
+    }
+
+    /// Wrapping multiplication, wrapping around at the boundary of the type.
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        self.overflowing_mul(rhs).0
+    }
+
+    fn bit(self, i: u32) -> bool {
+        self.0[i as usize / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn checked_div_rem(self, rhs: Self) -> Option<(Self, Self)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        // binary long division, one bit of the quotient at a time
+        let mut quotient = Self::ZERO;
+        let mut remainder = Self::ZERO;
+        for i in (0..256 - self.leading_zeros()).rev() {
+            let carry = remainder.bit(255);
+            remainder = remainder.wrapping_add(remainder);
+            remainder.0[0] |= u64::from(self.bit(i));
+            if carry || remainder >= rhs {
+                remainder = remainder.wrapping_sub(rhs);
+                quotient.0[i as usize / 64] |= 1 << (i % 64);
+            }
+        }
+        Some((quotient, remainder))
+    }
+
+    fn div_rem_u64(self, rhs: u64) -> (Self, u64) {
+        let mut words = [0; 4];
+        let mut remainder = 0;
+        for i in (0..4).rev() {
+            let x = (u128::from(remainder) << 64) | u128::from(self.0[i]);
+            words[i] = (x / u128::from(rhs)) as u64;
+            remainder = (x % u128::from(rhs)) as u64;
+        }
+        (Self(words), remainder)
+    }
+
+    fn to_str_radix(mut self, radix: u32) -> String {
+        let mut digits = vec![];
+        loop {
+            let (quotient, digit) = self.div_rem_u64(u64::from(radix));
+            digits.push(char::from_digit(digit as u32, radix).expect("digit is below the radix"));
+            self = quotient;
+            if self.is_zero() {
+                return digits.into_iter().rev().collect();
+            }
+        }
+    }
+}
+
+impl I256 {
+    /// The smallest value that can be represented, `-2^255`.
+    pub const MIN: Self = Self([0, 0, 0, SIGN_BIT]);
+    /// The largest value that can be represented, `2^255 - 1`.
+    pub const MAX: Self = Self([u64::MAX, u64::MAX, u64::MAX, !SIGN_BIT]);
+    /// The value zero.
+    pub const ZERO: Self = Self([0; 4]);
+    /// The value one.
+    pub const ONE: Self = Self([1, 0, 0, 0]);
+
+    /// Creates a value from its two's complement words, least significant first.
+    pub const fn from_words(words: [u64; 4]) -> Self {
+        Self(words)
+    }
+
+    /// Returns the two's complement words of the value, least significant first.
+    pub const fn to_words(self) -> [u64; 4] {
+        self.0
+    }
+
+    /// Creates a value from its little endian bytes, the same bytes it is sent as.
+    pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+        Self(words_from_le_bytes(bytes))
+    }
+
+    /// Returns the little endian bytes of the value, the same bytes it is sent as.
+    pub fn to_le_bytes(self) -> [u8; 32] {
+        words_to_le_bytes(self.0)
+    }
+
+    /// Creates a value from its big endian bytes.
+    pub fn from_be_bytes(mut bytes: [u8; 32]) -> Self {
+        bytes.reverse();
+        Self::from_le_bytes(bytes)
+    }
+
+    /// Returns the big endian bytes of the value.
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = self.to_le_bytes();
+        bytes.reverse();
+        bytes
+    }
+
+    /// Returns `true` if the value is less than zero.
+    pub fn is_negative(self) -> bool {
+        self.0[3] & SIGN_BIT != 0
+    }
+
+    /// Returns `true` if the value is greater than zero.
+    pub fn is_positive(self) -> bool {
+        !self.is_negative() && self != Self::ZERO
+    }
+
+    /// Returns the absolute value as a [`U256`], which cannot overflow.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::I256;
+    /// use xbf_rs::U256;
+    ///
+    /// assert_eq!(I256::from(-7i8).unsigned_abs(), U256::from(7u8));
+    /// assert_eq!(I256::MIN.unsigned_abs().leading_zeros(), 0);
+    /// ```
+    pub fn unsigned_abs(self) -> U256 {
+        let bits = U256(self.0);
+        if self.is_negative() {
+            U256::ZERO.wrapping_sub(bits)
+        } else {
+            bits
+        }
+    }
+
+    /// Parses a value from a string of digits in the given radix, with an optional `+` or `-`
+    /// sign.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range from 2 to 36, the same as [`i64::from_str_radix`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::I256;
+    ///
+    /// assert_eq!(I256::from_str_radix("-ff", 16), Ok(I256::from(-255i16)));
+    /// ```
+    pub fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseInt256Error> {
+        check_radix(radix);
+        let (negative, digits) = split_sign(src);
+        Self::parse_signed(negative, digits, radix)
+    }
+
+    /// Calculates `self + rhs`, returning whether the addition overflowed.
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let sum = Self(U256(self.0).wrapping_add(U256(rhs.0)).0);
+        let overflow =
+            self.is_negative() == rhs.is_negative() && sum.is_negative() != self.is_negative();
+        (sum, overflow)
+    }
+
+    /// Calculates `self - rhs`, returning whether the subtraction overflowed.
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let diff = Self(U256(self.0).wrapping_sub(U256(rhs.0)).0);
+        let overflow =
+            self.is_negative() != rhs.is_negative() && diff.is_negative() != self.is_negative();
+        (diff, overflow)
+    }
+
+    /// Calculates `self * rhs`, returning whether the multiplication overflowed.
+    pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        let product = Self(U256(self.0).wrapping_mul(U256(rhs.0)).0);
+        let overflow = self
+            .unsigned_abs()
+            .checked_mul(rhs.unsigned_abs())
+            .and_then(|x| {
+                Self::from_sign_magnitude(self.is_negative() != rhs.is_negative(), x).ok()
+            })
+            .is_none();
+        (product, overflow)
+    }
+
+    /// Checked negation. Returns [`None`] if `self` is [`Self::MIN`].
+    pub fn checked_neg(self) -> Option<Self> {
+        Self::ZERO.checked_sub(self)
+    }
+
+    /// Checked absolute value. Returns [`None`] if `self` is [`Self::MIN`].
+    pub fn checked_abs(self) -> Option<Self> {
+        if self.is_negative() {
+            self.checked_neg()
+        } else {
+            Some(self)
+        }
+    }
+
+    /// Checked addition. Returns [`None`] if the result overflows.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        checked(self.overflowing_add(rhs))
+    }
+
+    /// Checked subtraction. Returns [`None`] if the result overflows.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        checked(self.overflowing_sub(rhs))
+    }
+
+    /// Checked multiplication. Returns [`None`] if the result overflows.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        checked(self.overflowing_mul(rhs))
+    }
+
+    /// Checked division, rounding towards zero. Returns [`None`] if `rhs` is zero or the result
+    /// overflows, which only happens for `I256::MIN / -1`.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        let quotient = self.unsigned_abs().checked_div(rhs.unsigned_abs())?;
+        Self::from_sign_magnitude(self.is_negative() != rhs.is_negative(), quotient).ok()
+    }
+
+    /// Checked remainder, which has the same sign as `self`. Returns [`None`] if `rhs` is zero
+    /// or the division overflows, which only happens for `I256::MIN % -1`.
+    pub fn checked_rem(self, rhs: Self) -> Option<Self> {
+        self.checked_div(rhs)?;
+        let remainder = self.unsigned_abs().checked_rem(rhs.unsigned_abs())?;
+        Self::from_sign_magnitude(self.is_negative(), remainder).ok()
+    }
+
+    /// Wrapping negation, where `-I256::MIN` wraps around to `I256::MIN`.
+    pub fn wrapping_neg(self) -> Self {
+        Self::ZERO.wrapping_sub(self)
+    }
+
+    /// Wrapping addition, wrapping around at the boundary of the type.
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        self.overflowing_add(rhs).0
+    }
+
+    /// Wrapping subtraction, wrapping around at the boundary of the type.
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        self.overflowing_sub(rhs).0
+    }
+
+    /// Wrapping multiplication, wrapping around at the boundary of the type.
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        self.overflowing_mul(rhs).0
+    }
+
+    fn parse_signed(negative: bool, digits: &str, radix: u32) -> Result<Self, ParseInt256Error> {
+        let magnitude = parse_magnitude(digits, radix).map_err(|e| match e {
+            ParseInt256Error::PosOverflow if negative => ParseInt256Error::NegOverflow,
+            e => e,
+        })?;
+        Self::from_sign_magnitude(negative, magnitude)
+    }
+
+    fn from_sign_magnitude(negative: bool, magnitude: U256) -> Result<Self, ParseInt256Error> {
+        if negative {
+            if magnitude > I256::MIN.unsigned_abs() {
+                return Err(ParseInt256Error::NegOverflow);
+            }
+            Ok(Self(U256::ZERO.wrapping_sub(magnitude).0))
+        } else if magnitude.bit(255) {
+            Err(ParseInt256Error::PosOverflow)
+        } else {
+            Ok(Self(magnitude.0))
+        }
+    }
+}
+
+fn words_from_le_bytes(bytes: [u8; 32]) -> [u64; 4] {
+    let mut words = [0; 4];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes(chunk.try_into().expect("chunks are 8 bytes"));
+    }
+    words
+}
+
+fn words_to_le_bytes(words: [u64; 4]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(8).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+fn checked<T>((value, overflow): (T, bool)) -> Option<T> {
+    (!overflow).then_some(value)
+}
+
+fn check_radix(radix: u32) {
+    assert!(
+        (2..=36).contains(&radix),
+        "radix must lie in the range `[2, 36]`, found {radix}"
+    );
+}
+
+fn split_sign(src: &str) -> (bool, &str) {
+    match src.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, src.strip_prefix('+').unwrap_or(src)),
+    }
+}
+
+/// Splits off a `0x` prefix, returning the radix of the remaining digits.
+fn split_radix(digits: &str) -> (&str, u32) {
+    match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(digits) => (digits, 16),
+        None => (digits, 10),
+    }
+}
+
+fn parse_magnitude(digits: &str, radix: u32) -> Result<U256, ParseInt256Error> {
+    if digits.is_empty() {
+        return Err(ParseInt256Error::Empty);
+    }
+    digits.chars().try_fold(U256::ZERO, |acc, c| {
+        let digit = c.to_digit(radix).ok_or(ParseInt256Error::InvalidDigit)?;
+        acc.checked_mul(U256::from(radix))
+            .and_then(|x| x.checked_add(U256::from(digit)))
+            .ok_or(ParseInt256Error::PosOverflow)
+    })
+}
+
+impl FromStr for U256 {
+    type Err = ParseInt256Error;
+
+    /// Parses a decimal value, or a hexadecimal one if it starts with `0x`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (digits, radix) = split_radix(s.strip_prefix('+').unwrap_or(s));
+        parse_magnitude(digits, radix)
+    }
+}
+
+impl FromStr for I256 {
+    type Err = ParseInt256Error;
+
+    /// Parses a decimal value, or a hexadecimal one if it starts with `0x` after the sign.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = split_sign(s);
+        let (digits, radix) = split_radix(digits);
+        Self::parse_signed(negative, digits, radix)
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for I256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // flipping the sign bit orders two's complement values the same as unsigned ones
+        let biased = |x: &Self| U256([x.0[0], x.0[1], x.0[2], x.0[3] ^ SIGN_BIT]);
+        biased(self).cmp(&biased(other))
+    }
+}
+
+impl PartialOrd for I256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad_integral(true, "", &self.to_str_radix(10))
+    }
+}
+
+impl Display for I256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad_integral(
+            !self.is_negative(),
+            "",
+            &self.unsigned_abs().to_str_radix(10),
+        )
+    }
+}
+
+impl Debug for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Debug for I256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+macro_rules! impl_hex {
+    ($ty:ty) => {
+        /// Formats the two's complement bits, the same as the primitive integers do.
+        impl LowerHex for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.pad_integral(true, "0x", &U256(self.0).to_str_radix(16))
+            }
+        }
+
+        /// Formats the two's complement bits, the same as the primitive integers do.
+        impl UpperHex for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let digits = U256(self.0).to_str_radix(16).to_ascii_uppercase();
+                f.pad_integral(true, "0x", &digits)
+            }
+        }
+    };
+}
+
+impl_hex!(U256);
+impl_hex!(I256);
+
+macro_rules! impl_op {
+    ($op:ident::$fn:ident, $assign:ident::$assign_fn:ident, $check:ident, $msg:literal) => {
+        impl_op!(U256, $op::$fn, $assign::$assign_fn, $check, $msg);
+        impl_op!(I256, $op::$fn, $assign::$assign_fn, $check, $msg);
+    };
+    (
+        $ty:ident, $op:ident::$fn:ident, $assign:ident::$assign_fn:ident, $check:ident, $msg:literal
+    ) => {
+        impl $op for $ty {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self::Output {
+                self.$check(rhs).expect($msg)
+            }
+        }
+
+        impl $assign for $ty {
+            fn $assign_fn(&mut self, rhs: Self) {
+                *self = $op::$fn(*self, rhs);
+            }
+        }
+    };
+}
+
+impl_op! { Add::add, AddAssign::add_assign, checked_add, "attempt to add with overflow" }
+impl_op! { Sub::sub, SubAssign::sub_assign, checked_sub, "attempt to subtract with overflow" }
+impl_op! { Mul::mul, MulAssign::mul_assign, checked_mul, "attempt to multiply with overflow" }
+impl_op! { Div::div, DivAssign::div_assign, checked_div, "attempt to divide by zero or overflow" }
+impl_op! { Rem::rem, RemAssign::rem_assign, checked_rem, "attempt to divide by zero or overflow" }
+
+impl Neg for I256 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.checked_neg().expect("attempt to negate with overflow")
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for U256 {
+            fn from(value: $ty) -> Self {
+                let value = value as u128;
+                Self([value as u64, (value >> 64) as u64, 0, 0])
+            }
+        }
+
+        impl From<$ty> for I256 {
+            fn from(value: $ty) -> Self {
+                Self(U256::from(value).0)
+            }
+        }
+
+        impl TryFrom<U256> for $ty {
+            type Error = TryFromInt256Error;
+
+            fn try_from(value: U256) -> Result<Self, Self::Error> {
+                let [low, high, 0, 0] = value.0 else {
+                    return Err(TryFromInt256Error);
+                };
+                let value = (u128::from(high) << 64) | u128::from(low);
+                <$ty>::try_from(value).map_err(|_| TryFromInt256Error)
+            }
+        }
+
+        impl TryFrom<I256> for $ty {
+            type Error = TryFromInt256Error;
+
+            fn try_from(value: I256) -> Result<Self, Self::Error> {
+                U256::try_from(value)?.try_into()
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_from_signed {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for I256 {
+            fn from(value: $ty) -> Self {
+                let value = value as i128;
+                let extension = if value < 0 { u64::MAX } else { 0 };
+                Self([value as u64, (value >> 64) as u64, extension, extension])
+            }
+        }
+
+        impl TryFrom<$ty> for U256 {
+            type Error = TryFromInt256Error;
+
+            fn try_from(value: $ty) -> Result<Self, Self::Error> {
+                u128::try_from(value).map(Self::from).map_err(|_| TryFromInt256Error)
+            }
+        }
+
+        impl TryFrom<I256> for $ty {
+            type Error = TryFromInt256Error;
+
+            fn try_from(value: I256) -> Result<Self, Self::Error> {
+                let extension = if value.is_negative() { u64::MAX } else { 0 };
+                let [low, high, a, b] = value.0;
+                if a != extension || b != extension || (high & SIGN_BIT != 0) != value.is_negative()
+                {
+                    return Err(TryFromInt256Error);
+                }
+                let value = ((u128::from(high) << 64) | u128::from(low)) as i128;
+                <$ty>::try_from(value).map_err(|_| TryFromInt256Error)
+            }
+        }
+
+        impl TryFrom<U256> for $ty {
+            type Error = TryFromInt256Error;
+
+            fn try_from(value: U256) -> Result<Self, Self::Error> {
+                <$ty>::try_from(I256::try_from(value)?)
+            }
+        }
+    )*};
+}
+
+impl_from_unsigned!(u8, u16, u32, u64, u128);
+impl_from_signed!(i8, i16, i32, i64, i128);
+
+impl TryFrom<I256> for U256 {
+    type Error = TryFromInt256Error;
+
+    fn try_from(value: I256) -> Result<Self, Self::Error> {
+        if value.is_negative() {
+            Err(TryFromInt256Error)
+        } else {
+            Ok(Self(value.0))
+        }
+    }
+}
+
+impl TryFrom<U256> for I256 {
+    type Error = TryFromInt256Error;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        if value.bit(255) {
+            Err(TryFromInt256Error)
+        } else {
+            Ok(Self(value.0))
+        }
+    }
+}
+
+/// Error type for parsing a [`U256`] or [`I256`] from a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseInt256Error {
+    /// The string has no digits.
+    Empty,
+    /// The string contains a character that isn't a digit in the radix.
+    InvalidDigit,
+    /// The value is too large to fit in the type.
+    PosOverflow,
+    /// The value is too small to fit in the type.
+    NegOverflow,
+}
+
+impl Display for ParseInt256Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseInt256Error::Empty => write!(f, "cannot parse integer from empty string"),
+            ParseInt256Error::InvalidDigit => write!(f, "invalid digit found in string"),
+            ParseInt256Error::PosOverflow => write!(f, "number too large to fit in target type"),
+            ParseInt256Error::NegOverflow => write!(f, "number too small to fit in target type"),
+        }
+    }
+}
+
+impl Error for ParseInt256Error {}
+
+/// Error type for a checked conversion to or from a [`U256`] or [`I256`] where the value is out
+/// of range of the target type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TryFromInt256Error;
+
+impl Display for TryFromInt256Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "out of range integral type conversion attempted")
+    }
+}
+
+impl Error for TryFromInt256Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const U256_MAX: &str =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    const I256_MIN: &str =
+        "-57896044618658097711785492504343953926634992332820282019728792003956564819968";
+
+    #[test]
+    fn u256_parse_and_display_round_trip() {
+        assert_eq!(U256_MAX.parse::<U256>(), Ok(U256::MAX));
+        assert_eq!(U256::MAX.to_string(), U256_MAX);
+        assert_eq!(U256::ZERO.to_string(), "0");
+        assert_eq!(format!("{:x}", U256::MAX), "f".repeat(64));
+        assert_eq!(format!("{:#X}", U256::from(255u8)), "0xFF");
+        assert_eq!(format!("{:>5}", U256::from(42u8)), "   42");
+        assert_eq!(format!("{:?}", U256::from(42u8)), "42");
+        assert_eq!(
+            "0x".to_string() + &"f".repeat(64),
+            format!("{:#x}", U256::MAX)
+        );
+        assert_eq!(
+            ("0x".to_string() + &"f".repeat(64)).parse::<U256>(),
+            Ok(U256::MAX)
+        );
+        assert_eq!(U256::from_str_radix("+101", 2), Ok(U256::from(5u8)));
+    }
+
+    #[test]
+    fn u256_parse_errors() {
+        assert_eq!("".parse::<U256>(), Err(ParseInt256Error::Empty));
+        assert_eq!("0x".parse::<U256>(), Err(ParseInt256Error::Empty));
+        assert_eq!("-1".parse::<U256>(), Err(ParseInt256Error::InvalidDigit));
+        assert_eq!("12a".parse::<U256>(), Err(ParseInt256Error::InvalidDigit));
+        let too_large = U256_MAX.replace("935", "936");
+        assert_eq!(
+            too_large.parse::<U256>(),
+            Err(ParseInt256Error::PosOverflow)
+        );
+    }
+
+    #[test]
+    fn i256_parse_and_display_round_trip() {
+        assert_eq!(I256_MIN.parse::<I256>(), Ok(I256::MIN));
+        assert_eq!(I256::MIN.to_string(), I256_MIN);
+        assert_eq!(I256::MAX.to_string(), I256_MIN[1..].replace("968", "967"));
+        assert_eq!("-0x10".parse::<I256>(), Ok(I256::from(-16i8)));
+        assert_eq!("+7".parse::<I256>(), Ok(I256::from(7u8)));
+        assert_eq!(format!("{:+}", I256::from(7u8)), "+7");
+        assert_eq!(format!("{:05}", I256::from(-7i8)), "-0007");
+        assert_eq!(format!("{:x}", I256::MIN), format!("8{}", "0".repeat(63)));
+    }
+
+    #[test]
+    fn i256_parse_errors() {
+        assert_eq!("-".parse::<I256>(), Err(ParseInt256Error::Empty));
+        let too_small = I256_MIN.replace("968", "969");
+        assert_eq!(
+            too_small.parse::<I256>(),
+            Err(ParseInt256Error::NegOverflow)
+        );
+        let far_too_small = format!("-{}", "9".repeat(100));
+        assert_eq!(
+            far_too_small.parse::<I256>(),
+            Err(ParseInt256Error::NegOverflow)
+        );
+        assert_eq!(
+            I256::from_str_radix(&far_too_small, 10),
+            Err(ParseInt256Error::NegOverflow)
+        );
+        assert_eq!(
+            I256_MIN[1..].parse::<I256>(),
+            Err(ParseInt256Error::PosOverflow)
+        );
+        assert_eq!(
+            far_too_small[1..].parse::<I256>(),
+            Err(ParseInt256Error::PosOverflow)
+        );
+    }
+
+    #[test]
+    fn i256_is_twos_complement() {
+        assert_eq!(I256::from(-1i8).to_words(), [u64::MAX; 4]);
+        assert_eq!(
+            I256::from(i128::MIN).to_words(),
+            [0, SIGN_BIT, u64::MAX, u64::MAX]
+        );
+        assert_eq!(I256::from(u128::MAX).to_words(), [u64::MAX, u64::MAX, 0, 0]);
+        assert_eq!(I256::from_le_bytes([0xff; 32]), I256::from(-1i8));
+        assert_eq!(I256::from(-2i8).to_be_bytes()[31], 0xfe);
+        assert!(I256::MIN < I256::from(-1i8));
+        assert!(I256::from(-1i8) < I256::ZERO);
+        assert!(I256::ZERO < I256::MAX);
+        assert_eq!(
+            I256::MIN.unsigned_abs(),
+            U256::from_words([0, 0, 0, SIGN_BIT])
+        );
+    }
+
+    #[test]
+    fn checked_conversions() {
+        assert_eq!(u128::try_from(U256::from(u128::MAX)), Ok(u128::MAX));
+        assert_eq!(u128::try_from(U256::MAX), Err(TryFromInt256Error));
+        assert_eq!(u8::try_from(U256::from(256u16)), Err(TryFromInt256Error));
+        assert_eq!(i128::try_from(I256::from(i128::MIN)), Ok(i128::MIN));
+        assert_eq!(
+            i128::try_from(I256::from(u128::MAX)),
+            Err(TryFromInt256Error)
+        );
+        assert_eq!(
+            i128::try_from(I256::from(i128::MIN).wrapping_sub(I256::ONE)),
+            Err(TryFromInt256Error)
+        );
+        assert_eq!(i8::try_from(I256::from(-128i16)), Ok(-128));
+        assert_eq!(u64::try_from(I256::from(-1i8)), Err(TryFromInt256Error));
+        assert_eq!(i64::try_from(U256::from(7u8)), Ok(7));
+        assert_eq!(U256::try_from(-1i128), Err(TryFromInt256Error));
+        assert_eq!(
+            U256::try_from(I256::MAX).map(I256::try_from),
+            Ok(Ok(I256::MAX))
+        );
+        assert_eq!(I256::try_from(U256::MAX), Err(TryFromInt256Error));
+    }
+
+    #[test]
+    fn u256_arithmetic() {
+        let x = U256::from(u128::MAX);
+        assert_eq!(x + U256::ONE, U256::from_words([0, 0, 1, 0]));
+        assert_eq!(x * x, U256::from_words([1, 0, u64::MAX - 1, u64::MAX]));
+        assert_eq!(U256::MAX.checked_add(U256::ONE), None);
+        assert_eq!(U256::ZERO.checked_sub(U256::ONE), None);
+        assert_eq!(U256::MAX.wrapping_add(U256::ONE), U256::ZERO);
+        assert_eq!(U256::MAX.checked_mul(U256::from(2u8)), None);
+        assert_eq!(
+            U256::from_words([0, 0, 0, 1]).checked_mul(U256::from_words([0, 1, 0, 0])),
+            None
+        );
+        assert_eq!(U256::MAX / U256::MAX, U256::ONE);
+        assert_eq!(U256::MAX % U256::from(10u8), U256::from(5u8));
+        assert_eq!(U256::MAX / (x + U256::ONE), x);
+        assert_eq!(U256::ONE.checked_div(U256::ZERO), None);
+
+        let big: U256 = "123456789012345678901234567890123456789012345678901234567890"
+            .parse()
+            .unwrap();
+        let divisor: U256 = "98765432109876543210987654321".parse().unwrap();
+        let (quotient, remainder) = (big / divisor, big % divisor);
+        assert!(remainder < divisor);
+        assert_eq!(quotient * divisor + remainder, big);
+    }
+
+    #[test]
+    fn i256_arithmetic() {
+        let minus_one = I256::from(-1i8);
+        assert_eq!(I256::from(-5i8) + I256::from(3u8), I256::from(-2i8));
+        assert_eq!(I256::from(-5i8) * I256::from(-3i8), I256::from(15u8));
+        assert_eq!(I256::from(-7i8) / I256::from(2u8), I256::from(-3i8));
+        assert_eq!(I256::from(-7i8) % I256::from(2u8), minus_one);
+        assert_eq!(I256::from(7u8) % I256::from(-2i8), I256::ONE);
+        assert_eq!(-I256::MAX - I256::ONE, I256::MIN);
+        assert_eq!(I256::MIN.checked_sub(I256::ONE), None);
+        assert_eq!(I256::MAX.checked_add(I256::ONE), None);
+        assert_eq!(I256::MIN.checked_div(minus_one), None);
+        assert_eq!(I256::MIN.checked_rem(minus_one), None);
+        assert_eq!(I256::MIN.checked_mul(minus_one), None);
+        assert_eq!(I256::MIN.checked_mul(I256::ONE), Some(I256::MIN));
+        assert_eq!(I256::MIN.wrapping_neg(), I256::MIN);
+        assert_eq!(I256::MIN.checked_abs(), None);
+        assert!(minus_one.is_negative() && !minus_one.is_positive());
+    }
+}
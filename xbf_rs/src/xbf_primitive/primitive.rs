@@ -1,14 +1,48 @@
 use crate::{
-    util::{read_bytes, read_string, write_bytes, write_string},
-    XbfPrimitiveMetadata, XbfTypeUpcast,
+    bf16, f16,
+    util::{bytes_encoded_len, read_bytes, read_string, write_bytes, write_string, ReadContext},
+    Result, XbfConfig, XbfError, XbfMetadata, XbfPrimitiveMetadata, XbfType, XbfTypeUpcast, I256,
+    U256,
 };
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{self, Read, Write};
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{Read, Write},
+};
+
+macro_rules! primitive_accessor {
+    ($as:ident, $ty:ty, $xbf_type:tt) => {
+        #[doc = concat!("Returns the value if this is a [`XbfPrimitive::", stringify!($xbf_type), "`].")]
+        pub fn $as(&self) -> Option<$ty> {
+            match self {
+                XbfPrimitive::$xbf_type(x) => Some(*x),
+                _ => None,
+            }
+        }
+    };
+}
 
 /// A primitive type as defined by the XBF specification.
 ///
-/// Each XBF primitive maps to the corresponding Rust type, with the exception of 256 bit numbers,
-/// which are represented as a `[u64; 4]`.
+/// Each XBF primitive maps to the corresponding Rust type, with 256 bit numbers represented by
+/// [`U256`] and [`I256`], and 16-bit floats by [`f16`](struct@f16) and [`bf16`], which convert to
+/// and from `f32` with `from_f32` and `to_f32`. A char is sent as its Unicode scalar value in a
+/// `u32`.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::f16;
+/// use xbf_rs::XbfPrimitive;
+///
+/// let primitive = XbfPrimitive::F16(f16::from_f32(0.5));
+/// let mut writer = vec![];
+/// primitive.serialize_primitive_type(&mut writer).unwrap();
+///
+/// assert_eq!(writer, 0x3800u16.to_le_bytes());
+/// assert_eq!(primitive.as_f16().map(f16::to_f32), Some(0.5));
+/// ```
 #[derive(Debug, Clone, PartialEq)]
 pub enum XbfPrimitive {
     Bool(bool),
@@ -17,17 +51,20 @@ pub enum XbfPrimitive {
     U32(u32),
     U64(u64),
     U128(u128),
-    U256([u64; 4]),
+    U256(U256),
     I8(i8),
     I16(i16),
     I32(i32),
     I64(i64),
     I128(i128),
-    I256([u64; 4]),
+    I256(I256),
     F32(f32),
     F64(f64),
     Bytes(Vec<u8>),
     String(String),
+    Char(char),
+    F16(f16),
+    BF16(bf16),
 }
 
 impl XbfPrimitive {
@@ -37,6 +74,13 @@ impl XbfPrimitive {
     /// metadata, convert this type to a [`XbfPrimitiveMetadata`] and call
     /// [`XbfPrimitiveMetadata::serialize_primitive_metadata`].
     ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns
+    /// [`XbfError::LengthOverflow`](crate::XbfError::LengthOverflow) if the length of a
+    /// [`XbfPrimitive::Bytes`] or [`XbfPrimitive::String`] does not fit in an unsigned 16-bit
+    /// integer. Use [`Self::serialize_primitive_type_with_config`] to send longer values.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -48,30 +92,41 @@ impl XbfPrimitive {
     ///
     /// assert_eq!(writer, 420u64.to_le_bytes());
     /// ```
-    pub fn serialize_primitive_type(&self, writer: &mut impl Write) -> io::Result<()> {
+    pub fn serialize_primitive_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_primitive_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize a primitive type using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_primitive_type`] for more details.
+    pub fn serialize_primitive_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
         match self {
-            XbfPrimitive::Bool(x) => writer.write_u8(u8::from(*x)),
-            XbfPrimitive::U8(x) => writer.write_u8(*x),
-            XbfPrimitive::U16(x) => writer.write_u16::<LittleEndian>(*x),
-            XbfPrimitive::U32(x) => writer.write_u32::<LittleEndian>(*x),
-            XbfPrimitive::U64(x) => writer.write_u64::<LittleEndian>(*x),
-            XbfPrimitive::U128(x) => writer.write_u128::<LittleEndian>(*x),
-            XbfPrimitive::U256(x) => x
-                .iter()
-                .try_for_each(|x| writer.write_u64::<LittleEndian>(*x)),
-            XbfPrimitive::I8(x) => writer.write_i8(*x),
-            XbfPrimitive::I16(x) => writer.write_i16::<LittleEndian>(*x),
-            XbfPrimitive::I32(x) => writer.write_i32::<LittleEndian>(*x),
-            XbfPrimitive::I64(x) => writer.write_i64::<LittleEndian>(*x),
-            XbfPrimitive::I128(x) => writer.write_i128::<LittleEndian>(*x),
-            XbfPrimitive::I256(x) => x
-                .iter()
-                .try_for_each(|x| writer.write_u64::<LittleEndian>(*x)),
-            XbfPrimitive::F32(x) => writer.write_f32::<LittleEndian>(*x),
-            XbfPrimitive::F64(x) => writer.write_f64::<LittleEndian>(*x),
-            XbfPrimitive::Bytes(x) => write_bytes(x, writer),
-            XbfPrimitive::String(x) => write_string(x, writer),
+            XbfPrimitive::Bool(x) => writer.write_u8(u8::from(*x))?,
+            XbfPrimitive::U8(x) => writer.write_u8(*x)?,
+            XbfPrimitive::U16(x) => writer.write_u16::<LittleEndian>(*x)?,
+            XbfPrimitive::U32(x) => writer.write_u32::<LittleEndian>(*x)?,
+            XbfPrimitive::U64(x) => writer.write_u64::<LittleEndian>(*x)?,
+            XbfPrimitive::U128(x) => writer.write_u128::<LittleEndian>(*x)?,
+            XbfPrimitive::U256(x) => writer.write_all(&x.to_le_bytes())?,
+            XbfPrimitive::I8(x) => writer.write_i8(*x)?,
+            XbfPrimitive::I16(x) => writer.write_i16::<LittleEndian>(*x)?,
+            XbfPrimitive::I32(x) => writer.write_i32::<LittleEndian>(*x)?,
+            XbfPrimitive::I64(x) => writer.write_i64::<LittleEndian>(*x)?,
+            XbfPrimitive::I128(x) => writer.write_i128::<LittleEndian>(*x)?,
+            XbfPrimitive::I256(x) => writer.write_all(&x.to_le_bytes())?,
+            XbfPrimitive::F32(x) => writer.write_f32::<LittleEndian>(*x)?,
+            XbfPrimitive::F64(x) => writer.write_f64::<LittleEndian>(*x)?,
+            XbfPrimitive::Bytes(x) => write_bytes(x, writer, config)?,
+            XbfPrimitive::String(x) => write_string(x, writer, config)?,
+            XbfPrimitive::Char(x) => writer.write_u32::<LittleEndian>(u32::from(*x))?,
+            XbfPrimitive::F16(x) => writer.write_u16::<LittleEndian>(x.to_bits())?,
+            XbfPrimitive::BF16(x) => writer.write_u16::<LittleEndian>(x.to_bits())?,
         }
+        Ok(())
     }
 
     /// Deserialize a primitive type as defined by the XBF specification.
@@ -95,42 +150,68 @@ impl XbfPrimitive {
     pub fn deserialize_primitive_type(
         primitive_metadata: &XbfPrimitiveMetadata,
         reader: &mut impl Read,
-    ) -> io::Result<XbfPrimitive> {
-        match primitive_metadata {
-            XbfPrimitiveMetadata::Bool => reader.read_u8().map(|x| XbfPrimitive::Bool(x != 0)),
-            XbfPrimitiveMetadata::U8 => reader.read_u8().map(XbfPrimitive::U8),
-            XbfPrimitiveMetadata::U16 => reader.read_u16::<LittleEndian>().map(XbfPrimitive::U16),
-            XbfPrimitiveMetadata::U32 => reader.read_u32::<LittleEndian>().map(XbfPrimitive::U32),
-            XbfPrimitiveMetadata::U64 => reader.read_u64::<LittleEndian>().map(XbfPrimitive::U64),
-            XbfPrimitiveMetadata::U128 => {
-                reader.read_u128::<LittleEndian>().map(XbfPrimitive::U128)
-            }
+    ) -> Result<XbfPrimitive> {
+        Self::deserialize_primitive_type_with_config(
+            primitive_metadata,
+            reader,
+            &XbfConfig::default(),
+        )
+    }
+
+    /// Deserialize a primitive type using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_primitive_type`] for more details.
+    pub fn deserialize_primitive_type_with_config(
+        primitive_metadata: &XbfPrimitiveMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfPrimitive> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_primitive_type_with_context(primitive_metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_primitive_type_with_context(
+        primitive_metadata: &XbfPrimitiveMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfPrimitive> {
+        Ok(match primitive_metadata {
+            XbfPrimitiveMetadata::Bool => XbfPrimitive::Bool(reader.read_u8()? != 0),
+            XbfPrimitiveMetadata::U8 => XbfPrimitive::U8(reader.read_u8()?),
+            XbfPrimitiveMetadata::U16 => XbfPrimitive::U16(reader.read_u16::<LittleEndian>()?),
+            XbfPrimitiveMetadata::U32 => XbfPrimitive::U32(reader.read_u32::<LittleEndian>()?),
+            XbfPrimitiveMetadata::U64 => XbfPrimitive::U64(reader.read_u64::<LittleEndian>()?),
+            XbfPrimitiveMetadata::U128 => XbfPrimitive::U128(reader.read_u128::<LittleEndian>()?),
             XbfPrimitiveMetadata::U256 => {
-                let mut data = [0; 4];
-                for i in &mut data {
-                    *i = reader.read_u64::<LittleEndian>()?
-                }
-                Ok(XbfPrimitive::U256(data))
-            }
-            XbfPrimitiveMetadata::I8 => reader.read_i8().map(XbfPrimitive::I8),
-            XbfPrimitiveMetadata::I16 => reader.read_i16::<LittleEndian>().map(XbfPrimitive::I16),
-            XbfPrimitiveMetadata::I32 => reader.read_i32::<LittleEndian>().map(XbfPrimitive::I32),
-            XbfPrimitiveMetadata::I64 => reader.read_i64::<LittleEndian>().map(XbfPrimitive::I64),
-            XbfPrimitiveMetadata::I128 => {
-                reader.read_i128::<LittleEndian>().map(XbfPrimitive::I128)
+                let mut bytes = [0; 32];
+                reader.read_exact(&mut bytes)?;
+                XbfPrimitive::U256(U256::from_le_bytes(bytes))
             }
+            XbfPrimitiveMetadata::I8 => XbfPrimitive::I8(reader.read_i8()?),
+            XbfPrimitiveMetadata::I16 => XbfPrimitive::I16(reader.read_i16::<LittleEndian>()?),
+            XbfPrimitiveMetadata::I32 => XbfPrimitive::I32(reader.read_i32::<LittleEndian>()?),
+            XbfPrimitiveMetadata::I64 => XbfPrimitive::I64(reader.read_i64::<LittleEndian>()?),
+            XbfPrimitiveMetadata::I128 => XbfPrimitive::I128(reader.read_i128::<LittleEndian>()?),
             XbfPrimitiveMetadata::I256 => {
-                let mut data = [0; 4];
-                for i in &mut data {
-                    *i = reader.read_u64::<LittleEndian>()?
-                }
-                Ok(XbfPrimitive::I256(data))
+                let mut bytes = [0; 32];
+                reader.read_exact(&mut bytes)?;
+                XbfPrimitive::I256(I256::from_le_bytes(bytes))
             }
-            XbfPrimitiveMetadata::F32 => reader.read_f32::<LittleEndian>().map(XbfPrimitive::F32),
-            XbfPrimitiveMetadata::F64 => reader.read_f64::<LittleEndian>().map(XbfPrimitive::F64),
-            XbfPrimitiveMetadata::Bytes => read_bytes(reader).map(XbfPrimitive::Bytes),
-            XbfPrimitiveMetadata::String => read_string(reader).map(XbfPrimitive::String),
-        }
+            XbfPrimitiveMetadata::F32 => XbfPrimitive::F32(reader.read_f32::<LittleEndian>()?),
+            XbfPrimitiveMetadata::F64 => XbfPrimitive::F64(reader.read_f64::<LittleEndian>()?),
+            XbfPrimitiveMetadata::Bytes => XbfPrimitive::Bytes(read_bytes(reader)?),
+            XbfPrimitiveMetadata::String => XbfPrimitive::String(read_string(reader)?),
+            XbfPrimitiveMetadata::Char => {
+                let x = reader.read_u32::<LittleEndian>()?;
+                XbfPrimitive::Char(char::from_u32(x).ok_or(XbfError::InvalidChar(x))?)
+            }
+            XbfPrimitiveMetadata::F16 => {
+                XbfPrimitive::F16(f16::from_bits(reader.read_u16::<LittleEndian>()?))
+            }
+            XbfPrimitiveMetadata::BF16 => {
+                XbfPrimitive::BF16(bf16::from_bits(reader.read_u16::<LittleEndian>()?))
+            }
+        })
     }
 
     /// Get the metadata for this primitive type.
@@ -152,6 +233,86 @@ impl XbfPrimitive {
     pub fn get_metadata(&self) -> XbfPrimitiveMetadata {
         XbfPrimitiveMetadata::from(self)
     }
+
+    /// Returns the number of bytes [`Self::serialize_primitive_type`] would write.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    ///
+    /// assert_eq!(XbfPrimitive::U32(42).encoded_len(), 4);
+    /// // a u16 length followed by the bytes
+    /// assert_eq!(XbfPrimitive::String("hello".to_string()).encoded_len(), 7);
+    /// ```
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_primitive_type_with_config`] would write
+    /// using the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        match self {
+            XbfPrimitive::Bytes(x) => bytes_encoded_len(x.len(), config),
+            XbfPrimitive::String(x) => bytes_encoded_len(x.len(), config),
+            x => XbfPrimitiveMetadata::from(x)
+                .fixed_size()
+                .expect("only bytes and strings vary in size"),
+        }
+    }
+
+    primitive_accessor!(as_bool, bool, Bool);
+    primitive_accessor!(as_u8, u8, U8);
+    primitive_accessor!(as_u16, u16, U16);
+    primitive_accessor!(as_u32, u32, U32);
+    primitive_accessor!(as_u64, u64, U64);
+    primitive_accessor!(as_u128, u128, U128);
+    primitive_accessor!(as_u256, U256, U256);
+    primitive_accessor!(as_i8, i8, I8);
+    primitive_accessor!(as_i16, i16, I16);
+    primitive_accessor!(as_i32, i32, I32);
+    primitive_accessor!(as_i64, i64, I64);
+    primitive_accessor!(as_i128, i128, I128);
+    primitive_accessor!(as_i256, I256, I256);
+    primitive_accessor!(as_f32, f32, F32);
+    primitive_accessor!(as_f64, f64, F64);
+    primitive_accessor!(as_char, char, Char);
+    primitive_accessor!(as_f16, f16, F16);
+    primitive_accessor!(as_bf16, bf16, BF16);
+
+    /// Returns the bytes if this is a [`XbfPrimitive::Bytes`].
+    ///
+    /// None of the accessors convert between types, so this returns [`None`] for a
+    /// [`XbfPrimitive::String`], and [`Self::as_u64`] returns [`None`] for a
+    /// [`XbfPrimitive::U32`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    ///
+    /// let bytes = XbfPrimitive::Bytes(vec![1, 2, 3]);
+    /// let string = XbfPrimitive::String("hello".to_string());
+    ///
+    /// assert_eq!(bytes.as_bytes(), Some(&[1, 2, 3][..]));
+    /// assert_eq!(string.as_bytes(), None);
+    /// assert_eq!(string.as_str(), Some("hello"));
+    /// assert_eq!(XbfPrimitive::U32(7).as_u64(), None);
+    /// ```
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            XbfPrimitive::Bytes(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns the string if this is a [`XbfPrimitive::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            XbfPrimitive::String(x) => Some(x),
+            _ => None,
+        }
+    }
 }
 
 impl XbfTypeUpcast for XbfPrimitive {}
@@ -162,7 +323,9 @@ impl XbfTypeUpcast for XbfPrimitive {}
 //
 /// A trait for converting native Rust types to [`XbfPrimitive`] types.
 ///
-/// This trait is implemented for all of the supported types that have an XBF equivalent.
+/// This trait is implemented for all of the supported types that have an XBF equivalent. Each of
+/// these types also implements [`TryFrom`] for [`XbfPrimitive`] and [`XbfType`], by value and by
+/// reference, to get the native value back out.
 ///
 /// You should not implement this trait yourself.
 pub trait NativeToXbfPrimitive: Into<XbfPrimitive>
@@ -224,6 +387,62 @@ macro_rules! impl_NativeToXbfPrimitive {
         }
 
         impl NativeToXbfPrimitive for $ty {}
+
+        impl TryFrom<XbfPrimitive> for $ty {
+            type Error = PrimitiveMismatchError;
+
+            fn try_from(value: XbfPrimitive) -> Result<Self, Self::Error> {
+                match value {
+                    XbfPrimitive::$xbf_type(x) => Ok(x),
+                    x => Err(PrimitiveMismatchError::new(
+                        XbfPrimitiveMetadata::$xbf_type,
+                        x.get_metadata().into(),
+                    )),
+                }
+            }
+        }
+
+        impl TryFrom<&XbfPrimitive> for $ty {
+            type Error = PrimitiveMismatchError;
+
+            fn try_from(value: &XbfPrimitive) -> Result<Self, Self::Error> {
+                match value {
+                    XbfPrimitive::$xbf_type(x) => Ok(x.clone()),
+                    x => Err(PrimitiveMismatchError::new(
+                        XbfPrimitiveMetadata::$xbf_type,
+                        x.get_metadata().into(),
+                    )),
+                }
+            }
+        }
+
+        impl TryFrom<XbfType> for $ty {
+            type Error = PrimitiveMismatchError;
+
+            fn try_from(value: XbfType) -> Result<Self, Self::Error> {
+                match value {
+                    XbfType::Primitive(x) => x.try_into(),
+                    x => Err(PrimitiveMismatchError::new(
+                        XbfPrimitiveMetadata::$xbf_type,
+                        XbfMetadata::from(&x),
+                    )),
+                }
+            }
+        }
+
+        impl TryFrom<&XbfType> for $ty {
+            type Error = PrimitiveMismatchError;
+
+            fn try_from(value: &XbfType) -> Result<Self, Self::Error> {
+                match value {
+                    XbfType::Primitive(x) => x.try_into(),
+                    x => Err(PrimitiveMismatchError::new(
+                        XbfPrimitiveMetadata::$xbf_type,
+                        XbfMetadata::from(x),
+                    )),
+                }
+            }
+        }
     };
 }
 
@@ -233,20 +452,74 @@ impl_NativeToXbfPrimitive!(u16, U16);
 impl_NativeToXbfPrimitive!(u32, U32);
 impl_NativeToXbfPrimitive!(u64, U64);
 impl_NativeToXbfPrimitive!(u128, U128);
+impl_NativeToXbfPrimitive!(U256, U256);
 impl_NativeToXbfPrimitive!(i8, I8);
 impl_NativeToXbfPrimitive!(i16, I16);
 impl_NativeToXbfPrimitive!(i32, I32);
 impl_NativeToXbfPrimitive!(i64, I64);
 impl_NativeToXbfPrimitive!(i128, I128);
+impl_NativeToXbfPrimitive!(I256, I256);
 impl_NativeToXbfPrimitive!(f32, F32);
 impl_NativeToXbfPrimitive!(f64, F64);
 impl_NativeToXbfPrimitive!(Vec<u8>, Bytes);
 impl_NativeToXbfPrimitive!(String, String);
+impl_NativeToXbfPrimitive!(char, Char);
+impl_NativeToXbfPrimitive!(f16, F16);
+impl_NativeToXbfPrimitive!(bf16, BF16);
+
+/// Error type for converting an [`XbfPrimitive`] or [`XbfType`] to a native Rust type.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::XbfMetadata;
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfPrimitiveMetadata;
+///
+/// let err = i32::try_from(XbfPrimitive::U8(1)).unwrap_err();
+///
+/// assert_eq!(err.expected(), XbfPrimitiveMetadata::I32);
+/// assert_eq!(err.actual(), &XbfMetadata::from(XbfPrimitiveMetadata::U8));
+/// assert_eq!(err.to_string(), "expected I32, found U8");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimitiveMismatchError {
+    expected: XbfPrimitiveMetadata,
+    actual: XbfMetadata,
+}
+
+impl PrimitiveMismatchError {
+    pub(crate) fn new(expected: XbfPrimitiveMetadata, actual: XbfMetadata) -> Self {
+        Self { expected, actual }
+    }
+
+    /// Returns the type that was asked for.
+    pub fn expected(&self) -> XbfPrimitiveMetadata {
+        self.expected
+    }
+
+    /// Returns the type the value actually had. This is only something other than an
+    /// [`XbfMetadata::Primitive`] when converting from an [`XbfType`].
+    pub fn actual(&self) -> &XbfMetadata {
+        &self.actual
+    }
+}
+
+impl Display for PrimitiveMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.actual {
+            XbfMetadata::Primitive(x) => write!(f, "expected {:?}, found {x:?}", self.expected),
+            x => write!(f, "expected {:?}, found {x:?}", self.expected),
+        }
+    }
+}
+
+impl Error for PrimitiveMismatchError {}
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{XbfMetadata, XbfType};
+    use crate::{LengthEncoding, XbfVec, XbfVecMetadata};
     use std::io::Cursor;
 
     macro_rules! serde_primitive_test {
@@ -302,7 +575,7 @@ mod test {
     #[test]
     fn u256_serde_works() {
         const TEST_NUM: [u64; 4] = [1, 2, 3, 4];
-        let primitive = XbfPrimitive::U256(TEST_NUM);
+        let primitive = XbfPrimitive::U256(U256::from_words(TEST_NUM));
         let mut writer = Vec::new();
 
         primitive.serialize_primitive_type(&mut writer).unwrap();
@@ -331,10 +604,26 @@ mod test {
         serde_primitive_test!(I128, 18_446_744_073_709_551_617i128);
     }
 
+    #[test]
+    fn half_floats_serde_works() {
+        let value = f16::from_f32(-1.5);
+        serde_primitive_test!(F16, value);
+        let value = bf16::from_f32(3.0e38);
+        serde_primitive_test!(BF16, value);
+
+        assert_eq!(f16::from_f32(-1.5).to_le_bytes(), 0xbe00u16.to_le_bytes());
+        // bfloat16 is the top half of an f32
+        assert_eq!(
+            bf16::from_f32(1.0).to_bits(),
+            (1.0f32.to_bits() >> 16) as u16
+        );
+        assert_eq!(XbfPrimitive::from(f16::ONE).encoded_len(), 2);
+    }
+
     #[test]
     fn i256_serde_works() {
         const TEST_NUM: [u64; 4] = [1, 2, 3, 4];
-        let primitive = XbfPrimitive::I256(TEST_NUM);
+        let primitive = XbfPrimitive::I256(I256::from_words(TEST_NUM));
         let mut writer = Vec::new();
 
         primitive.serialize_primitive_type(&mut writer).unwrap();
@@ -414,6 +703,72 @@ mod test {
         )
     }
 
+    #[test]
+    fn long_bytes_fail_with_default_config() {
+        let primitive = XbfPrimitive::Bytes(vec![0; u16::MAX as usize + 1]);
+        let mut writer = vec![];
+
+        let err = primitive.serialize_primitive_type(&mut writer).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::LengthOverflow { .. }));
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn long_string_serde_works_with_wide_lengths() {
+        let test_string = "a".repeat(70_000);
+        let primitive = XbfPrimitive::String(test_string.clone());
+
+        for length_encoding in [LengthEncoding::U32, LengthEncoding::Varint] {
+            let config = XbfConfig::new().with_length_encoding(length_encoding);
+            let mut writer = vec![];
+
+            primitive
+                .serialize_primitive_type_with_config(&mut writer, &config)
+                .unwrap();
+
+            let mut reader = Cursor::new(writer);
+            let deserialized = XbfPrimitive::deserialize_primitive_type_with_config(
+                &XbfPrimitiveMetadata::String,
+                &mut reader,
+                &config,
+            )
+            .unwrap();
+
+            assert_eq!(deserialized, XbfPrimitive::String(test_string.clone()));
+        }
+    }
+
+    #[test]
+    fn char_serde_works() {
+        let primitive = 'ß'.to_xbf_primitive();
+        let mut writer = vec![];
+
+        primitive.serialize_primitive_type(&mut writer).unwrap();
+
+        assert_eq!(writer, 0xDFu32.to_le_bytes());
+
+        let mut reader = Cursor::new(writer);
+        let deserialized = XbfType::deserialize_base_type(
+            &XbfMetadata::Primitive(XbfPrimitiveMetadata::Char),
+            &mut reader,
+        )
+        .unwrap();
+
+        assert_eq!(deserialized, XbfType::Primitive(XbfPrimitive::Char('ß')));
+    }
+
+    #[test]
+    fn char_deserialize_rejects_invalid_scalar_values() {
+        for invalid in [0xD800u32, 0xDFFF, 0x110000, u32::MAX] {
+            let mut reader = Cursor::new(invalid.to_le_bytes());
+            let err =
+                XbfPrimitive::deserialize_primitive_type(&XbfPrimitiveMetadata::Char, &mut reader)
+                    .unwrap_err();
+            assert!(matches!(err.root_cause(), XbfError::InvalidChar(x) if *x == invalid));
+        }
+    }
+
     #[test]
     fn upcast_works() {
         let primitive_type = XbfPrimitive::I32(69);
@@ -454,6 +809,7 @@ mod test {
         primitive_from_native_test!(f32, F32, 42.0);
         primitive_from_native_test!(f64, F64, 42.0);
         primitive_from_native_test!(String, String, "Hello World".to_string());
+        primitive_from_native_test!(char, Char, 'x');
     }
 
     macro_rules! primitive_metadata_from_primitive_test {
@@ -473,16 +829,67 @@ mod test {
         primitive_metadata_from_primitive_test!(U32, 1);
         primitive_metadata_from_primitive_test!(U64, 1);
         primitive_metadata_from_primitive_test!(U128, 1);
-        primitive_metadata_from_primitive_test!(U256, [1, 2, 3, 4]);
+        primitive_metadata_from_primitive_test!(U256, U256::from_words([1, 2, 3, 4]));
         primitive_metadata_from_primitive_test!(I8, 1);
         primitive_metadata_from_primitive_test!(I16, 1);
         primitive_metadata_from_primitive_test!(I32, 1);
         primitive_metadata_from_primitive_test!(I64, 1);
         primitive_metadata_from_primitive_test!(I128, 1);
-        primitive_metadata_from_primitive_test!(I256, [1, 2, 3, 4]);
+        primitive_metadata_from_primitive_test!(I256, I256::from_words([1, 2, 3, 4]));
         primitive_metadata_from_primitive_test!(F32, 1.0);
         primitive_metadata_from_primitive_test!(F64, 1.0);
         primitive_metadata_from_primitive_test!(Bytes, vec![1, 2, 3, 4]);
         primitive_metadata_from_primitive_test!(String, "Hello World".to_string());
+        primitive_metadata_from_primitive_test!(Char, 'x');
+        primitive_metadata_from_primitive_test!(F16, f16::ONE);
+        primitive_metadata_from_primitive_test!(BF16, bf16::ONE);
+    }
+
+    #[test]
+    fn try_from_primitive_works() {
+        assert_eq!(i32::try_from(XbfPrimitive::I32(-5)), Ok(-5));
+        assert_eq!(u128::try_from(&XbfPrimitive::U128(7)), Ok(7));
+        assert_eq!(
+            String::try_from(XbfPrimitive::String("hi".to_string())),
+            Ok("hi".to_string())
+        );
+        assert_eq!(
+            Vec::<u8>::try_from(&XbfPrimitive::Bytes(vec![1, 2])),
+            Ok(vec![1, 2])
+        );
+
+        let err = u64::try_from(XbfPrimitive::U32(1)).unwrap_err();
+        assert_eq!(err.expected(), XbfPrimitiveMetadata::U64);
+        assert_eq!(err.actual(), &XbfPrimitiveMetadata::U32.into());
+        assert_eq!(err.to_string(), "expected U64, found U32");
+    }
+
+    #[test]
+    fn try_from_base_type_works() {
+        let value = XbfType::from(XbfPrimitive::Char('x'));
+        assert_eq!(char::try_from(&value), Ok('x'));
+        assert_eq!(char::try_from(value), Ok('x'));
+
+        let vec: XbfType =
+            XbfVec::new_unchecked(XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into()), vec![])
+                .into();
+        let err = bool::try_from(&vec).unwrap_err();
+        assert_eq!(err.expected(), XbfPrimitiveMetadata::Bool);
+        assert_eq!(err.actual(), &XbfMetadata::from(&vec));
+        assert_eq!(vec.as_bool(), None);
+    }
+
+    #[test]
+    fn accessors_work() {
+        let primitive = XbfPrimitive::U64(42);
+        assert_eq!(primitive.as_u64(), Some(42));
+        assert_eq!(primitive.as_i64(), None);
+        assert_eq!(primitive.as_str(), None);
+        assert_eq!(XbfPrimitive::F64(1.5).as_f64(), Some(1.5));
+        assert_eq!(XbfPrimitive::Bool(true).as_bool(), Some(true));
+
+        let value = XbfType::from(XbfPrimitive::Bytes(vec![9]));
+        assert_eq!(value.as_bytes(), Some(&[9][..]));
+        assert_eq!(value.as_u8(), None);
     }
 }
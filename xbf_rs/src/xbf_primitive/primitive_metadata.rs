@@ -1,6 +1,6 @@
-use crate::{XbfMetadataUpcast, XbfPrimitive};
+use crate::{Result, XbfError, XbfMetadataUpcast, XbfPrimitive};
 use byteorder::WriteBytesExt;
-use std::io::{self, Write};
+use std::io::Write;
 
 /// Metadata for a primitive type.
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
@@ -23,6 +23,17 @@ pub enum XbfPrimitiveMetadata {
     F64,
     Bytes,
     String,
+    /// A Unicode scalar value.
+    ///
+    /// Vectors and the other compound types take the discriminant values directly after
+    /// [`String`](Self::String), so Char is placed after the last of them rather than renumbering
+    /// any existing types.
+    Char = 23,
+    /// A 16-bit IEEE 754 half precision float.
+    F16 = 24,
+    /// A 16-bit bfloat16 float, which has the same exponent range as [`F32`](Self::F32) with less
+    /// precision.
+    BF16 = 25,
 }
 
 impl XbfPrimitiveMetadata {
@@ -39,17 +50,56 @@ impl XbfPrimitiveMetadata {
     ///
     /// assert_eq!(writer, [0u8]);
     /// ```
-    pub fn serialize_primitive_metadata(&self, writer: &mut impl Write) -> io::Result<()> {
-        writer.write_u8(*self as u8)
+    pub fn serialize_primitive_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        Ok(writer.write_u8(*self as u8)?)
     }
 
     // TODO: should there be a deserialize_primitive_metadata that wraps the TryFrom impl?
+
+    /// Returns the number of bytes [`Self::serialize_primitive_metadata`] would write, which is
+    /// always one.
+    pub fn encoded_len(&self) -> usize {
+        1
+    }
+
+    /// Returns the size of every value of this type, or `None` for [`Bytes`](Self::Bytes) and
+    /// [`String`](Self::String), whose size depends on their length.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// assert_eq!(XbfPrimitiveMetadata::U256.fixed_size(), Some(32));
+    /// assert_eq!(XbfPrimitiveMetadata::String.fixed_size(), None);
+    /// ```
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            XbfPrimitiveMetadata::Bool | XbfPrimitiveMetadata::U8 | XbfPrimitiveMetadata::I8 => {
+                Some(1)
+            }
+            XbfPrimitiveMetadata::U16
+            | XbfPrimitiveMetadata::I16
+            | XbfPrimitiveMetadata::F16
+            | XbfPrimitiveMetadata::BF16 => Some(2),
+            XbfPrimitiveMetadata::U32
+            | XbfPrimitiveMetadata::I32
+            | XbfPrimitiveMetadata::F32
+            | XbfPrimitiveMetadata::Char => Some(4),
+            XbfPrimitiveMetadata::U64 | XbfPrimitiveMetadata::I64 | XbfPrimitiveMetadata::F64 => {
+                Some(8)
+            }
+            XbfPrimitiveMetadata::U128 | XbfPrimitiveMetadata::I128 => Some(16),
+            XbfPrimitiveMetadata::U256 | XbfPrimitiveMetadata::I256 => Some(32),
+            XbfPrimitiveMetadata::Bytes | XbfPrimitiveMetadata::String => None,
+        }
+    }
 }
 
 impl TryFrom<u8> for XbfPrimitiveMetadata {
-    type Error = io::Error;
+    type Error = XbfError;
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
+    fn try_from(value: u8) -> Result<Self> {
         match value {
             0 => Ok(Self::Bool),
             1 => Ok(Self::U8),
@@ -68,10 +118,10 @@ impl TryFrom<u8> for XbfPrimitiveMetadata {
             14 => Ok(Self::F64),
             15 => Ok(Self::Bytes),
             16 => Ok(Self::String),
-            _ => Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "invalid primitive metadata",
-            )),
+            23 => Ok(Self::Char),
+            24 => Ok(Self::F16),
+            25 => Ok(Self::BF16),
+            x => Err(XbfError::UnknownDiscriminant(x)),
         }
     }
 }
@@ -98,6 +148,9 @@ impl From<&XbfPrimitive> for XbfPrimitiveMetadata {
             XbfPrimitive::F64(_) => XbfPrimitiveMetadata::F64,
             XbfPrimitive::Bytes(_) => XbfPrimitiveMetadata::Bytes,
             XbfPrimitive::String(_) => XbfPrimitiveMetadata::String,
+            XbfPrimitive::Char(_) => XbfPrimitiveMetadata::Char,
+            XbfPrimitive::F16(_) => XbfPrimitiveMetadata::F16,
+            XbfPrimitive::BF16(_) => XbfPrimitiveMetadata::BF16,
         }
     }
 }
@@ -136,6 +189,7 @@ mod test {
         serialize_primitive_metadata_test!(F64, 14);
         serialize_primitive_metadata_test!(Bytes, 15);
         serialize_primitive_metadata_test!(String, 16);
+        serialize_primitive_metadata_test!(Char, 23);
     }
 
     macro_rules! deserialize_primitive_metadata_test {
@@ -169,13 +223,26 @@ mod test {
         deserialize_primitive_metadata_test!(F64);
         deserialize_primitive_metadata_test!(Bytes);
         deserialize_primitive_metadata_test!(String);
+        deserialize_primitive_metadata_test!(Char);
+        deserialize_primitive_metadata_test!(F16);
+        deserialize_primitive_metadata_test!(BF16);
+    }
+
+    #[test]
+    fn char_discriminant_follows_compound_types() {
+        assert_eq!(
+            XbfPrimitiveMetadata::Char as u8,
+            crate::ARRAY_METADATA_DISCRIMINANT + 1
+        );
+        assert_eq!(XbfPrimitiveMetadata::F16 as u8, 24);
+        assert_eq!(XbfPrimitiveMetadata::BF16 as u8, 25);
     }
 
     #[test]
     fn metadata_try_from_u8_err_for_unknown_id() {
         let err =
             XbfPrimitiveMetadata::try_from(XbfPrimitiveMetadata::String as u8 + 1).unwrap_err();
-        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
-        assert_eq!(err.to_string(), "invalid primitive metadata");
+        assert!(matches!(err, XbfError::UnknownDiscriminant(17)));
+        assert_eq!(err.to_string(), "unknown metadata discriminant 17");
     }
 }
@@ -0,0 +1,274 @@
+use crate::{
+    bf16, f16, util::ReadContext, Result, XbfConfig, XbfPrimitive, XbfPrimitiveMetadata, I256, U256,
+};
+
+/// A primitive type borrowing its bytes or string from the input it was read from.
+///
+/// This is the borrowed counterpart of [`XbfPrimitive`], used by
+/// [`XbfTypeRef`](crate::XbfTypeRef). Every variant other than [`XbfPrimitiveRef::Bytes`] and
+/// [`XbfPrimitiveRef::String`] holds the same value as in [`XbfPrimitive`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XbfPrimitiveRef<'a> {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    U256(U256),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    I256(I256),
+    F32(f32),
+    F64(f64),
+    Bytes(&'a [u8]),
+    String(&'a str),
+    Char(char),
+    F16(f16),
+    BF16(bf16),
+}
+
+impl<'a> XbfPrimitiveRef<'a> {
+    /// Deserialize a primitive type from a slice, borrowing bytes and strings from it.
+    ///
+    /// The slice is advanced past the bytes that were read.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfPrimitiveRef;
+    ///
+    /// let data = [5, 0, b'h', b'e', b'l', b'l', b'o', 42];
+    /// let mut input = &data[..];
+    ///
+    /// let primitive =
+    ///     XbfPrimitiveRef::deserialize_primitive_type(&XbfPrimitiveMetadata::String, &mut input)
+    ///         .unwrap();
+    ///
+    /// assert_eq!(primitive, XbfPrimitiveRef::String("hello"));
+    /// assert_eq!(input, [42]);
+    /// ```
+    pub fn deserialize_primitive_type(
+        primitive_metadata: &XbfPrimitiveMetadata,
+        input: &mut &'a [u8],
+    ) -> Result<Self> {
+        Self::deserialize_primitive_type_with_config(
+            primitive_metadata,
+            input,
+            &XbfConfig::default(),
+        )
+    }
+
+    /// Deserialize a primitive type from a slice using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_primitive_type`] for more details.
+    pub fn deserialize_primitive_type_with_config(
+        primitive_metadata: &XbfPrimitiveMetadata,
+        input: &mut &'a [u8],
+        config: &XbfConfig,
+    ) -> Result<Self> {
+        ReadContext::decode_slice(input, config, |reader| {
+            Self::deserialize_primitive_type_with_context(primitive_metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_primitive_type_with_context(
+        primitive_metadata: &XbfPrimitiveMetadata,
+        reader: &mut ReadContext<&'a [u8]>,
+    ) -> Result<Self> {
+        Ok(match primitive_metadata {
+            XbfPrimitiveMetadata::Bytes => XbfPrimitiveRef::Bytes(reader.read_borrowed_bytes()?),
+            XbfPrimitiveMetadata::String => XbfPrimitiveRef::String(reader.read_borrowed_str()?),
+            x => match XbfPrimitive::deserialize_primitive_type_with_context(x, reader)? {
+                XbfPrimitive::Bool(x) => XbfPrimitiveRef::Bool(x),
+                XbfPrimitive::U8(x) => XbfPrimitiveRef::U8(x),
+                XbfPrimitive::U16(x) => XbfPrimitiveRef::U16(x),
+                XbfPrimitive::U32(x) => XbfPrimitiveRef::U32(x),
+                XbfPrimitive::U64(x) => XbfPrimitiveRef::U64(x),
+                XbfPrimitive::U128(x) => XbfPrimitiveRef::U128(x),
+                XbfPrimitive::U256(x) => XbfPrimitiveRef::U256(x),
+                XbfPrimitive::I8(x) => XbfPrimitiveRef::I8(x),
+                XbfPrimitive::I16(x) => XbfPrimitiveRef::I16(x),
+                XbfPrimitive::I32(x) => XbfPrimitiveRef::I32(x),
+                XbfPrimitive::I64(x) => XbfPrimitiveRef::I64(x),
+                XbfPrimitive::I128(x) => XbfPrimitiveRef::I128(x),
+                XbfPrimitive::I256(x) => XbfPrimitiveRef::I256(x),
+                XbfPrimitive::F32(x) => XbfPrimitiveRef::F32(x),
+                XbfPrimitive::F64(x) => XbfPrimitiveRef::F64(x),
+                XbfPrimitive::Char(x) => XbfPrimitiveRef::Char(x),
+                XbfPrimitive::F16(x) => XbfPrimitiveRef::F16(x),
+                XbfPrimitive::BF16(x) => XbfPrimitiveRef::BF16(x),
+                XbfPrimitive::Bytes(_) | XbfPrimitive::String(_) => {
+                    unreachable!("bytes and strings are borrowed above")
+                }
+            },
+        })
+    }
+
+    /// Get the metadata for this primitive type.
+    pub fn get_metadata(&self) -> XbfPrimitiveMetadata {
+        match self {
+            XbfPrimitiveRef::Bool(_) => XbfPrimitiveMetadata::Bool,
+            XbfPrimitiveRef::U8(_) => XbfPrimitiveMetadata::U8,
+            XbfPrimitiveRef::U16(_) => XbfPrimitiveMetadata::U16,
+            XbfPrimitiveRef::U32(_) => XbfPrimitiveMetadata::U32,
+            XbfPrimitiveRef::U64(_) => XbfPrimitiveMetadata::U64,
+            XbfPrimitiveRef::U128(_) => XbfPrimitiveMetadata::U128,
+            XbfPrimitiveRef::U256(_) => XbfPrimitiveMetadata::U256,
+            XbfPrimitiveRef::I8(_) => XbfPrimitiveMetadata::I8,
+            XbfPrimitiveRef::I16(_) => XbfPrimitiveMetadata::I16,
+            XbfPrimitiveRef::I32(_) => XbfPrimitiveMetadata::I32,
+            XbfPrimitiveRef::I64(_) => XbfPrimitiveMetadata::I64,
+            XbfPrimitiveRef::I128(_) => XbfPrimitiveMetadata::I128,
+            XbfPrimitiveRef::I256(_) => XbfPrimitiveMetadata::I256,
+            XbfPrimitiveRef::F32(_) => XbfPrimitiveMetadata::F32,
+            XbfPrimitiveRef::F64(_) => XbfPrimitiveMetadata::F64,
+            XbfPrimitiveRef::Bytes(_) => XbfPrimitiveMetadata::Bytes,
+            XbfPrimitiveRef::String(_) => XbfPrimitiveMetadata::String,
+            XbfPrimitiveRef::Char(_) => XbfPrimitiveMetadata::Char,
+            XbfPrimitiveRef::F16(_) => XbfPrimitiveMetadata::F16,
+            XbfPrimitiveRef::BF16(_) => XbfPrimitiveMetadata::BF16,
+        }
+    }
+
+    /// Copies the primitive into an owned [`XbfPrimitive`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveRef;
+    ///
+    /// let primitive = XbfPrimitiveRef::Bytes(&[1, 2, 3]);
+    ///
+    /// assert_eq!(primitive.to_owned(), XbfPrimitive::Bytes(vec![1, 2, 3]));
+    /// ```
+    pub fn to_owned(&self) -> XbfPrimitive {
+        match *self {
+            XbfPrimitiveRef::Bool(x) => XbfPrimitive::Bool(x),
+            XbfPrimitiveRef::U8(x) => XbfPrimitive::U8(x),
+            XbfPrimitiveRef::U16(x) => XbfPrimitive::U16(x),
+            XbfPrimitiveRef::U32(x) => XbfPrimitive::U32(x),
+            XbfPrimitiveRef::U64(x) => XbfPrimitive::U64(x),
+            XbfPrimitiveRef::U128(x) => XbfPrimitive::U128(x),
+            XbfPrimitiveRef::U256(x) => XbfPrimitive::U256(x),
+            XbfPrimitiveRef::I8(x) => XbfPrimitive::I8(x),
+            XbfPrimitiveRef::I16(x) => XbfPrimitive::I16(x),
+            XbfPrimitiveRef::I32(x) => XbfPrimitive::I32(x),
+            XbfPrimitiveRef::I64(x) => XbfPrimitive::I64(x),
+            XbfPrimitiveRef::I128(x) => XbfPrimitive::I128(x),
+            XbfPrimitiveRef::I256(x) => XbfPrimitive::I256(x),
+            XbfPrimitiveRef::F32(x) => XbfPrimitive::F32(x),
+            XbfPrimitiveRef::F64(x) => XbfPrimitive::F64(x),
+            XbfPrimitiveRef::Bytes(x) => XbfPrimitive::Bytes(x.to_vec()),
+            XbfPrimitiveRef::String(x) => XbfPrimitive::String(x.to_string()),
+            XbfPrimitiveRef::Char(x) => XbfPrimitive::Char(x),
+            XbfPrimitiveRef::F16(x) => XbfPrimitive::F16(x),
+            XbfPrimitiveRef::BF16(x) => XbfPrimitive::BF16(x),
+        }
+    }
+}
+
+impl From<XbfPrimitiveRef<'_>> for XbfPrimitive {
+    fn from(value: XbfPrimitiveRef<'_>) -> Self {
+        value.to_owned()
+    }
+}
+
+impl<'a> From<&'a XbfPrimitive> for XbfPrimitiveRef<'a> {
+    fn from(value: &'a XbfPrimitive) -> Self {
+        match value {
+            XbfPrimitive::Bool(x) => XbfPrimitiveRef::Bool(*x),
+            XbfPrimitive::U8(x) => XbfPrimitiveRef::U8(*x),
+            XbfPrimitive::U16(x) => XbfPrimitiveRef::U16(*x),
+            XbfPrimitive::U32(x) => XbfPrimitiveRef::U32(*x),
+            XbfPrimitive::U64(x) => XbfPrimitiveRef::U64(*x),
+            XbfPrimitive::U128(x) => XbfPrimitiveRef::U128(*x),
+            XbfPrimitive::U256(x) => XbfPrimitiveRef::U256(*x),
+            XbfPrimitive::I8(x) => XbfPrimitiveRef::I8(*x),
+            XbfPrimitive::I16(x) => XbfPrimitiveRef::I16(*x),
+            XbfPrimitive::I32(x) => XbfPrimitiveRef::I32(*x),
+            XbfPrimitive::I64(x) => XbfPrimitiveRef::I64(*x),
+            XbfPrimitive::I128(x) => XbfPrimitiveRef::I128(*x),
+            XbfPrimitive::I256(x) => XbfPrimitiveRef::I256(*x),
+            XbfPrimitive::F32(x) => XbfPrimitiveRef::F32(*x),
+            XbfPrimitive::F64(x) => XbfPrimitiveRef::F64(*x),
+            XbfPrimitive::Bytes(x) => XbfPrimitiveRef::Bytes(x),
+            XbfPrimitive::String(x) => XbfPrimitiveRef::String(x),
+            XbfPrimitive::Char(x) => XbfPrimitiveRef::Char(*x),
+            XbfPrimitive::F16(x) => XbfPrimitiveRef::F16(*x),
+            XbfPrimitive::BF16(x) => XbfPrimitiveRef::BF16(*x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{LengthEncoding, XbfError};
+
+    #[test]
+    fn borrowed_primitives_match_owned() {
+        let primitives = [
+            XbfPrimitive::I64(-3),
+            XbfPrimitive::U256(U256::from_words([1, 2, 3, 4])),
+            XbfPrimitive::Bytes(vec![9, 8]),
+            XbfPrimitive::String("héllo".to_string()),
+            XbfPrimitive::Char('x'),
+        ];
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::Varint);
+        let mut writer = vec![];
+        for primitive in &primitives {
+            primitive
+                .serialize_primitive_type_with_config(&mut writer, &config)
+                .unwrap();
+        }
+
+        let mut input = &writer[..];
+        for primitive in &primitives {
+            let borrowed = XbfPrimitiveRef::deserialize_primitive_type_with_config(
+                &primitive.get_metadata(),
+                &mut input,
+                &config,
+            )
+            .unwrap();
+            assert_eq!(borrowed, XbfPrimitiveRef::from(primitive));
+            assert_eq!(borrowed.get_metadata(), primitive.get_metadata());
+            assert_eq!(&borrowed.to_owned(), primitive);
+        }
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn borrowed_strings_point_into_the_input() {
+        let data = [2, 0, b'h', b'i'];
+        let mut input = &data[..];
+        let XbfPrimitiveRef::String(x) =
+            XbfPrimitiveRef::deserialize_primitive_type(&XbfPrimitiveMetadata::String, &mut input)
+                .unwrap()
+        else {
+            panic!("expected a string");
+        };
+        assert_eq!(x.as_ptr(), data[2..].as_ptr());
+    }
+
+    #[test]
+    fn truncated_and_invalid_input_fails() {
+        let mut input = &[3, 0, b'a'][..];
+        let err =
+            XbfPrimitiveRef::deserialize_primitive_type(&XbfPrimitiveMetadata::Bytes, &mut input)
+                .unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+
+        let mut input = &[1, 0, 0xff][..];
+        let err =
+            XbfPrimitiveRef::deserialize_primitive_type(&XbfPrimitiveMetadata::String, &mut input)
+                .unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::InvalidUtf8));
+        assert_eq!(err.offset(), Some(3));
+    }
+}
@@ -0,0 +1,600 @@
+//! Support for types implementing serde's `Serialize` and `Deserialize`, behind the `serde`
+//! feature.
+//!
+//! Values are written with the same layout the equivalent [`XbfType`](crate::XbfType) would
+//! have, so the bytes can be read by any XBF implementation that knows the metadata. Serde's data
+//! model maps onto XBF as follows:
+//!
+//! - `bool`, the integer and float types, `char`, strings and byte arrays are primitives.
+//! - Sequences are vecs, and maps are maps. Both must know their length before being written.
+//! - Structs, tuples and tuple structs are structs, with tuple fields named `"0"`, `"1"`, ....
+//!   Unit and unit structs are structs with no fields.
+//! - Newtype structs are written as the value they wrap.
+//! - `Option` is an optional.
+//! - Enums are enums. Unit variants carry an empty struct, newtype variants carry their value,
+//!   and tuple and struct variants carry a struct named after the variant.
+//!
+//! The format is not self-describing, so features of serde relying on
+//! `Deserializer::deserialize_any`, such as untagged enums and `#[serde(flatten)]`, are not
+//! supported.
+
+mod de;
+mod ser;
+mod trace;
+
+pub use de::Deserializer;
+pub use ser::{Compound, Serializer};
+
+use crate::{util::ReadContext, Result, XbfConfig, XbfError, XbfMetadata};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+};
+use trace::Tracer;
+
+impl serde::ser::Error for XbfError {
+    fn custom<T: Display>(msg: T) -> Self {
+        XbfError::Custom(msg.to_string())
+    }
+}
+
+impl serde::de::Error for XbfError {
+    fn custom<T: Display>(msg: T) -> Self {
+        XbfError::Custom(msg.to_string())
+    }
+}
+
+/// Serialize a value implementing [`Serialize`] as defined by the XBF specification.
+///
+/// Only the value is written, not its metadata. Use [`trace_metadata`] to get the metadata
+/// describing it. Struct fields can't be skipped and map keys must be primitives, since the
+/// metadata couldn't describe the value otherwise.
+///
+/// # Example
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Player {
+///     id: u8,
+///     name: String,
+/// }
+///
+/// let player = Player { id: 7, name: "alice".to_string() };
+/// let mut writer = vec![];
+///
+/// xbf_rs::to_writer(&mut writer, &player).unwrap();
+///
+/// let mut expected = vec![7];
+/// expected.extend_from_slice(&5u16.to_le_bytes());
+/// expected.extend_from_slice(b"alice");
+/// assert_eq!(writer, expected);
+///
+/// let deserialized: Player = xbf_rs::from_reader(&mut std::io::Cursor::new(writer)).unwrap();
+/// assert_eq!(deserialized, player);
+/// ```
+pub fn to_writer<T: ?Sized + Serialize>(writer: &mut impl Write, value: &T) -> Result<()> {
+    to_writer_with_config(writer, value, &XbfConfig::default())
+}
+
+/// Serialize a value implementing [`Serialize`] using the given [`XbfConfig`].
+///
+/// See [`to_writer`] for more details.
+pub fn to_writer_with_config<T: ?Sized + Serialize>(
+    writer: &mut impl Write,
+    value: &T,
+    config: &XbfConfig,
+) -> Result<()> {
+    value.serialize(&mut Serializer::new(writer, config))
+}
+
+/// Deserialize a value implementing [`Deserialize`](serde::Deserialize) as defined by the XBF
+/// specification.
+///
+/// As with [`XbfMap`](crate::XbfMap), a map holding the same key twice is rejected with
+/// [`XbfError::DuplicateKey`].
+///
+/// See [`to_writer`] for an example.
+pub fn from_reader<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T> {
+    from_reader_with_config(reader, &XbfConfig::default())
+}
+
+/// Deserialize a value implementing [`Deserialize`](serde::Deserialize) using the given
+/// [`XbfConfig`].
+///
+/// See [`to_writer`] for an example.
+pub fn from_reader_with_config<T: DeserializeOwned>(
+    reader: &mut impl Read,
+    config: &XbfConfig,
+) -> Result<T> {
+    ReadContext::decode(reader, config, |reader| {
+        T::deserialize(Deserializer::new(reader))
+    })
+}
+
+/// Returns the metadata of the values [`to_writer`] writes for a type.
+///
+/// The metadata is found by deserializing made up values of the type and recording what is asked
+/// for, once for every variant of every enum it contains. This means the type's `Deserialize`
+/// implementation must accept zeroes, empty strings and collections of one element, and must
+/// ask for the same thing every time. Recursive types can't be traced.
+///
+/// # Example
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use xbf_rs::XbfEnumMetadata;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfStructMetadata;
+/// use xbf_rs::XbfVecMetadata;
+///
+/// #[derive(Deserialize)]
+/// #[allow(dead_code)]
+/// enum Shape {
+///     Circle(f32),
+///     Square { side: f32 },
+/// }
+///
+/// #[derive(Deserialize)]
+/// #[allow(dead_code)]
+/// struct Drawing {
+///     shapes: Vec<Shape>,
+/// }
+///
+/// assert_eq!(
+///     xbf_rs::trace_metadata::<Drawing>().unwrap(),
+///     XbfStructMetadata::new(
+///         "Drawing".to_string(),
+///         vec![(
+///             "shapes".to_string(),
+///             XbfVecMetadata::new(
+///                 XbfEnumMetadata::new(
+///                     "Shape".to_string(),
+///                     vec![
+///                         ("Circle".to_string(), XbfPrimitiveMetadata::F32.into()),
+///                         (
+///                             "Square".to_string(),
+///                             XbfStructMetadata::new(
+///                                 "Square".to_string(),
+///                                 vec![("side".to_string(), XbfPrimitiveMetadata::F32.into())],
+///                             )
+///                             .into()
+///                         ),
+///                     ],
+///                 )
+///                 .into()
+///             )
+///             .into()
+///         )],
+///     )
+///     .into()
+/// );
+/// ```
+pub fn trace_metadata<T: DeserializeOwned>() -> Result<XbfMetadata> {
+    let mut tracer = Tracer::default();
+    loop {
+        T::deserialize(&mut tracer)?;
+        let metadata = tracer.take();
+        if tracer.is_complete() {
+            return Ok(tracer.resolve(&metadata));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Xbf, XbfEnumMetadata, XbfEnumValue, XbfMapMetadata, XbfOptional, XbfOptionalMetadata,
+        XbfPrimitive, XbfPrimitiveMetadata, XbfStruct, XbfStructMetadata, XbfType, XbfVec,
+        XbfVecMetadata,
+    };
+    use serde::Deserialize;
+    use std::{collections::BTreeMap, io::Cursor};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Pet {
+        None,
+        Named(String),
+        Cat { lives: u8 },
+        Dog(u8, bool),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Player {
+        name: String,
+        position: (f32, f32),
+        pets: Vec<Pet>,
+        scores: BTreeMap<String, u32>,
+        title: Option<String>,
+        initial: char,
+    }
+
+    fn test_player() -> Player {
+        Player {
+            name: "alice".to_string(),
+            position: (1.0, -2.5),
+            pets: vec![
+                Pet::Named("rex".to_string()),
+                Pet::Cat { lives: 9 },
+                Pet::None,
+                Pet::Dog(3, true),
+            ],
+            scores: [("level1".to_string(), 10), ("level2".to_string(), 20)].into(),
+            title: None,
+            initial: 'a',
+        }
+    }
+
+    fn pet_metadata() -> XbfEnumMetadata {
+        XbfEnumMetadata::new(
+            "Pet".to_string(),
+            vec![
+                (
+                    "None".to_string(),
+                    XbfStructMetadata::new("None".to_string(), vec![]).into(),
+                ),
+                ("Named".to_string(), XbfPrimitiveMetadata::String.into()),
+                (
+                    "Cat".to_string(),
+                    XbfStructMetadata::new(
+                        "Cat".to_string(),
+                        vec![("lives".to_string(), XbfPrimitiveMetadata::U8.into())],
+                    )
+                    .into(),
+                ),
+                (
+                    "Dog".to_string(),
+                    XbfStructMetadata::new(
+                        "Dog".to_string(),
+                        vec![
+                            ("0".to_string(), XbfPrimitiveMetadata::U8.into()),
+                            ("1".to_string(), XbfPrimitiveMetadata::Bool.into()),
+                        ],
+                    )
+                    .into(),
+                ),
+            ],
+        )
+    }
+
+    fn position_metadata() -> XbfStructMetadata {
+        XbfStructMetadata::new(
+            "tuple".to_string(),
+            vec![
+                ("0".to_string(), XbfPrimitiveMetadata::F32.into()),
+                ("1".to_string(), XbfPrimitiveMetadata::F32.into()),
+            ],
+        )
+    }
+
+    fn player_metadata() -> XbfStructMetadata {
+        XbfStructMetadata::new(
+            "Player".to_string(),
+            vec![
+                ("name".to_string(), XbfPrimitiveMetadata::String.into()),
+                ("position".to_string(), position_metadata().into()),
+                (
+                    "pets".to_string(),
+                    XbfVecMetadata::new(pet_metadata().into()).into(),
+                ),
+                (
+                    "scores".to_string(),
+                    XbfMapMetadata::new(
+                        XbfPrimitiveMetadata::String,
+                        XbfPrimitiveMetadata::U32.into(),
+                    )
+                    .into(),
+                ),
+                (
+                    "title".to_string(),
+                    XbfOptionalMetadata::new(XbfPrimitiveMetadata::String.into()).into(),
+                ),
+                ("initial".to_string(), XbfPrimitiveMetadata::Char.into()),
+            ],
+        )
+    }
+
+    #[test]
+    fn trace_metadata_works() {
+        assert_eq!(
+            trace_metadata::<Player>().unwrap(),
+            player_metadata().into()
+        );
+    }
+
+    #[test]
+    fn to_writer_matches_xbf_type() {
+        let player = test_player();
+        let pet = |variant: &str, value: XbfType| {
+            XbfEnumValue::new(pet_metadata(), variant, value).unwrap()
+        };
+        let pets = vec![
+            pet("Named", XbfPrimitive::String("rex".to_string()).into()),
+            pet(
+                "Cat",
+                XbfStruct::new_unchecked(
+                    XbfStructMetadata::new(
+                        "Cat".to_string(),
+                        vec![("lives".to_string(), XbfPrimitiveMetadata::U8.into())],
+                    ),
+                    vec![XbfPrimitive::U8(9).into()],
+                )
+                .into(),
+            ),
+            pet(
+                "None",
+                XbfStruct::new_unchecked(
+                    XbfStructMetadata::new("None".to_string(), vec![]),
+                    vec![],
+                )
+                .into(),
+            ),
+            pet(
+                "Dog",
+                XbfStruct::new_unchecked(
+                    XbfStructMetadata::new(
+                        "Dog".to_string(),
+                        vec![
+                            ("0".to_string(), XbfPrimitiveMetadata::U8.into()),
+                            ("1".to_string(), XbfPrimitiveMetadata::Bool.into()),
+                        ],
+                    ),
+                    vec![XbfPrimitive::U8(3).into(), XbfPrimitive::Bool(true).into()],
+                )
+                .into(),
+            ),
+        ];
+        let scores = crate::XbfMap::new(
+            XbfMapMetadata::new(
+                XbfPrimitiveMetadata::String,
+                XbfPrimitiveMetadata::U32.into(),
+            ),
+            vec![
+                ("level1".to_string().into(), 10u32.to_xbf_type()),
+                ("level2".to_string().into(), 20u32.to_xbf_type()),
+            ],
+        )
+        .unwrap();
+        let expected = XbfStruct::new_unchecked(
+            player_metadata(),
+            vec![
+                "alice".to_string().to_xbf_type(),
+                XbfStruct::new_unchecked(
+                    position_metadata(),
+                    vec![1.0f32.to_xbf_type(), (-2.5f32).to_xbf_type()],
+                )
+                .into(),
+                XbfVec::new(
+                    XbfVecMetadata::new(pet_metadata().into()),
+                    pets.into_iter().map(Into::into).collect(),
+                )
+                .unwrap()
+                .into(),
+                scores.into(),
+                XbfOptional::new(
+                    XbfOptionalMetadata::new(XbfPrimitiveMetadata::String.into()),
+                    None,
+                )
+                .unwrap()
+                .into(),
+                'a'.to_xbf_type(),
+            ],
+        );
+
+        let mut writer = vec![];
+        to_writer(&mut writer, &player).unwrap();
+        let mut expected_writer = vec![];
+        expected
+            .serialize_struct_type(&mut expected_writer)
+            .unwrap();
+        assert_eq!(writer, expected_writer);
+
+        let deserialized: Player = from_reader(&mut Cursor::new(writer)).unwrap();
+        assert_eq!(deserialized, player);
+    }
+
+    #[test]
+    fn derived_xbf_and_serde_agree() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Item {
+            id: u64,
+            tags: Vec<String>,
+            parent: Option<u32>,
+        }
+        let item = Item {
+            id: 12,
+            tags: vec!["a".to_string(), "bc".to_string()],
+            parent: Some(3),
+        };
+        let expected = XbfStruct::new_unchecked(
+            XbfStructMetadata::new(
+                "Item".to_string(),
+                vec![
+                    ("id".to_string(), u64::xbf_metadata()),
+                    ("tags".to_string(), Vec::<String>::xbf_metadata()),
+                    ("parent".to_string(), Option::<u32>::xbf_metadata()),
+                ],
+            ),
+            vec![
+                item.id.to_xbf_type(),
+                item.tags.to_xbf_type(),
+                item.parent.to_xbf_type(),
+            ],
+        );
+
+        let mut writer = vec![];
+        to_writer(&mut writer, &item).unwrap();
+        let mut expected_writer = vec![];
+        expected
+            .serialize_struct_type(&mut expected_writer)
+            .unwrap();
+        assert_eq!(writer, expected_writer);
+        assert_eq!(
+            trace_metadata::<Item>().unwrap(),
+            XbfMetadata::from(expected.get_metadata())
+        );
+    }
+
+    #[test]
+    fn config_is_respected() {
+        let config = XbfConfig::new().with_length_encoding(crate::LengthEncoding::Varint);
+        let value = vec!["hi".to_string()];
+        let mut writer = vec![];
+        to_writer_with_config(&mut writer, &value, &config).unwrap();
+        assert_eq!(writer, [1, 2, b'h', b'i']);
+
+        let deserialized: Vec<String> =
+            from_reader_with_config(&mut Cursor::new(writer), &config).unwrap();
+        assert_eq!(deserialized, value);
+    }
+
+    #[test]
+    fn errors_have_paths() {
+        let mut writer = vec![];
+        to_writer(&mut writer, &test_player()).unwrap();
+        // cut off in the middle of the second pet
+        writer.truncate(2 + 5 + 8 + 2 + 2 + 2 + 3 + 2);
+
+        let err = from_reader::<Player>(&mut Cursor::new(writer)).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+        assert_eq!(err.path(), Some("root.pets[1]::Cat.lives"));
+
+        let err = to_writer(&mut vec![], &vec![(); 70_000]).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::LengthOverflow { .. }));
+    }
+
+    #[test]
+    fn duplicate_map_keys_are_rejected() {
+        // two entries, both with key 1
+        let bytes = [2, 0, 1, 5, 1, 6];
+
+        let err = from_reader::<BTreeMap<u8, u8>>(&mut Cursor::new(bytes)).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::DuplicateKey));
+        assert_eq!(err.path(), Some("root[1]"));
+
+        let metadata =
+            XbfMapMetadata::new(XbfPrimitiveMetadata::U8, XbfPrimitiveMetadata::U8.into());
+        let err =
+            XbfType::deserialize_base_type(&metadata.into(), &mut Cursor::new(bytes)).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::DuplicateKey));
+    }
+
+    #[test]
+    fn unsupported_values_fail_to_write() {
+        #[derive(Serialize)]
+        struct Sparse {
+            x: u8,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            y: Option<u8>,
+            z: u8,
+        }
+        let value = Sparse {
+            x: 1,
+            y: None,
+            z: 2,
+        };
+        let err = to_writer(&mut vec![], &value).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::Custom(x) if x.contains("y")));
+        assert_eq!(err.path(), Some("root.y"));
+
+        let map = BTreeMap::from([((1u8, 2u8), 3u8)]);
+        let err = to_writer(&mut vec![], &map).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+        assert_eq!(err.path(), Some("root[0]"));
+
+        let map = BTreeMap::from([(Some(1u8), 3u8)]);
+        let err = to_writer(&mut vec![], &map).unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+    }
+
+    #[test]
+    fn same_named_enums_are_traced_separately() {
+        mod a {
+            #[derive(serde::Deserialize)]
+            pub enum Kind {
+                X,
+                Y,
+                Z,
+            }
+        }
+        #[allow(dead_code)]
+        mod b {
+            #[derive(serde::Deserialize)]
+            pub enum Kind {
+                P(u8),
+            }
+
+            #[derive(serde::Deserialize)]
+            pub struct Pair<T> {
+                pub inner: T,
+            }
+        }
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Kinds {
+            a: a::Kind,
+            b: b::Kind,
+        }
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Pair {
+            pair: b::Pair<a::Kind>,
+        }
+
+        let unit = |name: &str| XbfStructMetadata::new(name.to_string(), vec![]).into();
+        let a = XbfEnumMetadata::new(
+            "Kind".to_string(),
+            vec![
+                ("X".to_string(), unit("X")),
+                ("Y".to_string(), unit("Y")),
+                ("Z".to_string(), unit("Z")),
+            ],
+        );
+        let b = XbfEnumMetadata::new(
+            "Kind".to_string(),
+            vec![("P".to_string(), XbfPrimitiveMetadata::U8.into())],
+        );
+        assert_eq!(
+            trace_metadata::<Kinds>().unwrap(),
+            XbfStructMetadata::new(
+                "Kinds".to_string(),
+                vec![
+                    ("a".to_string(), a.clone().into()),
+                    ("b".to_string(), b.into())
+                ],
+            )
+            .into()
+        );
+
+        let inner =
+            XbfStructMetadata::new("Pair".to_string(), vec![("inner".to_string(), a.into())]);
+        assert_eq!(
+            trace_metadata::<Pair>().unwrap(),
+            XbfStructMetadata::new("Pair".to_string(), vec![("pair".to_string(), inner.into())])
+                .into()
+        );
+    }
+
+    #[test]
+    fn unsupported_types_fail_to_trace() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Node {
+            children: Vec<Node>,
+        }
+        let err = trace_metadata::<Node>().unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::Custom(_)));
+        assert_eq!(err.path(), Some("root.children"));
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Keyed {
+            map: BTreeMap<(u8, u8), u8>,
+        }
+        let err = trace_metadata::<Keyed>().unwrap_err();
+        assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+    }
+}
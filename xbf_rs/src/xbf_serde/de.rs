@@ -0,0 +1,339 @@
+use crate::{
+    util::{read_bytes, read_string, ReadContext},
+    Result, XbfError,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::de::{self, value::U32Deserializer, DeserializeSeed, IntoDeserializer, Visitor};
+use std::{collections::HashSet, io::Read};
+
+/// A serde [`Deserializer`](serde::Deserializer) reading the XBF wire format.
+///
+/// The wire format does not describe itself, so this relies on the type being deserialized to
+/// say what comes next. Anything that needs [`deserialize_any`](de::Deserializer::deserialize_any),
+/// such as untagged enums or flattened structs, is not supported.
+pub struct Deserializer<'a, R> {
+    reader: &'a mut ReadContext<R>,
+}
+
+impl<'a, R: Read> Deserializer<'a, R> {
+    pub fn new(reader: &'a mut ReadContext<R>) -> Self {
+        Self { reader }
+    }
+
+    fn reborrow(&mut self) -> Deserializer<'_, R> {
+        Deserializer {
+            reader: &mut *self.reader,
+        }
+    }
+}
+
+fn not_self_describing(what: &str) -> XbfError {
+    XbfError::Custom(format!(
+        "xbf is not self-describing, so {what} can't be deserialized"
+    ))
+}
+
+macro_rules! deserialize_number {
+    ($deserialize:ident, $visit:ident, $read:ident) => {
+        fn $deserialize<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            visitor.$visit(self.reader.$read::<LittleEndian>()?)
+        }
+    };
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for Deserializer<'_, R> {
+    type Error = XbfError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(not_self_describing("a value of unknown type"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.reader.read_u8()? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.reader.read_i8()?)
+    }
+
+    deserialize_number!(deserialize_i16, visit_i16, read_i16);
+    deserialize_number!(deserialize_i32, visit_i32, read_i32);
+    deserialize_number!(deserialize_i64, visit_i64, read_i64);
+    deserialize_number!(deserialize_i128, visit_i128, read_i128);
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.reader.read_u8()?)
+    }
+
+    deserialize_number!(deserialize_u16, visit_u16, read_u16);
+    deserialize_number!(deserialize_u32, visit_u32, read_u32);
+    deserialize_number!(deserialize_u64, visit_u64, read_u64);
+    deserialize_number!(deserialize_u128, visit_u128, read_u128);
+    deserialize_number!(deserialize_f32, visit_f32, read_f32);
+    deserialize_number!(deserialize_f64, visit_f64, read_f64);
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let x = self.reader.read_u32::<LittleEndian>()?;
+        visitor.visit_char(char::from_u32(x).ok_or(XbfError::InvalidChar(x))?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(read_string(self.reader)?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(read_string(self.reader)?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(read_bytes(self.reader)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(read_bytes(self.reader)?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.reader.nested(|reader| match reader.read_u8()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(Deserializer::new(reader)),
+            x => Err(XbfError::InvalidPresenceByte(x)),
+        })
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.reader.nested(|reader| {
+            let len = reader.read_elements_len()?;
+            visitor.visit_seq(SeqAccess::elements(reader, len))
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        self.reader
+            .nested(|reader| visitor.visit_seq(SeqAccess::fields(reader, len, None)))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.reader.nested(|reader| {
+            let len = reader.read_elements_len()?;
+            visitor.visit_map(MapAccess {
+                reader,
+                len,
+                index: 0,
+                keys: HashSet::new(),
+            })
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.reader.nested(|reader| {
+            visitor.visit_seq(SeqAccess::fields(reader, fields.len(), Some(fields)))
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.reader.nested(|reader| {
+            let index = reader.read_u16::<LittleEndian>()?;
+            let variant = *variants
+                .get(usize::from(index))
+                .ok_or(XbfError::UnknownVariant(index))?;
+            visitor
+                .visit_enum(EnumAccess { reader, index })
+                .map_err(|e| e.within(format_args!("::{variant}")))
+        })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(not_self_describing("an identifier"))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(not_self_describing("an ignored value"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Reads the elements of a sequence, or the fields of a tuple or struct.
+struct SeqAccess<'a, R> {
+    reader: &'a mut ReadContext<R>,
+    len: usize,
+    index: usize,
+    names: Option<&'static [&'static str]>,
+    is_fields: bool,
+}
+
+impl<'a, R> SeqAccess<'a, R> {
+    fn elements(reader: &'a mut ReadContext<R>, len: usize) -> Self {
+        Self {
+            reader,
+            len,
+            index: 0,
+            names: None,
+            is_fields: false,
+        }
+    }
+
+    fn fields(
+        reader: &'a mut ReadContext<R>,
+        len: usize,
+        names: Option<&'static [&'static str]>,
+    ) -> Self {
+        Self {
+            reader,
+            len,
+            index: 0,
+            names,
+            is_fields: true,
+        }
+    }
+}
+
+impl<'de, R: Read> de::SeqAccess<'de> for SeqAccess<'_, R> {
+    type Error = XbfError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(Deserializer::new(self.reader))
+            .map(Some)
+            .map_err(|e| match (self.is_fields, self.names) {
+                (true, Some(names)) => e.within(format_args!(".{}", names[index])),
+                (true, None) => e.within(format_args!(".{index}")),
+                (false, _) => e.within(format_args!("[{index}]")),
+            })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
+}
+
+/// Reads the entries of a map.
+struct MapAccess<'a, R> {
+    reader: &'a mut ReadContext<R>,
+    len: usize,
+    index: usize,
+    /// The encoded keys read so far. Keys are primitives, so they're equal exactly when their
+    /// encodings are, which is how [`XbfMap`](crate::XbfMap) compares them too.
+    keys: HashSet<Vec<u8>>,
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for MapAccess<'_, R> {
+    type Error = XbfError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let index = self.index;
+        let (key, bytes) = self
+            .reader
+            .record(|reader| seed.deserialize(Deserializer::new(reader)))
+            .map_err(|e| e.within(format_args!("[{index}]")))?;
+        if !self.keys.insert(bytes) {
+            return Err(XbfError::DuplicateKey.within(format_args!("[{index}]")));
+        }
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(Deserializer::new(self.reader))
+            .map_err(|e| e.within(format_args!("[{index}]")))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
+}
+
+/// Hands the variant index read from the wire to the enum's visitor.
+struct EnumAccess<'a, R> {
+    reader: &'a mut ReadContext<R>,
+    index: u16,
+}
+
+impl<'de, 'a, R: Read> de::EnumAccess<'de> for EnumAccess<'a, R> {
+    type Error = XbfError;
+    type Variant = Deserializer<'a, R>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let deserializer: U32Deserializer<XbfError> = u32::from(self.index).into_deserializer();
+        let value = seed.deserialize(deserializer)?;
+        Ok((value, Deserializer::new(self.reader)))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for Deserializer<'_, R> {
+    type Error = XbfError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(mut self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(SeqAccess::fields(self.reborrow().reader, len, None))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(SeqAccess::fields(
+            self.reborrow().reader,
+            fields.len(),
+            Some(fields),
+        ))
+    }
+}
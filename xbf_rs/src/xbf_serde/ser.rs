@@ -0,0 +1,514 @@
+use crate::{
+    util::{write_bytes, write_len, write_string},
+    Result, XbfConfig, XbfError,
+};
+use byteorder::{LittleEndian, WriteBytesExt};
+use serde::{ser, Serialize};
+use std::io::Write;
+
+/// A serde [`Serializer`](serde::Serializer) writing the XBF wire format.
+///
+/// Only values are written, never metadata, so the reader needs to know the shape of what was
+/// written ahead of time.
+pub struct Serializer<'a, W> {
+    writer: &'a mut W,
+    config: &'a XbfConfig,
+}
+
+impl<'a, W: Write> Serializer<'a, W> {
+    pub fn new(writer: &'a mut W, config: &'a XbfConfig) -> Self {
+        Self { writer, config }
+    }
+
+    fn reborrow(&mut self) -> Serializer<'_, W> {
+        Serializer {
+            writer: &mut *self.writer,
+            config: self.config,
+        }
+    }
+
+    fn write_variant_index(&mut self, variant_index: u32) -> Result<()> {
+        let index = u16::try_from(variant_index).map_err(|_| {
+            XbfError::TypeMismatch(format!(
+                "enum variant index {variant_index} does not fit in a u16"
+            ))
+        })?;
+        Ok(self.writer.write_u16::<LittleEndian>(index)?)
+    }
+
+    fn compound(&mut self) -> Compound<'_, W> {
+        Compound {
+            ser: self.reborrow(),
+            index: 0,
+        }
+    }
+
+    fn sequence(&mut self, len: Option<usize>) -> Result<Compound<'_, W>> {
+        let len = len.ok_or_else(|| {
+            XbfError::Custom("sequences and maps must know their length up front".to_string())
+        })?;
+        write_len(len, self.writer, self.config)?;
+        Ok(self.compound())
+    }
+}
+
+impl<'a, 'b, W: Write> ser::Serializer for &'a mut Serializer<'b, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        Ok(self.writer.write_u8(v.into())?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        Ok(self.writer.write_i8(v)?)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        Ok(self.writer.write_i16::<LittleEndian>(v)?)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        Ok(self.writer.write_i32::<LittleEndian>(v)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        Ok(self.writer.write_i64::<LittleEndian>(v)?)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        Ok(self.writer.write_i128::<LittleEndian>(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        Ok(self.writer.write_u8(v)?)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        Ok(self.writer.write_u16::<LittleEndian>(v)?)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        Ok(self.writer.write_u32::<LittleEndian>(v)?)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        Ok(self.writer.write_u64::<LittleEndian>(v)?)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        Ok(self.writer.write_u128::<LittleEndian>(v)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        Ok(self.writer.write_f32::<LittleEndian>(v)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        Ok(self.writer.write_f64::<LittleEndian>(v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        Ok(self.writer.write_u32::<LittleEndian>(v.into())?)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        write_string(v, self.writer, self.config)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        write_bytes(v, self.writer, self.config)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(self.writer.write_u8(0)?)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        self.writer.write_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.write_variant_index(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.write_variant_index(variant_index)?;
+        value
+            .serialize(self)
+            .map_err(|e| e.within(format_args!("::{variant}")))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.sequence(len)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self.compound())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Ok(self.compound())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.write_variant_index(variant_index)?;
+        Ok(self.compound())
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.sequence(len)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self.compound())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.write_variant_index(variant_index)?;
+        Ok(self.compound())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Writes the elements of a sequence, tuple, map or struct one after another.
+pub struct Compound<'a, W> {
+    ser: Serializer<'a, W>,
+    index: usize,
+}
+
+impl<W: Write> Compound<'_, W> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let index = self.index;
+        self.index += 1;
+        value
+            .serialize(&mut self.ser.reborrow())
+            .map_err(|e| e.within(format_args!("[{index}]")))
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, name: &str, value: &T) -> Result<()> {
+        value
+            .serialize(&mut self.ser.reborrow())
+            .map_err(|e| e.within(format_args!(".{name}")))
+    }
+
+    /// Every field of a struct is always written, since its metadata can't leave any out.
+    fn skipped(&self, name: &str) -> Result<()> {
+        Err(XbfError::Custom(format!(
+            "field {name} was skipped, but xbf structs always contain every field"
+        ))
+        .within(format_args!(".{name}")))
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let name = self.index.to_string();
+        self.index += 1;
+        self.field(&name, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeTuple::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeTuple::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        let index = self.index;
+        key.serialize(MapKeySerializer(self.ser.reborrow()))
+            .map_err(|e| e.within(format_args!("[{index}]")))
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        self.skipped(key)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        self.skipped(key)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a map key, which must be a primitive as map metadata only has room for one.
+struct MapKeySerializer<'a, W>(Serializer<'a, W>);
+
+fn not_a_key<T>(found: &str) -> Result<T> {
+    Err(XbfError::TypeMismatch(format!(
+        "map keys must be primitives, found {found}"
+    )))
+}
+
+macro_rules! write_key {
+    ($($serialize:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $serialize(mut self, v: $ty) -> Result<()> {
+                ser::Serializer::$serialize(&mut self.0, v)
+            }
+        )*
+    };
+}
+
+impl<W: Write> ser::Serializer for MapKeySerializer<'_, W> {
+    type Ok = ();
+    type Error = XbfError;
+
+    type SerializeSeq = ser::Impossible<(), XbfError>;
+    type SerializeTuple = ser::Impossible<(), XbfError>;
+    type SerializeTupleStruct = ser::Impossible<(), XbfError>;
+    type SerializeTupleVariant = ser::Impossible<(), XbfError>;
+    type SerializeMap = ser::Impossible<(), XbfError>;
+    type SerializeStruct = ser::Impossible<(), XbfError>;
+    type SerializeStructVariant = ser::Impossible<(), XbfError>;
+
+    write_key!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+    );
+
+    fn serialize_none(self) -> Result<()> {
+        not_a_key("an optional")
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<()> {
+        not_a_key("an optional")
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        not_a_key("a unit")
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
+        not_a_key(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        not_a_key(name)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        not_a_key(name)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        not_a_key("a sequence")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        not_a_key("a tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        not_a_key(name)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        not_a_key(name)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        not_a_key("a map")
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        not_a_key(name)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        not_a_key(name)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
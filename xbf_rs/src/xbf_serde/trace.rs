@@ -0,0 +1,461 @@
+use crate::{
+    Result, XbfArrayMetadata, XbfEnumMetadata, XbfError, XbfMapMetadata, XbfMetadata,
+    XbfOptionalMetadata, XbfPrimitiveMetadata, XbfStructMetadata, XbfVecMetadata,
+};
+use serde::de::{self, value::U32Deserializer, DeserializeSeed, IntoDeserializer, Visitor};
+
+/// Records the metadata of a type by deserializing a made up value of it.
+///
+/// Every sequence and map is given exactly one element, every optional is present, and each
+/// enum gets one of its variants. Enums are recorded separately, and the metadata built while
+/// tracing only holds an empty placeholder for them, named after their index in
+/// [`Tracer::enums`], until [`Tracer::resolve`] fills them in. This lets one pass pick a
+/// different variant than the last without invalidating anything recorded so far.
+///
+/// Types are told apart by their name together with the address of their field or variant
+/// names, since two types in different modules may share a name.
+#[derive(Default)]
+pub(crate) struct Tracer {
+    /// The metadata of the last value deserialized.
+    last: Option<XbfMetadata>,
+    /// The containers currently being traced, to catch recursive types.
+    stack: Vec<TypeKey>,
+    enums: Vec<EnumTrace>,
+}
+
+/// The name of a type and the address of its field or variant names, if it has any.
+type TypeKey = (&'static str, usize);
+
+fn type_key(name: &'static str, names: &'static [&'static str]) -> TypeKey {
+    (name, names.as_ptr() as usize)
+}
+
+struct EnumTrace {
+    key: TypeKey,
+    variants: Vec<(&'static str, Option<XbfMetadata>)>,
+    /// Used to cycle through the variants once they've all been seen, so that enums nested
+    /// inside any of them are reached as well.
+    next: usize,
+}
+
+impl Tracer {
+    /// Whether every enum seen so far has had all of its variants traced.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.enums
+            .iter()
+            .all(|x| x.variants.iter().all(|(_, metadata)| metadata.is_some()))
+    }
+
+    /// Takes the metadata of the last value deserialized.
+    pub(crate) fn take(&mut self) -> XbfMetadata {
+        self.last
+            .take()
+            .expect("every deserialize method records metadata")
+    }
+
+    /// Replaces every enum placeholder in the metadata with the variants that were traced.
+    pub(crate) fn resolve(&self, metadata: &XbfMetadata) -> XbfMetadata {
+        match metadata {
+            XbfMetadata::Primitive(_) => metadata.clone(),
+            XbfMetadata::Vec(x) => XbfVecMetadata::new(self.resolve(&x.inner_type)).into(),
+            XbfMetadata::Struct(x) => {
+                let fields = x
+                    .fields
+                    .iter()
+                    .map(|(name, x)| (name.clone(), self.resolve(x)))
+                    .collect();
+                XbfStructMetadata::new(x.name().to_string(), fields).into()
+            }
+            XbfMetadata::Optional(x) => {
+                XbfOptionalMetadata::new(self.resolve(&x.inner_type)).into()
+            }
+            XbfMetadata::Enum(x) => {
+                let trace = x
+                    .name()
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.enums.get(index))
+                    .expect("enums are only recorded as placeholders");
+                let variants = trace
+                    .variants
+                    .iter()
+                    .map(|(name, x)| {
+                        let x = x
+                            .as_ref()
+                            .expect("tracing finishes once all variants are seen");
+                        (name.to_string(), self.resolve(x))
+                    })
+                    .collect();
+                XbfEnumMetadata::new(trace.key.0.to_string(), variants).into()
+            }
+            XbfMetadata::Map(x) => {
+                XbfMapMetadata::new(x.key_type, self.resolve(&x.value_type)).into()
+            }
+            XbfMetadata::Array(x) => {
+                XbfArrayMetadata::new(self.resolve(&x.inner_type), x.len).into()
+            }
+        }
+    }
+
+    fn enter(&mut self, key: TypeKey) -> Result<()> {
+        if self.stack.contains(&key) {
+            return Err(XbfError::Custom(format!(
+                "{} is recursive, which xbf metadata can't describe",
+                key.0
+            )));
+        }
+        self.stack.push(key);
+        Ok(())
+    }
+
+    fn exit(&mut self) {
+        self.stack.pop();
+    }
+
+    fn primitive<T>(&mut self, primitive: XbfPrimitiveMetadata, value: T) -> T {
+        self.last = Some(primitive.into());
+        value
+    }
+
+    /// Traces each field of a struct, tuple or variant and records the struct.
+    fn fields<'de, V: Visitor<'de>>(
+        &mut self,
+        name: &str,
+        names: Option<&'static [&'static str]>,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        let mut access = FieldsAccess {
+            tracer: self,
+            names,
+            len,
+            fields: vec![],
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        let fields = access.fields;
+        self.last = Some(XbfStructMetadata::new(name.to_string(), fields).into());
+        Ok(value)
+    }
+}
+
+fn unit_struct(name: &str) -> XbfMetadata {
+    XbfStructMetadata::new(name.to_string(), vec![]).into()
+}
+
+macro_rules! trace_primitive {
+    ($deserialize:ident, $visit:ident, $primitive:ident, $value:expr) => {
+        fn $deserialize<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            visitor.$visit(self.primitive(XbfPrimitiveMetadata::$primitive, $value))
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Tracer {
+    type Error = XbfError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(XbfError::Custom(
+            "xbf is not self-describing, so a value of unknown type can't be traced".to_string(),
+        ))
+    }
+
+    trace_primitive!(deserialize_bool, visit_bool, Bool, false);
+    trace_primitive!(deserialize_i8, visit_i8, I8, 0);
+    trace_primitive!(deserialize_i16, visit_i16, I16, 0);
+    trace_primitive!(deserialize_i32, visit_i32, I32, 0);
+    trace_primitive!(deserialize_i64, visit_i64, I64, 0);
+    trace_primitive!(deserialize_i128, visit_i128, I128, 0);
+    trace_primitive!(deserialize_u8, visit_u8, U8, 0);
+    trace_primitive!(deserialize_u16, visit_u16, U16, 0);
+    trace_primitive!(deserialize_u32, visit_u32, U32, 0);
+    trace_primitive!(deserialize_u64, visit_u64, U64, 0);
+    trace_primitive!(deserialize_u128, visit_u128, U128, 0);
+    trace_primitive!(deserialize_f32, visit_f32, F32, 0.0);
+    trace_primitive!(deserialize_f64, visit_f64, F64, 0.0);
+    trace_primitive!(deserialize_char, visit_char, Char, '\0');
+    trace_primitive!(deserialize_str, visit_string, String, String::new());
+    trace_primitive!(deserialize_string, visit_string, String, String::new());
+    trace_primitive!(deserialize_bytes, visit_byte_buf, Bytes, vec![]);
+    trace_primitive!(deserialize_byte_buf, visit_byte_buf, Bytes, vec![]);
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = visitor.visit_some(&mut *self)?;
+        let inner = self.take();
+        self.last = Some(XbfOptionalMetadata::new(inner).into());
+        Ok(value)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.last = Some(unit_struct("()"));
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.last = Some(unit_struct(name));
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.enter(type_key(name, &[]))?;
+        let value = visitor.visit_newtype_struct(&mut *self)?;
+        self.exit();
+        Ok(value)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut access = OneElementAccess {
+            tracer: self,
+            metadata: None,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        let inner = access.metadata.ok_or_else(|| {
+            XbfError::Custom("a sequence was traced without reading an element".to_string())
+        })?;
+        self.last = Some(XbfVecMetadata::new(inner).into());
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        self.fields("tuple", None, len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.enter(type_key(name, &[]))?;
+        let value = self.fields(name, None, len, visitor)?;
+        self.exit();
+        Ok(value)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut access = OneEntryAccess {
+            tracer: self,
+            key: None,
+            value: None,
+        };
+        let value = visitor.visit_map(&mut access)?;
+        let (Some(key), Some(inner)) = (access.key, access.value) else {
+            return Err(XbfError::Custom(
+                "a map was traced without reading an entry".to_string(),
+            ));
+        };
+        self.last = Some(XbfMapMetadata::new(key, inner).into());
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.enter(type_key(name, fields))?;
+        let value = self.fields(name, Some(fields), fields.len(), visitor)?;
+        self.exit();
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if variants.is_empty() {
+            return Err(XbfError::Custom(format!(
+                "{name} has no variants, so no value of it can be traced"
+            )));
+        }
+        let key = type_key(name, variants);
+        self.enter(key)?;
+        let id = match self.enums.iter().position(|x| x.key == key) {
+            Some(id) => id,
+            None => {
+                self.enums.push(EnumTrace {
+                    key,
+                    variants: variants.iter().map(|x| (*x, None)).collect(),
+                    next: 0,
+                });
+                self.enums.len() - 1
+            }
+        };
+        let trace = &mut self.enums[id];
+        let index = trace
+            .variants
+            .iter()
+            .position(|(_, metadata)| metadata.is_none())
+            .unwrap_or_else(|| {
+                trace.next = (trace.next + 1) % trace.variants.len();
+                trace.next
+            });
+        let value = visitor.visit_enum(VariantAccess {
+            tracer: &mut *self,
+            index,
+            name: variants[index],
+        })?;
+        let payload = self.take();
+        self.enums[id].variants[index].1 = Some(payload);
+        self.exit();
+        self.last = Some(XbfEnumMetadata::new(id.to_string(), vec![]).into());
+        Ok(value)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(XbfError::Custom(
+            "xbf is not self-describing, so an identifier can't be traced".to_string(),
+        ))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(XbfError::Custom(
+            "xbf is not self-describing, so an ignored value can't be traced".to_string(),
+        ))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Gives a sequence a single element.
+struct OneElementAccess<'a> {
+    tracer: &'a mut Tracer,
+    metadata: Option<XbfMetadata>,
+}
+
+impl<'de> de::SeqAccess<'de> for OneElementAccess<'_> {
+    type Error = XbfError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.metadata.is_some() {
+            return Ok(None);
+        }
+        let value = seed.deserialize(&mut *self.tracer)?;
+        self.metadata = Some(self.tracer.take());
+        Ok(Some(value))
+    }
+}
+
+/// Gives a map a single entry, whose key must be a primitive.
+struct OneEntryAccess<'a> {
+    tracer: &'a mut Tracer,
+    key: Option<XbfPrimitiveMetadata>,
+    value: Option<XbfMetadata>,
+}
+
+impl<'de> de::MapAccess<'de> for OneEntryAccess<'_> {
+    type Error = XbfError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.key.is_some() {
+            return Ok(None);
+        }
+        let key = seed.deserialize(&mut *self.tracer)?;
+        match self.tracer.take() {
+            XbfMetadata::Primitive(x) => self.key = Some(x),
+            x => {
+                return Err(XbfError::TypeMismatch(format!(
+                    "map keys must be primitives, found {x:?}"
+                )))
+            }
+        }
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = seed.deserialize(&mut *self.tracer)?;
+        self.value = Some(self.tracer.take());
+        Ok(value)
+    }
+}
+
+/// Gives a struct, tuple or variant each of its fields.
+struct FieldsAccess<'a> {
+    tracer: &'a mut Tracer,
+    names: Option<&'static [&'static str]>,
+    len: usize,
+    fields: Vec<(String, XbfMetadata)>,
+}
+
+impl<'de> de::SeqAccess<'de> for FieldsAccess<'_> {
+    type Error = XbfError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        let index = self.fields.len();
+        if index == self.len {
+            return Ok(None);
+        }
+        let name = match self.names {
+            Some(names) => names[index].to_string(),
+            None => index.to_string(),
+        };
+        let value = seed
+            .deserialize(&mut *self.tracer)
+            .map_err(|e| e.within(format_args!(".{name}")))?;
+        self.fields.push((name, self.tracer.take()));
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.fields.len())
+    }
+}
+
+/// Picks the variant chosen by [`Tracer`] and traces its payload.
+struct VariantAccess<'a> {
+    tracer: &'a mut Tracer,
+    index: usize,
+    name: &'static str,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for VariantAccess<'a> {
+    type Error = XbfError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let deserializer: U32Deserializer<XbfError> = (self.index as u32).into_deserializer();
+        Ok((seed.deserialize(deserializer)?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_> {
+    type Error = XbfError;
+
+    fn unit_variant(self) -> Result<()> {
+        self.tracer.last = Some(unit_struct(self.name));
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(&mut *self.tracer)
+            .map_err(|e| e.within(format_args!("::{}", self.name)))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        self.tracer
+            .fields(self.name, None, len, visitor)
+            .map_err(|e| e.within(format_args!("::{}", self.name)))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.tracer
+            .fields(self.name, Some(fields), fields.len(), visitor)
+            .map_err(|e| e.within(format_args!("::{}", self.name)))
+    }
+}
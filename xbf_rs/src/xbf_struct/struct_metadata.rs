@@ -1,10 +1,16 @@
 use crate::{
-    base_metadata::XbfMetadataUpcast,
-    util::{read_string, write_string},
-    XbfMetadata, XbfStruct, VEC_METADATA_DISCRIMINANT,
+    base_metadata::{check_name_lens, check_names, XbfMetadataUpcast},
+    util::{
+        bytes_encoded_len, len_encoded_len, preallocate, read_string, write_len, write_string,
+        ReadContext,
+    },
+    InvalidMetadataError, Result, XbfConfig, XbfMetadata, XbfStruct, VEC_METADATA_DISCRIMINANT,
+};
+use byteorder::WriteBytesExt;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
 };
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{self, Read, Write};
 
 /// The metadata discriminant for a Struct type.
 ///
@@ -15,8 +21,8 @@ pub const STRUCT_METADATA_DISCRIMINANT: u8 = VEC_METADATA_DISCRIMINANT + 1;
 /// Metadata for a Struct type.
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct XbfStructMetadata {
-    name: String,
-    pub(super) fields: Vec<(String, XbfMetadata)>,
+    name: Arc<str>,
+    pub(crate) fields: Arc<[(String, XbfMetadata)]>,
 }
 
 impl XbfStructMetadata {
@@ -36,9 +42,86 @@ impl XbfStructMetadata {
     ///     ],
     /// );
     ///
-    /// // TODO: accessors for the name and fields? with a similar api to a hashmap?
+    /// assert_eq!(metadata.name(), "test_struct");
+    /// assert_eq!(metadata.fields().len(), 2);
+    /// assert_eq!(metadata.field("b"), Some(&XbfPrimitiveMetadata::U64.into()));
+    /// assert_eq!(metadata.field_index("b"), Some(1));
+    /// assert_eq!(metadata.field("c"), None);
+    /// ```
     pub fn new(name: String, fields: Vec<(String, XbfMetadata)>) -> Self {
-        Self { name, fields }
+        Self {
+            name: name.into(),
+            fields: fields.into(),
+        }
+    }
+
+    /// Tries to create a new [`XbfStructMetadata`], checking that every field has a name that is
+    /// not empty and not used by another field, and that every name fits in the length prefix of
+    /// the default [`XbfConfig`].
+    ///
+    /// Only the fields of this struct are checked, use
+    /// [`XbfMetadata::validate`](crate::XbfMetadata::validate) to check the types nested inside
+    /// them as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidMetadataError`] for the first name that is empty, repeated or too long.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::InvalidMetadataError;
+    /// use xbf_rs::XbfStructMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfStructMetadata::try_new(
+    ///     "test_struct".to_string(),
+    ///     vec![
+    ///         ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("b".to_string(), XbfPrimitiveMetadata::U64.into()),
+    ///     ],
+    /// );
+    /// assert!(metadata.is_ok());
+    ///
+    /// let metadata = XbfStructMetadata::try_new(
+    ///     "test_struct".to_string(),
+    ///     vec![
+    ///         ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("a".to_string(), XbfPrimitiveMetadata::U64.into()),
+    ///     ],
+    /// );
+    /// assert_eq!(
+    ///     metadata,
+    ///     Err(InvalidMetadataError::DuplicateName { index: 1, name: "a".to_string() })
+    /// );
+    /// ```
+    pub fn try_new(
+        name: String,
+        fields: Vec<(String, XbfMetadata)>,
+    ) -> Result<Self, InvalidMetadataError> {
+        check_name_lens(&name, &fields)?;
+        check_names(&fields)?;
+        Ok(Self::new(name, fields))
+    }
+
+    /// Returns the name of the struct.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name and type of every field, in the order they are sent.
+    pub fn fields(&self) -> &[(String, XbfMetadata)] {
+        &self.fields
+    }
+
+    /// Returns the type of the field with the given name, if there is one.
+    pub fn field(&self, name: &str) -> Option<&XbfMetadata> {
+        self.field_index(name).map(|i| &self.fields[i].1)
+    }
+
+    /// Returns the position of the field with the given name, if there is one.
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|(x, _)| x == name)
     }
 
     /// Serialize a struct as defined by the XBF specification.
@@ -83,12 +166,25 @@ impl XbfStructMetadata {
     ///
     /// assert_eq!(writer, expected);
     /// ```
-    pub fn serialize_struct_metadata(&self, writer: &mut impl Write) -> io::Result<()> {
+    pub fn serialize_struct_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_struct_metadata_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize struct metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_struct_metadata`] for more details.
+    pub fn serialize_struct_metadata_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
         writer.write_u8(STRUCT_METADATA_DISCRIMINANT)?;
-        write_string(&self.name, writer)?;
-        writer.write_u16::<LittleEndian>(self.fields.len() as u16)?;
+        write_string(&self.name, writer, config)?;
+        write_len(self.fields.len(), writer, config)?;
         self.fields.iter().try_for_each(|(name, type_)| {
-            write_string(name, writer).and_then(|_| type_.serialize_base_metadata(writer))
+            write_string(name, writer, config)
+                .and_then(|_| type_.serialize_base_metadata_with_config(writer, config))
+                .map_err(|e| e.within(format_args!(".{name}")))
         })
     }
 
@@ -130,17 +226,58 @@ impl XbfStructMetadata {
     ///     (field1_name, XbfPrimitiveMetadata::I32.into()),
     ///     (field2_name, XbfPrimitiveMetadata::U64.into()),
     /// ]));
-    pub fn deserialize_struct_metadata(reader: &mut impl Read) -> io::Result<XbfStructMetadata> {
-        let name = read_string(reader)?;
-        let len = reader.read_u16::<LittleEndian>()?;
-        let mut fields = Vec::with_capacity(len as usize);
-        for _ in 0..len {
-            fields.push((
-                read_string(reader)?,
-                XbfMetadata::deserialize_base_metadata(reader)?,
-            ))
-        }
-        Ok(XbfStructMetadata { name, fields })
+    pub fn deserialize_struct_metadata(reader: &mut impl Read) -> Result<XbfStructMetadata> {
+        Self::deserialize_struct_metadata_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize struct metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_struct_metadata`] for more details.
+    pub fn deserialize_struct_metadata_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfStructMetadata> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_struct_metadata_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_struct_metadata_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfStructMetadata> {
+        reader.nested(|reader| {
+            let name = read_string(reader)?;
+            let len = reader.read_fields_len()?;
+            let mut fields = preallocate(len);
+            for i in 0..len {
+                let field_name =
+                    read_string(reader).map_err(|e| e.within(format_args!(".<field {i}>")))?;
+                let field_type = XbfMetadata::deserialize_base_metadata_with_context(reader)
+                    .map_err(|e| e.within(format_args!(".{field_name}")))?;
+                fields.push((field_name, field_type))
+            }
+            check_names(&fields)?;
+            Ok(XbfStructMetadata::new(name, fields))
+        })
+    }
+
+    /// Returns the number of bytes [`Self::serialize_struct_metadata`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_struct_metadata_with_config`] would write using the given
+    /// [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        1 + bytes_encoded_len(self.name.len(), config)
+            + len_encoded_len(self.fields.len(), config)
+            + self
+                .fields
+                .iter()
+                .map(|(name, x)| {
+                    bytes_encoded_len(name.len(), config) + x.encoded_len_with_config(config)
+                })
+                .sum::<usize>()
     }
 }
 
@@ -155,7 +292,11 @@ impl From<&XbfStruct> for XbfStructMetadata {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{xbf_primitive::XbfPrimitiveMetadata, XbfVecMetadata};
+    use crate::{
+        xbf_primitive::XbfPrimitiveMetadata, DecodeLimits, LengthEncoding, LimitExceededError,
+        XbfError, XbfVecMetadata,
+    };
+    use byteorder::LittleEndian;
     use std::io::Cursor;
 
     #[test]
@@ -173,13 +314,13 @@ mod test {
                 ),
                 (
                     "c".to_string(),
-                    XbfMetadata::Struct(XbfStructMetadata {
-                        name: "inner".to_string(),
-                        fields: vec![(
+                    XbfMetadata::Struct(XbfStructMetadata::new(
+                        "inner".to_string(),
+                        vec![(
                             "d".to_string(),
                             XbfMetadata::Primitive(XbfPrimitiveMetadata::I32),
                         )],
-                    }),
+                    )),
                 ),
             ],
         );
@@ -191,27 +332,27 @@ mod test {
         // disciminant
         expected.write_u8(STRUCT_METADATA_DISCRIMINANT).unwrap();
         // name
-        write_string(&metadata.name, &mut expected).unwrap();
+        write_string(&metadata.name, &mut expected, &XbfConfig::default()).unwrap();
         // num of fields
         expected.write_u16::<LittleEndian>(3).unwrap();
         // field a
-        write_string("a", &mut expected).unwrap();
+        write_string("a", &mut expected, &XbfConfig::default()).unwrap();
         expected.write_u8(XbfPrimitiveMetadata::I32 as u8).unwrap();
         // field b
-        write_string("b", &mut expected).unwrap();
+        write_string("b", &mut expected, &XbfConfig::default()).unwrap();
         expected.write_u8(VEC_METADATA_DISCRIMINANT).unwrap();
         expected.write_u8(XbfPrimitiveMetadata::I32 as u8).unwrap();
         // field c
-        write_string("c", &mut expected).unwrap();
+        write_string("c", &mut expected, &XbfConfig::default()).unwrap();
         // field c is a struct, so do struct stuff again
         // discriminant
         expected.write_u8(STRUCT_METADATA_DISCRIMINANT).unwrap();
         // name
-        write_string("inner", &mut expected).unwrap();
+        write_string("inner", &mut expected, &XbfConfig::default()).unwrap();
         // num_of_fields
         expected.write_u16::<LittleEndian>(1).unwrap();
         // field d
-        write_string("d", &mut expected).unwrap();
+        write_string("d", &mut expected, &XbfConfig::default()).unwrap();
         expected.write_u8(XbfPrimitiveMetadata::I32 as u8).unwrap();
 
         assert_eq!(expected, writer);
@@ -221,6 +362,122 @@ mod test {
         assert_eq!(XbfMetadata::Struct(metadata), deserialized);
     }
 
+    #[test]
+    fn metadata_serde_works_with_varint_lengths() {
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::Varint);
+        let metadata = XbfStructMetadata::new(
+            "test".to_string(),
+            vec![("a".to_string(), XbfPrimitiveMetadata::I32.into())],
+        );
+
+        let mut writer = Vec::new();
+        metadata
+            .serialize_struct_metadata_with_config(&mut writer, &config)
+            .unwrap();
+
+        let mut expected = vec![STRUCT_METADATA_DISCRIMINANT, 4];
+        expected.extend_from_slice(b"test");
+        expected.extend_from_slice(&[1, 1]);
+        expected.extend_from_slice(b"a");
+        expected.push(XbfPrimitiveMetadata::I32 as u8);
+
+        assert_eq!(writer, expected);
+
+        let mut reader = Cursor::new(writer);
+        let deserialized =
+            XbfMetadata::deserialize_base_metadata_with_config(&mut reader, &config).unwrap();
+        assert_eq!(XbfMetadata::Struct(metadata), deserialized);
+    }
+
+    #[test]
+    fn struct_field_limit_is_enforced() {
+        let fields = (0..3)
+            .map(|i| (format!("field{i}"), XbfPrimitiveMetadata::I32.into()))
+            .collect();
+        let metadata = XbfStructMetadata::new("test_struct".to_string(), fields);
+        let mut writer = vec![];
+        metadata.serialize_struct_metadata(&mut writer).unwrap();
+
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_struct_fields(2));
+        let err =
+            XbfMetadata::deserialize_base_metadata_with_config(&mut Cursor::new(writer), &config)
+                .unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::StructFields {
+                limit: 2,
+                actual: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn try_new_rejects_invalid_field_names() {
+        let metadata = XbfStructMetadata::try_new(
+            "test_struct".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("".to_string(), XbfPrimitiveMetadata::I32.into()),
+            ],
+        );
+        assert_eq!(metadata, Err(InvalidMetadataError::EmptyName { index: 1 }));
+
+        let metadata = XbfStructMetadata::try_new(
+            "test_struct".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+            ],
+        );
+        assert_eq!(
+            metadata,
+            Err(InvalidMetadataError::DuplicateName {
+                index: 2,
+                name: "a".to_string()
+            })
+        );
+
+        let long_name = "a".repeat(u16::MAX as usize + 1);
+        let too_long = Err(InvalidMetadataError::NameTooLong {
+            len: long_name.len(),
+            encoding: LengthEncoding::U16,
+        });
+        let metadata = XbfStructMetadata::try_new(long_name.clone(), vec![]);
+        assert_eq!(metadata, too_long);
+
+        let metadata = XbfStructMetadata::try_new(
+            "test_struct".to_string(),
+            vec![(long_name, XbfPrimitiveMetadata::I32.into())],
+        );
+        assert_eq!(metadata, too_long);
+    }
+
+    #[test]
+    fn deserialize_rejects_duplicate_field_names() {
+        let inner = XbfStructMetadata::new(
+            "inner".to_string(),
+            vec![
+                ("d".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("d".to_string(), XbfPrimitiveMetadata::U8.into()),
+            ],
+        );
+        let metadata =
+            XbfStructMetadata::new("outer".to_string(), vec![("c".to_string(), inner.into())]);
+        let mut writer = vec![];
+        metadata.serialize_struct_metadata(&mut writer).unwrap();
+
+        let err = XbfMetadata::deserialize_base_metadata(&mut Cursor::new(&writer)).unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::InvalidMetadata(InvalidMetadataError::DuplicateName { index: 1, .. })
+        ));
+        assert_eq!(err.path(), Some("root.c"));
+        assert_eq!(err.offset(), Some(writer.len() as u64));
+    }
+
     #[test]
     fn upcast_works() {
         let struct_metadata = XbfStructMetadata::new(
@@ -1,8 +1,11 @@
-use crate::{XbfMetadata, XbfStructMetadata, XbfType, XbfTypeUpcast};
+use crate::{
+    util::ReadContext, Result, XbfConfig, XbfError, XbfMetadata, XbfStructMetadata, XbfType,
+    XbfTypeUpcast,
+};
 use std::{
     error::Error,
     fmt::Display,
-    io::{self, Read, Write},
+    io::{Read, Write},
 };
 
 /// A struct as defined by the XBF specification.
@@ -17,8 +20,9 @@ impl XbfStruct {
     ///
     /// # Errors
     ///
-    /// If all fields are not the same XBF type as what's specififed in the metadata,
-    /// returns an [`StructFieldMismatchError`].
+    /// If the number of fields is not the number specified in the metadata, or all fields are not
+    /// the same XBF type as what's specififed in the metadata, returns an
+    /// [`StructFieldMismatchError`].
     ///
     /// # Examples
     ///
@@ -48,18 +52,28 @@ impl XbfStruct {
     ///
     /// assert!(struct_type.is_ok());
     ///
-    /// let struct2 = XbfStruct::new(metadata, vec![
+    /// let struct2 = XbfStruct::new(metadata.clone(), vec![
     ///     XbfPrimitive::I32(42).into(),
     ///     XbfPrimitive::I64(42).into(),
     ///   
     /// ]);
     ///
     /// assert!(struct2.is_err());
+    ///
+    /// let struct3 = XbfStruct::new(metadata, vec![XbfPrimitive::I32(42).into()]);
+    ///
+    /// assert!(struct3.is_err());
     ///```
     pub fn new(
         metadata: XbfStructMetadata,
         fields: Vec<XbfType>,
     ) -> Result<Self, StructFieldMismatchError> {
+        if fields.len() != metadata.fields.len() {
+            return Err(StructFieldMismatchError::wrong_count(
+                metadata.fields.len(),
+                fields.len(),
+            ));
+        }
         for ((name, expected_field_type), val) in metadata.fields.iter().zip(fields.iter()) {
             let actual_field_type = XbfMetadata::from(val);
             if *expected_field_type != actual_field_type {
@@ -105,12 +119,155 @@ impl XbfStruct {
         Self { metadata, fields }
     }
 
+    /// Returns a reference to the value of the field with the given name, if there is one.
+    ///
+    /// A struct made with [`Self::new_unchecked`] may have fewer values than fields, in which case
+    /// the fields without one return `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfStruct;
+    /// use xbf_rs::XbfStructMetadata;
+    /// use xbf_rs::XbfType;
+    ///
+    /// let metadata = XbfStructMetadata::new(
+    ///     "player".to_string(),
+    ///     vec![("id".to_string(), XbfPrimitiveMetadata::U8.into())],
+    /// );
+    /// let player = XbfStruct::new(metadata, vec![XbfPrimitive::U8(7).into()]).unwrap();
+    ///
+    /// assert_eq!(player.get("id"), Some(&XbfType::Primitive(XbfPrimitive::U8(7))));
+    /// assert_eq!(player.get("name"), None);
+    /// ```
+    pub fn get(&self, name: &str) -> Option<&XbfType> {
+        self.metadata
+            .field_index(name)
+            .and_then(|i| self.fields.get(i))
+    }
+
+    /// Returns a mutable reference to the value of the field with the given name, if there is
+    /// one.
+    ///
+    /// Nothing stops the value being replaced with one of a different type, which leaves the
+    /// struct in the same state as [`Self::new_unchecked`] with the wrong fields. Use
+    /// [`Self::set`] to replace a value with one that is checked against the metadata.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut XbfType> {
+        self.metadata
+            .field_index(name)
+            .and_then(|i| self.fields.get_mut(i))
+    }
+
+    /// Replaces the value of the field with the given name, returning the old value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`StructFieldMismatchError`] if the struct has no field with the given name, if
+    /// the value is not the type of that field in the metadata, or if the struct was made with
+    /// [`Self::new_unchecked`] without a value for the field. The struct is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfStruct;
+    /// use xbf_rs::XbfStructMetadata;
+    /// use xbf_rs::XbfType;
+    ///
+    /// let metadata = XbfStructMetadata::new(
+    ///     "player".to_string(),
+    ///     vec![("id".to_string(), XbfPrimitiveMetadata::U8.into())],
+    /// );
+    /// let mut player = XbfStruct::new(metadata, vec![XbfPrimitive::U8(7).into()]).unwrap();
+    ///
+    /// let old = player.set("id", XbfPrimitive::U8(8).into()).unwrap();
+    /// assert_eq!(old, XbfPrimitive::U8(7).into());
+    /// assert_eq!(player.get("id"), Some(&XbfType::Primitive(XbfPrimitive::U8(8))));
+    ///
+    /// assert!(player.set("id", XbfPrimitive::U16(8).into()).is_err());
+    /// assert!(player.set("name", XbfPrimitive::U8(8).into()).is_err());
+    /// ```
+    pub fn set(&mut self, name: &str, value: XbfType) -> Result<XbfType, StructFieldMismatchError> {
+        let index = self
+            .metadata
+            .field_index(name)
+            .ok_or_else(|| StructFieldMismatchError::unknown_field(name))?;
+        let expected_field_type = &self.metadata.fields[index].1;
+        let actual_field_type = XbfMetadata::from(&value);
+        if *expected_field_type != actual_field_type {
+            return Err(StructFieldMismatchError::new(
+                name,
+                expected_field_type,
+                &actual_field_type,
+            ));
+        }
+        let old = self
+            .fields
+            .get_mut(index)
+            .ok_or_else(|| StructFieldMismatchError::missing_value(name))?;
+        Ok(std::mem::replace(old, value))
+    }
+
+    /// Returns an iterator over the name and value of every field, in the order they are sent.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfStruct;
+    /// use xbf_rs::XbfStructMetadata;
+    ///
+    /// let metadata = XbfStructMetadata::new(
+    ///     "point".to_string(),
+    ///     vec![
+    ///         ("x".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///         ("y".to_string(), XbfPrimitiveMetadata::I32.into()),
+    ///     ],
+    /// );
+    /// let point = XbfStruct::new(
+    ///     metadata,
+    ///     vec![XbfPrimitive::I32(1).into(), XbfPrimitive::I32(2).into()],
+    /// )
+    /// .unwrap();
+    ///
+    /// let names: Vec<_> = point.iter().map(|(name, _)| name).collect();
+    /// assert_eq!(names, ["x", "y"]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &XbfType)> {
+        self.metadata
+            .fields
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .zip(self.fields.iter())
+    }
+
+    /// Consumes the struct, returning the name and value of every field in the order they are
+    /// sent.
+    pub fn into_fields(self) -> Vec<(String, XbfType)> {
+        self.metadata
+            .fields
+            .iter()
+            .map(|(name, _)| name.clone())
+            .zip(self.fields)
+            .collect()
+    }
+
     /// Serialize a struct as defined by the XBF specification.
     ///
     /// This function **does not** write out the metadata of the type. If you want to write out the
     /// metadata, get the metadata with [`Self::get_metadata`] and serialize that with
     /// [`XbfStructMetadata::serialize_struct_metadata`].
     ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns [`XbfError::TypeMismatch`] if the struct
+    /// was made with [`Self::new_unchecked`] and has a different number of values than fields.
+    /// Nothing is written in this case.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -135,10 +292,34 @@ impl XbfStruct {
     /// let mut expected = 42i32.to_le_bytes();
     /// assert_eq!(writer, expected);
     /// ```
-    pub fn serialize_struct_type(&self, writer: &mut impl Write) -> io::Result<()> {
+    pub fn serialize_struct_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_struct_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize a struct using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_struct_type`] for more details.
+    pub fn serialize_struct_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        if self.fields.len() != self.metadata.fields.len() {
+            return Err(XbfError::TypeMismatch(
+                StructFieldMismatchError::wrong_count(
+                    self.metadata.fields.len(),
+                    self.fields.len(),
+                )
+                .to_string(),
+            ));
+        }
         self.fields
             .iter()
-            .try_for_each(|f| f.serialize_base_type(writer))
+            .zip(self.metadata.fields.iter())
+            .try_for_each(|(f, (name, _))| {
+                f.serialize_base_type_with_config(writer, config)
+                    .map_err(|e| e.within(format_args!(".{name}")))
+            })
     }
 
     /// Deserialize a struct as defined by the XBF specification.
@@ -173,18 +354,43 @@ impl XbfStruct {
     pub fn deserialize_struct_type(
         metadata: &XbfStructMetadata,
         reader: &mut impl Read,
-    ) -> io::Result<XbfStruct> {
-        let mut struct_fields = vec![];
-        for (_, field_type) in metadata.fields.iter() {
-            struct_fields.push(XbfType::deserialize_base_type(field_type, reader)?);
-        }
-        Ok(Self::new_unchecked(metadata.clone(), struct_fields))
+    ) -> Result<XbfStruct> {
+        Self::deserialize_struct_type_with_config(metadata, reader, &XbfConfig::default())
+    }
+
+    /// Deserialize a struct using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_struct_type`] for more details.
+    pub fn deserialize_struct_type_with_config(
+        metadata: &XbfStructMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfStruct> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_struct_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_struct_type_with_context(
+        metadata: &XbfStructMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfStruct> {
+        reader.nested(|reader| {
+            let mut struct_fields = vec![];
+            for (name, field_type) in metadata.fields.iter() {
+                struct_fields.push(
+                    XbfType::deserialize_base_type_with_context(field_type, reader)
+                        .map_err(|e| e.within(format_args!(".{name}")))?,
+                );
+            }
+            Ok(Self::new_unchecked(metadata.clone(), struct_fields))
+        })
     }
 
     /// Returns the metadata of the struct.
     ///
-    /// Getting the metadata returns an owned [`XbfStructMetadata`], which requires a clone to take
-    /// place. This will likely be changed in the future to be mroe efficient.
+    /// Getting the metadata returns an owned [`XbfStructMetadata`], which only
+    /// clones the reference counted pointers it holds.
     ///
     /// # Example
     ///
@@ -214,6 +420,20 @@ impl XbfStruct {
     pub fn get_metadata(&self) -> XbfStructMetadata {
         self.metadata.clone()
     }
+
+    /// Returns the number of bytes [`Self::serialize_struct_type`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_struct_type_with_config`] would write using
+    /// the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        self.fields
+            .iter()
+            .map(|x| x.encoded_len_with_config(config))
+            .sum()
+    }
 }
 
 impl XbfTypeUpcast for XbfStruct {}
@@ -230,6 +450,20 @@ impl StructFieldMismatchError {
         let s = format!("provided value for field {field_name} is of type {actual_field_type:?}, expected {expected_field_type:?}");
         StructFieldMismatchError(s)
     }
+
+    fn unknown_field(field_name: &str) -> StructFieldMismatchError {
+        StructFieldMismatchError(format!("struct has no field named {field_name}"))
+    }
+
+    fn missing_value(field_name: &str) -> StructFieldMismatchError {
+        StructFieldMismatchError(format!("struct has no value for field {field_name}"))
+    }
+
+    fn wrong_count(expected: usize, actual: usize) -> StructFieldMismatchError {
+        StructFieldMismatchError(format!(
+            "struct has {expected} fields, but {actual} values were provided"
+        ))
+    }
 }
 
 impl Display for StructFieldMismatchError {
@@ -243,7 +477,10 @@ impl Error for StructFieldMismatchError {}
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{XbfMetadataUpcast, XbfPrimitive, XbfPrimitiveMetadata, XbfVec, XbfVecMetadata};
+    use crate::{
+        XbfError, XbfMetadataUpcast, XbfOptional, XbfOptionalMetadata, XbfPrimitive,
+        XbfPrimitiveMetadata, XbfVec, XbfVecMetadata,
+    };
     use std::io::Cursor;
 
     #[test]
@@ -275,9 +512,16 @@ mod test {
         );
 
         assert!(with_correct_fields.is_ok());
-        with_correct_fields.expect("a valid struct");
+        let with_correct_fields = with_correct_fields.expect("a valid struct");
 
-        // TODO: accessor methods to test the contents of what's in the struct
+        assert_eq!(
+            with_correct_fields.get(field1_name),
+            Some(&XbfPrimitive::I32(42).into_base_type())
+        );
+        assert_eq!(
+            with_correct_fields.get(field2_name),
+            Some(&XbfPrimitive::U64(69).into_base_type())
+        );
     }
 
     #[test]
@@ -320,6 +564,87 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_struct_new_rejects_wrong_field_count() {
+        let metadata = XbfStructMetadata::new(
+            "test_struct".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::U64.into()),
+            ],
+        );
+
+        let too_few = XbfStruct::new(metadata.clone(), vec![XbfPrimitive::I32(42).into()]);
+        assert_eq!(
+            too_few.unwrap_err().to_string(),
+            StructFieldMismatchError::wrong_count(2, 1).to_string()
+        );
+
+        let too_many = XbfStruct::new(
+            metadata,
+            vec![
+                XbfPrimitive::I32(42).into(),
+                XbfPrimitive::U64(69).into(),
+                XbfPrimitive::U64(69).into(),
+            ],
+        );
+        assert_eq!(
+            too_many.unwrap_err().to_string(),
+            "struct has 2 fields, but 3 values were provided"
+        );
+    }
+
+    #[test]
+    fn test_struct_new_with_optional_field_works() {
+        let optional_metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+        let metadata = XbfStructMetadata::new(
+            "test_struct".to_string(),
+            vec![("a".to_string(), optional_metadata.to_base_metadata())],
+        );
+
+        let with_none = XbfStruct::new(
+            metadata.clone(),
+            vec![XbfOptional::new(optional_metadata.clone(), None)
+                .unwrap()
+                .into_base_type()],
+        );
+        assert!(with_none.is_ok());
+
+        let with_some = XbfStruct::new(
+            metadata.clone(),
+            vec![XbfOptional::new(
+                optional_metadata,
+                Some(XbfPrimitive::I32(42).into_base_type()),
+            )
+            .unwrap()
+            .into_base_type()],
+        );
+        assert!(with_some.is_ok());
+
+        let with_wrong_inner_type = XbfStruct::new(
+            metadata,
+            vec![XbfOptional::new(
+                XbfOptionalMetadata::new(XbfPrimitiveMetadata::U64.into()),
+                None,
+            )
+            .unwrap()
+            .into_base_type()],
+        );
+        assert!(with_wrong_inner_type.is_err());
+
+        let with_bare_value = XbfStruct::new(
+            XbfStructMetadata::new(
+                "test_struct".to_string(),
+                vec![(
+                    "a".to_string(),
+                    XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into()).into(),
+                )],
+            ),
+            vec![XbfPrimitive::I32(42).into_base_type()],
+        );
+        assert!(with_bare_value.is_err());
+    }
+
     #[test]
     fn test_struct_new_unchecked_works() {
         let name = "test_struct";
@@ -340,7 +665,7 @@ mod test {
             ],
         );
 
-        let _blatantly_wrong_fields = XbfStruct::new_unchecked(
+        let blatantly_wrong_fields = XbfStruct::new_unchecked(
             metadata,
             vec![
                 XbfPrimitive::I32(42).into_base_type(),
@@ -348,7 +673,14 @@ mod test {
             ],
         );
 
-        // TODO: accessor methods to test the contents of what's in the struct
+        assert_eq!(
+            blatantly_wrong_fields.get(field1_name),
+            Some(&XbfPrimitive::I32(42).into_base_type())
+        );
+        assert_eq!(
+            blatantly_wrong_fields.get(field2_name),
+            Some(&XbfPrimitive::U64(69).into_base_type())
+        );
     }
 
     #[test]
@@ -405,6 +737,148 @@ mod test {
         assert_eq!(my_struct, deserialized);
     }
 
+    #[test]
+    fn deserialize_error_records_path_and_offset() {
+        let player_metadata = XbfStructMetadata::new(
+            "player".to_string(),
+            vec![("name".to_string(), XbfPrimitiveMetadata::String.into())],
+        );
+        let game_metadata = XbfStructMetadata::new(
+            "game".to_string(),
+            vec![(
+                "players".to_string(),
+                XbfVecMetadata::new(player_metadata.into()).into(),
+            )],
+        );
+        let mut data = vec![];
+        data.extend_from_slice(&4u16.to_le_bytes());
+        for name in ["a", "b", "c"] {
+            data.extend_from_slice(&1u16.to_le_bytes());
+            data.extend_from_slice(name.as_bytes());
+        }
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.push(0xff);
+
+        let err =
+            XbfStruct::deserialize_struct_type(&game_metadata, &mut Cursor::new(data)).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::InvalidUtf8));
+        assert_eq!(err.path(), Some("root.players[3].name"));
+        assert_eq!(err.offset(), Some(14));
+    }
+
+    #[test]
+    fn serialize_error_records_path() {
+        let metadata = XbfStructMetadata::new(
+            "test_struct".to_string(),
+            vec![("blob".to_string(), XbfPrimitiveMetadata::Bytes.into())],
+        );
+        let test_struct = XbfStruct::new(
+            metadata,
+            vec![XbfPrimitive::Bytes(vec![0; u16::MAX as usize + 1]).into()],
+        )
+        .unwrap();
+
+        let err = test_struct.serialize_struct_type(&mut vec![]).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::LengthOverflow { .. }));
+        assert_eq!(err.path(), Some("root.blob"));
+        assert_eq!(err.offset(), None);
+    }
+
+    #[test]
+    fn serialize_rejects_wrong_field_count() {
+        let metadata = XbfStructMetadata::new(
+            "test_struct".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::U8.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::U8.into()),
+            ],
+        );
+        let test_struct = XbfStruct::new_unchecked(metadata, vec![XbfPrimitive::U8(1).into()]);
+        let mut writer = vec![];
+
+        let err = test_struct.serialize_struct_type(&mut writer).unwrap_err();
+
+        assert!(matches!(err, XbfError::TypeMismatch(_)));
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn field_accessors_work() {
+        let metadata = XbfStructMetadata::new(
+            "test_struct".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::String.into()),
+            ],
+        );
+        let mut test_struct = XbfStruct::new(
+            metadata,
+            vec![
+                XbfPrimitive::I32(42).into(),
+                XbfPrimitive::String("hello".to_string()).into(),
+            ],
+        )
+        .unwrap();
+
+        if let Some(XbfType::Primitive(XbfPrimitive::I32(x))) = test_struct.get_mut("a") {
+            *x += 1;
+        }
+        assert!(test_struct.get_mut("c").is_none());
+
+        let err = test_struct
+            .set("b", XbfPrimitive::I32(1).into())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "provided value for field b is of type Primitive(I32), expected Primitive(String)"
+        );
+        let err = test_struct
+            .set("c", XbfPrimitive::I32(1).into())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "struct has no field named c");
+
+        let pairs: Vec<_> = test_struct.iter().collect();
+        assert_eq!(
+            pairs,
+            [
+                ("a", &XbfPrimitive::I32(43).into()),
+                ("b", &XbfPrimitive::String("hello".to_string()).into()),
+            ]
+        );
+        assert_eq!(
+            test_struct.into_fields(),
+            [
+                ("a".to_string(), XbfPrimitive::I32(43).into()),
+                (
+                    "b".to_string(),
+                    XbfPrimitive::String("hello".to_string()).into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn field_accessors_handle_missing_values() {
+        let metadata = XbfStructMetadata::new(
+            "test_struct".to_string(),
+            vec![
+                ("a".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("b".to_string(), XbfPrimitiveMetadata::I32.into()),
+            ],
+        );
+        let mut test_struct = XbfStruct::new_unchecked(metadata, vec![XbfPrimitive::I32(1).into()]);
+
+        assert_eq!(test_struct.get("a"), Some(&XbfPrimitive::I32(1).into()));
+        assert_eq!(test_struct.get("b"), None);
+        assert_eq!(test_struct.get_mut("b"), None);
+        let err = test_struct
+            .set("b", XbfPrimitive::I32(2).into())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "struct has no value for field b");
+    }
+
     #[test]
     fn upcast_works() {
         let my_struct = XbfStruct::new(
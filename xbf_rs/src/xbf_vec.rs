@@ -1,7 +1,9 @@
 //! A vector as defined by the XBF specification.
 
+mod numeric_vec;
 mod vec;
 mod vec_metadata;
 
+pub use numeric_vec::*;
 pub use vec::*;
 pub use vec_metadata::*;
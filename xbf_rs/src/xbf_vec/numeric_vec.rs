@@ -0,0 +1,399 @@
+use crate::{
+    bf16, f16,
+    util::{len_encoded_len, preallocate, write_len, ReadContext, MAX_PREALLOCATION},
+    PrimitiveMismatchError, Result, XbfConfig, XbfPrimitive, XbfPrimitiveMetadata, XbfType,
+    XbfTypeUpcast, XbfVec, XbfVecMetadata,
+};
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+    ops::{Deref, DerefMut},
+};
+
+mod sealed {
+    use bytemuck::Pod;
+
+    pub trait Sealed: Pod {
+        /// Converts between native and little endian byte order.
+        fn swap_le(self) -> Self;
+    }
+}
+
+/// A fixed-size numeric primitive that can be stored in an [`XbfNumericVec`].
+///
+/// This trait is sealed, and is implemented for every integer and float type with an XBF
+/// primitive equivalent.
+pub trait XbfNumeric:
+    sealed::Sealed
+    + Debug
+    + PartialEq
+    + Into<XbfPrimitive>
+    + TryFrom<XbfType, Error = PrimitiveMismatchError>
+{
+    /// The metadata of the primitive type matching `Self`.
+    const METADATA: XbfPrimitiveMetadata;
+}
+
+macro_rules! impl_XbfNumeric {
+    ($ty:ty, $xbf_type:ident, |$x:ident| $swap_le:expr) => {
+        impl sealed::Sealed for $ty {
+            fn swap_le(self) -> Self {
+                let $x = self;
+                $swap_le
+            }
+        }
+
+        impl XbfNumeric for $ty {
+            const METADATA: XbfPrimitiveMetadata = XbfPrimitiveMetadata::$xbf_type;
+        }
+    };
+}
+
+impl_XbfNumeric!(u8, U8, |x| x);
+impl_XbfNumeric!(u16, U16, |x| x.to_le());
+impl_XbfNumeric!(u32, U32, |x| x.to_le());
+impl_XbfNumeric!(u64, U64, |x| x.to_le());
+impl_XbfNumeric!(u128, U128, |x| x.to_le());
+impl_XbfNumeric!(i8, I8, |x| x);
+impl_XbfNumeric!(i16, I16, |x| x.to_le());
+impl_XbfNumeric!(i32, I32, |x| x.to_le());
+impl_XbfNumeric!(i64, I64, |x| x.to_le());
+impl_XbfNumeric!(i128, I128, |x| x.to_le());
+impl_XbfNumeric!(f32, F32, |x| f32::from_bits(x.to_bits().to_le()));
+impl_XbfNumeric!(f64, F64, |x| f64::from_bits(x.to_bits().to_le()));
+impl_XbfNumeric!(f16, F16, |x| f16::from_bits(x.to_bits().to_le()));
+impl_XbfNumeric!(bf16, BF16, |x| bf16::from_bits(x.to_bits().to_le()));
+
+/// A vector of numeric primitives, stored as a contiguous native buffer.
+///
+/// This encodes to exactly the same bytes as an [`XbfVec`] of the matching primitive type, but
+/// avoids wrapping every element in an [`XbfType`]. On little-endian hosts the elements are
+/// serialized and deserialized with a single bulk copy.
+///
+/// The buffer can be used directly as a slice, and converted to and from a [`Vec`] and an
+/// [`XbfVec`].
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::XbfNumericVec;
+/// use xbf_rs::XbfPrimitive;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfVec;
+/// use xbf_rs::XbfVecMetadata;
+///
+/// let samples = XbfNumericVec::new(vec![0.5f32, 1.5, -2.0]);
+/// let mut writer = vec![];
+/// samples.serialize_numeric_vec_type(&mut writer).unwrap();
+///
+/// let vec = XbfVec::new(
+///     XbfVecMetadata::new(XbfPrimitiveMetadata::F32.into()),
+///     vec![
+///         XbfPrimitive::F32(0.5).into(),
+///         XbfPrimitive::F32(1.5).into(),
+///         XbfPrimitive::F32(-2.0).into(),
+///     ],
+/// )
+/// .unwrap();
+/// let mut expected = vec![];
+/// vec.serialize_vec_type(&mut expected).unwrap();
+///
+/// assert_eq!(writer, expected);
+/// assert_eq!(samples.iter().sum::<f32>(), 0.0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XbfNumericVec<T: XbfNumeric> {
+    elements: Vec<T>,
+}
+
+impl<T: XbfNumeric> XbfNumericVec<T> {
+    /// Creates a new numeric vector from the given elements.
+    pub fn new(elements: Vec<T>) -> Self {
+        Self { elements }
+    }
+
+    /// Returns the underlying buffer of elements.
+    pub fn into_inner(self) -> Vec<T> {
+        self.elements
+    }
+
+    /// Serialize a numeric vector as defined by the XBF specification.
+    ///
+    /// This writes the same bytes as [`XbfVec::serialize_vec_type`] would for the equivalent
+    /// [`XbfVec`], and like it **does not** write out the metadata of the type.
+    ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns
+    /// [`XbfError::LengthOverflow`](crate::XbfError::LengthOverflow) if the number of elements
+    /// does not fit in an unsigned 16-bit integer. Use
+    /// [`Self::serialize_numeric_vec_type_with_config`] to send longer vectors.
+    pub fn serialize_numeric_vec_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_numeric_vec_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize a numeric vector using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_numeric_vec_type`] for more details.
+    pub fn serialize_numeric_vec_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        write_len(self.elements.len(), writer, config)?;
+        if cfg!(target_endian = "little") {
+            writer.write_all(bytemuck::cast_slice(&self.elements))?;
+        } else {
+            for x in &self.elements {
+                writer.write_all(bytemuck::bytes_of(&x.swap_le()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deserialize a numeric vector as defined by the XBF specification.
+    ///
+    /// This reads the same bytes as [`XbfVec::deserialize_vec_type`] would when given
+    /// [`Self::get_metadata`], and like it **does not** read the metadata of the type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfNumericVec;
+    ///
+    /// let mut reader = vec![];
+    /// reader.extend_from_slice(&2u16.to_le_bytes());
+    /// reader.extend_from_slice(&42u32.to_le_bytes());
+    /// reader.extend_from_slice(&69u32.to_le_bytes());
+    /// let mut reader = std::io::Cursor::new(reader);
+    ///
+    /// let vec = XbfNumericVec::<u32>::deserialize_numeric_vec_type(&mut reader).unwrap();
+    ///
+    /// assert_eq!(*vec, [42, 69]);
+    /// ```
+    pub fn deserialize_numeric_vec_type(reader: &mut impl Read) -> Result<Self> {
+        Self::deserialize_numeric_vec_type_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize a numeric vector using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_numeric_vec_type`] for more details.
+    pub fn deserialize_numeric_vec_type_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<Self> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_numeric_vec_type_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_numeric_vec_type_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<Self> {
+        reader.nested(|reader| {
+            let len = reader.read_elements_len()?;
+            let mut elements = preallocate(len);
+            // grow in chunks so a lying length can't allocate more than is actually sent
+            while elements.len() < len {
+                let start = elements.len();
+                elements.resize(len.min(start + MAX_PREALLOCATION), T::zeroed());
+                reader.read_exact(bytemuck::cast_slice_mut(&mut elements[start..]))?;
+            }
+            if cfg!(target_endian = "big") {
+                elements.iter_mut().for_each(|x| *x = x.swap_le());
+            }
+            Ok(Self::new(elements))
+        })
+    }
+
+    /// Returns the metadata of the equivalent [`XbfVec`].
+    pub fn get_metadata(&self) -> XbfVecMetadata {
+        XbfVecMetadata::new(T::METADATA.into())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_numeric_vec_type`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_numeric_vec_type_with_config`] would write
+    /// using the given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        len_encoded_len(self.elements.len(), config) + std::mem::size_of_val(&*self.elements)
+    }
+}
+
+impl<T: XbfNumeric> Deref for XbfNumericVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl<T: XbfNumeric> DerefMut for XbfNumericVec<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.elements
+    }
+}
+
+impl<T: XbfNumeric> From<Vec<T>> for XbfNumericVec<T> {
+    fn from(value: Vec<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: XbfNumeric> From<XbfNumericVec<T>> for Vec<T> {
+    fn from(value: XbfNumericVec<T>) -> Self {
+        value.elements
+    }
+}
+
+impl<T: XbfNumeric> From<&XbfNumericVec<T>> for XbfVec {
+    fn from(value: &XbfNumericVec<T>) -> Self {
+        XbfVec::new_unchecked(
+            value.get_metadata(),
+            value
+                .elements
+                .iter()
+                .map(|&x| XbfType::Primitive(x.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: XbfNumeric> From<XbfNumericVec<T>> for XbfVec {
+    fn from(value: XbfNumericVec<T>) -> Self {
+        (&value).into()
+    }
+}
+
+impl<T: XbfNumeric> From<XbfNumericVec<T>> for XbfType {
+    fn from(value: XbfNumericVec<T>) -> Self {
+        XbfVec::from(value).into()
+    }
+}
+
+impl<T: XbfNumeric> From<&XbfNumericVec<T>> for XbfType {
+    fn from(value: &XbfNumericVec<T>) -> Self {
+        XbfVec::from(value).into()
+    }
+}
+
+impl<T: XbfNumeric> XbfTypeUpcast for XbfNumericVec<T> {}
+
+impl<T: XbfNumeric> TryFrom<XbfVec> for XbfNumericVec<T> {
+    type Error = PrimitiveMismatchError;
+
+    /// Converts a vector of `T`'s primitive type into a numeric vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PrimitiveMismatchError`] if the vector, or any of its elements, is not the
+    /// primitive type matching `T`.
+    fn try_from(value: XbfVec) -> Result<Self, Self::Error> {
+        if *value.metadata.inner_type != T::METADATA.into() {
+            return Err(PrimitiveMismatchError::new(
+                T::METADATA,
+                (*value.metadata.inner_type).clone(),
+            ));
+        }
+        value
+            .into_iter()
+            .map(T::try_from)
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DecodeLimits, LengthEncoding, XbfError, XbfMetadata};
+    use std::io::Cursor;
+
+    fn xbf_vec<T: XbfNumeric>(elements: &[T]) -> XbfVec {
+        XbfVec::new(
+            XbfVecMetadata::new(T::METADATA.into()),
+            elements
+                .iter()
+                .map(|&x| XbfType::Primitive(x.into()))
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn assert_same_encoding<T: XbfNumeric>(elements: &[T]) {
+        let config = XbfConfig::new().with_length_encoding(LengthEncoding::Varint);
+        let numeric = XbfNumericVec::new(elements.to_vec());
+        let vec = xbf_vec(elements);
+
+        let mut expected = vec![];
+        vec.serialize_vec_type_with_config(&mut expected, &config)
+            .unwrap();
+        let mut writer = vec![];
+        numeric
+            .serialize_numeric_vec_type_with_config(&mut writer, &config)
+            .unwrap();
+        assert_eq!(writer, expected);
+        assert_eq!(numeric.encoded_len_with_config(&config), writer.len());
+
+        let decoded = XbfNumericVec::<T>::deserialize_numeric_vec_type_with_config(
+            &mut Cursor::new(writer),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(decoded, numeric);
+    }
+
+    #[test]
+    fn numeric_vec_matches_vec_encoding() {
+        assert_same_encoding(&[0u8, 1, u8::MAX]);
+        assert_same_encoding(&[0u16, 1, u16::MAX]);
+        assert_same_encoding(&[0u32, 1, u32::MAX]);
+        assert_same_encoding(&[0u64, 1, u64::MAX]);
+        assert_same_encoding(&[0u128, 1, u128::MAX]);
+        assert_same_encoding(&[i8::MIN, -1, i8::MAX]);
+        assert_same_encoding(&[i16::MIN, -1, i16::MAX]);
+        assert_same_encoding(&[i32::MIN, -1, i32::MAX]);
+        assert_same_encoding(&[i64::MIN, -1, i64::MAX]);
+        assert_same_encoding(&[i128::MIN, -1, i128::MAX]);
+        assert_same_encoding(&[f32::MIN, -0.5, f32::INFINITY]);
+        assert_same_encoding(&[f64::MIN, -0.5, f64::INFINITY]);
+        assert_same_encoding(&[f16::MIN, f16::NEG_ONE, f16::INFINITY]);
+        assert_same_encoding(&[bf16::MIN, bf16::NEG_ONE, bf16::INFINITY]);
+        assert_same_encoding::<u32>(&[]);
+        assert_same_encoding(&(0..10_000).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn numeric_vec_converts_to_and_from_vec() {
+        let numeric = XbfNumericVec::new(vec![1i64, -2, 3]);
+        let vec = XbfVec::from(&numeric);
+
+        assert_eq!(vec, xbf_vec(&[1i64, -2, 3]));
+        assert_eq!(XbfNumericVec::try_from(vec).unwrap(), numeric);
+
+        let err = XbfNumericVec::<u64>::try_from(xbf_vec(&[1i64])).unwrap_err();
+        assert_eq!(err.expected(), XbfPrimitiveMetadata::U64);
+        assert_eq!(*err.actual(), XbfMetadata::from(XbfPrimitiveMetadata::I64));
+    }
+
+    #[test]
+    fn lying_numeric_vec_length_fails() {
+        let mut data = vec![];
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&42f64.to_le_bytes());
+        let config = XbfConfig::new()
+            .with_length_encoding(LengthEncoding::U32)
+            .with_limits(DecodeLimits::unlimited());
+
+        let err = XbfNumericVec::<f64>::deserialize_numeric_vec_type_with_config(
+            &mut Cursor::new(data),
+            &config,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+    }
+}
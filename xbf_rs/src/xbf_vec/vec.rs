@@ -1,6 +1,11 @@
-use crate::{XbfType, XbfTypeUpcast, XbfVecMetadata};
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{self, Read, Write};
+use crate::{
+    util::{len_encoded_len, preallocate, write_len, ReadContext},
+    Result, XbfConfig, XbfType, XbfTypeUpcast, XbfVecMetadata,
+};
+use std::{
+    io::{Read, Write},
+    ops::Index,
+};
 
 /// A vector type as defined by the XBF specification.
 #[derive(Debug, Clone, PartialEq)]
@@ -62,6 +67,7 @@ impl XbfVec {
     /// use xbf_rs::XbfVecMetadata;
     /// use xbf_rs::XbfPrimitive;
     /// use xbf_rs::XbfPrimitiveMetadata;
+    /// use xbf_rs::XbfMetadata;
     ///
     /// let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into());
     /// let data = vec![
@@ -70,19 +76,121 @@ impl XbfVec {
     /// ];
     /// let vec = XbfVec::new_unchecked(metadata, data);
     ///
-    /// // TODO: create accessors for the elements / implement the [] operator.
-    /// // assert_ne!()
+    /// // The second element is not the type in the metadata.
+    /// assert_ne!(XbfMetadata::from(&vec[1]), XbfPrimitiveMetadata::I32.into());
     /// ```
     pub fn new_unchecked(metadata: XbfVecMetadata, elements: Vec<XbfType>) -> Self {
         Self { metadata, elements }
     }
 
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Returns a reference to the element at the given index, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&XbfType> {
+        self.elements.get(index)
+    }
+
+    /// Returns an iterator over the elements of the vector.
+    pub fn iter(&self) -> std::slice::Iter<'_, XbfType> {
+        self.elements.iter()
+    }
+
+    /// Appends an element to the end of the vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ElementsNotHomogenousError`] if the element is not the type in the metadata.
+    /// The vector is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfVec;
+    /// use xbf_rs::XbfVecMetadata;
+    /// use xbf_rs::XbfPrimitive;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into());
+    /// let mut vec = XbfVec::new(metadata, vec![]).unwrap();
+    ///
+    /// vec.push(XbfPrimitive::I32(42).into()).unwrap();
+    /// assert!(vec.push(XbfPrimitive::U32(42).into()).is_err());
+    ///
+    /// assert_eq!(vec.len(), 1);
+    /// assert_eq!(vec[0], XbfPrimitive::I32(42).into());
+    /// ```
+    pub fn push(&mut self, element: XbfType) -> Result<(), ElementsNotHomogenousError> {
+        self.check_element(&element)?;
+        self.elements.push(element);
+        Ok(())
+    }
+
+    /// Inserts an element at the given index, shifting every element after it to the right.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ElementsNotHomogenousError`] if the element is not the type in the metadata.
+    /// The vector is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`, the same as [`Vec::insert`].
+    pub fn insert(
+        &mut self,
+        index: usize,
+        element: XbfType,
+    ) -> Result<(), ElementsNotHomogenousError> {
+        self.check_element(&element)?;
+        self.elements.insert(index, element);
+        Ok(())
+    }
+
+    /// Appends every element of an iterator to the end of the vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ElementsNotHomogenousError`] if any element is not the type in the metadata.
+    /// Every element is checked before any are added, so on an error the vector is left
+    /// unchanged.
+    pub fn extend(
+        &mut self,
+        elements: impl IntoIterator<Item = XbfType>,
+    ) -> Result<(), ElementsNotHomogenousError> {
+        let mut elements: Vec<_> = elements.into_iter().collect();
+        elements.iter().try_for_each(|x| self.check_element(x))?;
+        self.elements.append(&mut elements);
+        Ok(())
+    }
+
+    fn check_element(&self, element: &XbfType) -> Result<(), ElementsNotHomogenousError> {
+        if *self.metadata.inner_type == element.into() {
+            Ok(())
+        } else {
+            Err(ElementsNotHomogenousError)
+        }
+    }
+
     /// Serialize a vector as defined by the XBF specification.
     ///
     /// This function **does not** write out the metadata of the type. If you want to write out the
     /// metadata, convert this type to a [`XbfVecMetadata`] and call
     /// [`XbfVecMetadata::serialize_vec_metadata`].
     ///
+    /// # Errors
+    ///
+    /// Along with any errors from the writer, returns
+    /// [`XbfError::LengthOverflow`](crate::XbfError::LengthOverflow) if the number of elements, or
+    /// the length of any nested value, does not fit in an unsigned 16-bit integer. Use
+    /// [`Self::serialize_vec_type_with_config`] to send longer vectors.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -103,11 +211,23 @@ impl XbfVec {
     /// expected.extend_from_slice(&42u32.to_le_bytes());
     /// assert_eq!(writer, expected);
     /// ```
-    pub fn serialize_vec_type(&self, writer: &mut impl Write) -> io::Result<()> {
-        writer.write_u16::<LittleEndian>(self.elements.len() as u16)?;
-        self.elements
-            .iter()
-            .try_for_each(|e| e.serialize_base_type(writer))
+    pub fn serialize_vec_type(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_vec_type_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize a vector using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_vec_type`] for more details.
+    pub fn serialize_vec_type_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
+        write_len(self.elements.len(), writer, config)?;
+        self.elements.iter().enumerate().try_for_each(|(i, e)| {
+            e.serialize_base_type_with_config(writer, config)
+                .map_err(|e| e.within(format_args!("[{i}]")))
+        })
     }
 
     /// Deserialize a vector as defined by the XBF specification.
@@ -137,20 +257,45 @@ impl XbfVec {
     pub fn deserialize_vec_type(
         metadata: &XbfVecMetadata,
         reader: &mut impl Read,
-    ) -> io::Result<XbfVec> {
-        let inner_type = &metadata.inner_type;
-        let len = reader.read_u16::<LittleEndian>()? as usize;
-        let mut elements = Vec::with_capacity(len);
-        for _ in 0..len {
-            elements.push(XbfType::deserialize_base_type(inner_type, reader)?);
-        }
-        Ok(XbfVec::new_unchecked(metadata.clone(), elements))
+    ) -> Result<XbfVec> {
+        Self::deserialize_vec_type_with_config(metadata, reader, &XbfConfig::default())
+    }
+
+    /// Deserialize a vector using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_vec_type`] for more details.
+    pub fn deserialize_vec_type_with_config(
+        metadata: &XbfVecMetadata,
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfVec> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_vec_type_with_context(metadata, reader)
+        })
+    }
+
+    pub(crate) fn deserialize_vec_type_with_context(
+        metadata: &XbfVecMetadata,
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfVec> {
+        reader.nested(|reader| {
+            let inner_type = &metadata.inner_type;
+            let len = reader.read_elements_len()?;
+            let mut elements = preallocate(len);
+            for i in 0..len {
+                elements.push(
+                    XbfType::deserialize_base_type_with_context(inner_type, reader)
+                        .map_err(|e| e.within(format_args!("[{i}]")))?,
+                );
+            }
+            Ok(XbfVec::new_unchecked(metadata.clone(), elements))
+        })
     }
 
     /// Returns the metadata of the vector.
     ///
-    /// Getting the metadata returns an owned [`XbfVecMetadata`], which requires a clone to take
-    /// place. This will likely be changed in the future to be more efficient.
+    /// Getting the metadata returns an owned [`XbfVecMetadata`], which only
+    /// clones the reference counted pointers it holds.
     ///
     /// # Example
     ///
@@ -172,6 +317,22 @@ impl XbfVec {
     pub fn get_metadata(&self) -> XbfVecMetadata {
         self.metadata.clone()
     }
+
+    /// Returns the number of bytes [`Self::serialize_vec_type`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_vec_type_with_config`] would write using the
+    /// given [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        len_encoded_len(self.elements.len(), config)
+            + self
+                .elements
+                .iter()
+                .map(|x| x.encoded_len_with_config(config))
+                .sum::<usize>()
+    }
 }
 
 /// Error type for [`XbfVec`]
@@ -184,14 +345,41 @@ pub struct ElementsNotHomogenousError;
 
 impl XbfTypeUpcast for XbfVec {}
 
+impl Index<usize> for XbfVec {
+    type Output = XbfType;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.elements[index]
+    }
+}
+
+impl IntoIterator for XbfVec {
+    type Item = XbfType;
+    type IntoIter = std::vec::IntoIter<XbfType>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a XbfVec {
+    type Item = &'a XbfType;
+    type IntoIter = std::slice::Iter<'a, XbfType>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
         xbf_primitive::{XbfPrimitive, XbfPrimitiveMetadata},
-        XbfMetadataUpcast, XbfVecMetadata,
+        DecodeLimits, LengthEncoding, LimitExceededError, XbfError, XbfMetadataUpcast, XbfOptional,
+        XbfOptionalMetadata, XbfStruct, XbfStructMetadata, XbfVecMetadata,
     };
-    use std::io::Cursor;
+    use std::{io::Cursor, sync::Arc};
 
     #[test]
     fn vec_new_fails_with_not_homogenous_data() {
@@ -201,6 +389,65 @@ mod test {
         assert_eq!(err, ElementsNotHomogenousError);
     }
 
+    #[test]
+    fn vec_accessors_work() {
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into());
+        let mut vec = XbfVec::new(metadata, vec![XbfPrimitive::I32(1).into()]).unwrap();
+
+        vec.insert(0, XbfPrimitive::I32(0).into()).unwrap();
+        vec.extend([XbfPrimitive::I32(2).into(), XbfPrimitive::I32(3).into()])
+            .unwrap();
+        assert_eq!(
+            vec.extend([XbfPrimitive::I32(4).into(), XbfPrimitive::U32(5).into()]),
+            Err(ElementsNotHomogenousError)
+        );
+        assert_eq!(
+            vec.insert(0, XbfPrimitive::U32(5).into()),
+            Err(ElementsNotHomogenousError)
+        );
+
+        assert_eq!(vec.len(), 4);
+        assert!(!vec.is_empty());
+        assert_eq!(vec.get(3), Some(&XbfPrimitive::I32(3).into()));
+        assert_eq!(vec.get(4), None);
+        let expected: Vec<XbfType> = (0..4).map(|x| XbfPrimitive::I32(x).into()).collect();
+        assert!(vec.iter().eq(expected.iter()));
+        assert!((&vec).into_iter().eq(expected.iter()));
+        assert_eq!(vec.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn deserialized_vec_can_be_inspected() {
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into());
+        let vec = XbfVec::deserialize_vec_type(&metadata, &mut Cursor::new([2, 0, 7, 9])).unwrap();
+
+        assert_eq!(vec[0], XbfPrimitive::U8(7).into());
+        assert_eq!(vec[1], XbfPrimitive::U8(9).into());
+    }
+
+    #[test]
+    fn vec_new_with_optional_elements_works() {
+        let optional_metadata = XbfOptionalMetadata::new(XbfPrimitiveMetadata::I32.into());
+        let metadata = XbfVecMetadata::new(optional_metadata.to_base_metadata());
+        let some = XbfOptional::new(
+            optional_metadata.clone(),
+            Some(XbfPrimitive::I32(42).into()),
+        )
+        .unwrap();
+        let none = XbfOptional::new(optional_metadata, None).unwrap();
+
+        let vec = XbfVec::new(metadata.clone(), vec![some.into(), none.into()]);
+        assert!(vec.is_ok());
+
+        let wrong_inner = XbfOptional::new(
+            XbfOptionalMetadata::new(XbfPrimitiveMetadata::U32.into()),
+            None,
+        )
+        .unwrap();
+        let err = XbfVec::new(metadata, vec![wrong_inner.into()]).unwrap_err();
+        assert_eq!(err, ElementsNotHomogenousError);
+    }
+
     #[test]
     fn serialize_vec_primitive_works() {
         const TEST_NUM: i32 = 42;
@@ -227,6 +474,77 @@ mod test {
         assert_eq!(vec, deserialized);
     }
 
+    #[test]
+    fn serialize_long_vec_fails_with_default_config() {
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into_base_metadata());
+        let vec = XbfVec::new(
+            metadata,
+            vec![XbfPrimitive::U8(0).into(); u16::MAX as usize + 1],
+        )
+        .unwrap();
+        let mut writer = vec![];
+
+        let err = vec.serialize_vec_type(&mut writer).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::LengthOverflow { .. }));
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn long_vec_serde_works_with_wide_lengths() {
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into_base_metadata());
+        let vec = XbfVec::new(metadata.clone(), vec![XbfPrimitive::U8(1).into(); 100_000]).unwrap();
+
+        for length_encoding in [LengthEncoding::U32, LengthEncoding::Varint] {
+            let config = XbfConfig::new().with_length_encoding(length_encoding);
+            let mut writer = vec![];
+
+            vec.serialize_vec_type_with_config(&mut writer, &config)
+                .unwrap();
+
+            let mut reader = Cursor::new(writer);
+            let deserialized =
+                XbfVec::deserialize_vec_type_with_config(&metadata, &mut reader, &config).unwrap();
+
+            assert_eq!(deserialized, vec);
+        }
+    }
+
+    #[test]
+    fn vec_element_limit_is_enforced() {
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U8.into_base_metadata());
+        let vec = XbfVec::new(metadata.clone(), vec![XbfPrimitive::U8(1).into(); 5]).unwrap();
+        let mut writer = vec![];
+        vec.serialize_vec_type(&mut writer).unwrap();
+
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_vec_elements(4));
+        let err =
+            XbfVec::deserialize_vec_type_with_config(&metadata, &mut Cursor::new(writer), &config)
+                .unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::VecElements {
+                limit: 4,
+                actual: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn lying_vec_length_does_not_allocate() {
+        let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::U64.into_base_metadata());
+        let config = XbfConfig::new()
+            .with_length_encoding(LengthEncoding::U32)
+            .with_limits(DecodeLimits::unlimited());
+        let mut reader = Cursor::new(u32::MAX.to_le_bytes());
+
+        let err =
+            XbfVec::deserialize_vec_type_with_config(&metadata, &mut reader, &config).unwrap_err();
+
+        assert!(matches!(err.root_cause(), XbfError::UnexpectedEof));
+    }
+
     #[test]
     fn serialize_vec_of_vec_works() {
         const TEST_NUM: i32 = 42;
@@ -241,7 +559,7 @@ mod test {
         )
         .unwrap();
         let vec_of_vec_of_i32 = XbfVec::new_unchecked(
-            vec_of_i32_metadata.into(),
+            vec_of_i32_metadata,
             vec![vec_of_two_i32.clone().into(), vec_of_two_i32.into()],
         );
 
@@ -318,4 +636,44 @@ mod test {
 
         assert_eq!(vec, expected.into());
     }
+
+    #[test]
+    fn deserialized_elements_share_metadata() {
+        let struct_metadata = XbfStructMetadata::new(
+            "point".to_string(),
+            vec![
+                ("x".to_string(), XbfPrimitiveMetadata::I32.into()),
+                ("y".to_string(), XbfPrimitiveMetadata::I32.into()),
+            ],
+        );
+        let vec_metadata = XbfVecMetadata::new(struct_metadata.to_base_metadata());
+        let point = XbfStruct::new(
+            struct_metadata.clone(),
+            vec![XbfPrimitive::I32(1).into(), XbfPrimitive::I32(2).into()],
+        )
+        .unwrap();
+        let vec = XbfVec::new(
+            vec_metadata.clone(),
+            vec![point.clone().into(), point.into()],
+        )
+        .unwrap();
+        let mut writer = vec![];
+        vec.serialize_vec_type(&mut writer).unwrap();
+
+        let vec = XbfVec::deserialize_vec_type(&vec_metadata, &mut Cursor::new(writer)).unwrap();
+
+        assert!(Arc::ptr_eq(
+            &vec.metadata.inner_type,
+            &vec_metadata.inner_type
+        ));
+        for element in &vec {
+            let XbfType::Struct(element) = element else {
+                panic!("expected a struct, found {element:?}");
+            };
+            assert!(Arc::ptr_eq(
+                &element.metadata.fields,
+                &struct_metadata.fields
+            ));
+        }
+    }
 }
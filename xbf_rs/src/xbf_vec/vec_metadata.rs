@@ -1,6 +1,12 @@
-use crate::{xbf_primitive::XbfPrimitiveMetadata, XbfMetadata, XbfMetadataUpcast, XbfVec};
+use crate::{
+    util::ReadContext, xbf_primitive::XbfPrimitiveMetadata, Result, XbfConfig, XbfMetadata,
+    XbfMetadataUpcast, XbfVec,
+};
 use byteorder::WriteBytesExt;
-use std::io::{self, Read, Write};
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
 
 /// The metadata discriminant for a Vec type.
 ///
@@ -12,11 +18,9 @@ pub const VEC_METADATA_DISCRIMINANT: u8 = XbfPrimitiveMetadata::String as u8 + 1
 ///
 /// Internally the metadata is stored on the heap to avoid having a recursive, infinitely sized
 /// type on the stack.
-///
-/// TODO: accessor for the inner type?
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct XbfVecMetadata {
-    pub(crate) inner_type: Box<XbfMetadata>,
+    pub(crate) inner_type: Arc<XbfMetadata>,
 }
 
 impl XbfVecMetadata {
@@ -36,7 +40,7 @@ impl XbfVecMetadata {
     /// ```
     pub fn new(inner_type: XbfMetadata) -> Self {
         Self {
-            inner_type: Box::new(inner_type),
+            inner_type: Arc::new(inner_type),
         }
     }
 
@@ -53,7 +57,25 @@ impl XbfVecMetadata {
     /// let metadata = XbfVecMetadata::from_boxed_type(boxed_inner_type);
     /// ````
     pub fn from_boxed_type(inner_type: Box<XbfMetadata>) -> Self {
-        Self { inner_type }
+        Self {
+            inner_type: inner_type.into(),
+        }
+    }
+
+    /// Returns the type of the elements of the vector.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use xbf_rs::XbfVecMetadata;
+    /// use xbf_rs::XbfPrimitiveMetadata;
+    ///
+    /// let metadata = XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into());
+    ///
+    /// assert_eq!(metadata.inner_type(), &XbfPrimitiveMetadata::I32.into());
+    /// ```
+    pub fn inner_type(&self) -> &XbfMetadata {
+        &self.inner_type
     }
 
     /// Serialize Vec metadata as defined by the XBF specification.
@@ -72,9 +94,22 @@ impl XbfVecMetadata {
     ///
     /// assert_eq!(writer, [VEC_METADATA_DISCRIMINANT, XbfPrimitiveMetadata::I32 as u8]);
     /// ```
-    pub fn serialize_vec_metadata(&self, writer: &mut impl Write) -> io::Result<()> {
+    pub fn serialize_vec_metadata(&self, writer: &mut impl Write) -> Result<()> {
+        self.serialize_vec_metadata_with_config(writer, &XbfConfig::default())
+    }
+
+    /// Serialize Vec metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::serialize_vec_metadata`] for more details.
+    pub fn serialize_vec_metadata_with_config(
+        &self,
+        writer: &mut impl Write,
+        config: &XbfConfig,
+    ) -> Result<()> {
         writer.write_u8(VEC_METADATA_DISCRIMINANT)?;
-        self.inner_type.serialize_base_metadata(writer)
+        self.inner_type
+            .serialize_base_metadata_with_config(writer, config)
+            .map_err(|e| e.within("[]"))
     }
 
     /// Deserialize Vec metadata as defined by the XBF specification.
@@ -97,9 +132,41 @@ impl XbfVecMetadata {
     ///
     /// assert_eq!(metadata, XbfVecMetadata::new(XbfPrimitiveMetadata::I32.into()));
     /// ```
-    pub fn deserialize_vec_metadata(reader: &mut impl Read) -> io::Result<XbfVecMetadata> {
-        let inner_type = XbfMetadata::deserialize_base_metadata(reader)?;
-        Ok(XbfVecMetadata::new(inner_type))
+    pub fn deserialize_vec_metadata(reader: &mut impl Read) -> Result<XbfVecMetadata> {
+        Self::deserialize_vec_metadata_with_config(reader, &XbfConfig::default())
+    }
+
+    /// Deserialize Vec metadata using the given [`XbfConfig`].
+    ///
+    /// See [`Self::deserialize_vec_metadata`] for more details.
+    pub fn deserialize_vec_metadata_with_config(
+        reader: &mut impl Read,
+        config: &XbfConfig,
+    ) -> Result<XbfVecMetadata> {
+        ReadContext::decode(reader, config, |reader| {
+            Self::deserialize_vec_metadata_with_context(reader)
+        })
+    }
+
+    pub(crate) fn deserialize_vec_metadata_with_context(
+        reader: &mut ReadContext<impl Read>,
+    ) -> Result<XbfVecMetadata> {
+        reader.nested(|reader| {
+            let inner_type = XbfMetadata::deserialize_base_metadata_with_context(reader)
+                .map_err(|e| e.within("[]"))?;
+            Ok(XbfVecMetadata::new(inner_type))
+        })
+    }
+
+    /// Returns the number of bytes [`Self::serialize_vec_metadata`] would write.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_config(&XbfConfig::default())
+    }
+
+    /// Returns the number of bytes [`Self::serialize_vec_metadata_with_config`] would write using the given
+    /// [`XbfConfig`].
+    pub fn encoded_len_with_config(&self, config: &XbfConfig) -> usize {
+        1 + self.inner_type.encoded_len_with_config(config)
     }
 }
 
@@ -107,14 +174,15 @@ impl XbfMetadataUpcast for XbfVecMetadata {}
 
 impl From<&XbfVec> for XbfVecMetadata {
     fn from(value: &XbfVec) -> Self {
-        Self::from_boxed_type(value.metadata.inner_type.clone())
+        value.metadata.clone()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use io::Cursor;
+    use crate::{DecodeLimits, LimitExceededError, XbfError};
+    use std::io::Cursor;
 
     #[test]
     fn primitive_metadata_serde_works() {
@@ -164,8 +232,8 @@ mod test {
     #[test]
     fn nested_vec_metadata_serialize_works() {
         let vec_vec_i32_metadata = XbfVecMetadata {
-            inner_type: Box::new(XbfMetadata::Vec(XbfVecMetadata {
-                inner_type: Box::new(XbfMetadata::Primitive(XbfPrimitiveMetadata::I32)),
+            inner_type: Arc::new(XbfMetadata::Vec(XbfVecMetadata {
+                inner_type: Arc::new(XbfMetadata::Primitive(XbfPrimitiveMetadata::I32)),
             })),
         };
         let mut writer = vec![];
@@ -194,6 +262,47 @@ mod test {
         assert_eq!(vec_vec_i32_metadata, expected_metadata);
     }
 
+    #[test]
+    fn deeply_nested_metadata_fails() {
+        let mut data = vec![VEC_METADATA_DISCRIMINANT; 100_000];
+        data.push(XbfPrimitiveMetadata::I32 as u8);
+        let mut reader = Cursor::new(data);
+
+        let err = XbfMetadata::deserialize_base_metadata(&mut reader).unwrap_err();
+
+        assert!(matches!(
+            err.root_cause(),
+            XbfError::LimitExceeded(LimitExceededError::Depth { limit: 128 })
+        ));
+    }
+
+    #[test]
+    fn max_depth_counts_each_vec() {
+        let config = XbfConfig::new().with_limits(DecodeLimits::new().with_max_depth(2));
+        let data = [
+            VEC_METADATA_DISCRIMINANT,
+            VEC_METADATA_DISCRIMINANT,
+            XbfPrimitiveMetadata::I32 as u8,
+        ];
+        assert!(XbfMetadata::deserialize_base_metadata_with_config(
+            &mut Cursor::new(data),
+            &config
+        )
+        .is_ok());
+
+        let data = [
+            VEC_METADATA_DISCRIMINANT,
+            VEC_METADATA_DISCRIMINANT,
+            VEC_METADATA_DISCRIMINANT,
+            XbfPrimitiveMetadata::I32 as u8,
+        ];
+        assert!(XbfMetadata::deserialize_base_metadata_with_config(
+            &mut Cursor::new(data),
+            &config
+        )
+        .is_err());
+    }
+
     #[test]
     fn upcast_works() {
         let primitive_metadata = XbfPrimitiveMetadata::I32;
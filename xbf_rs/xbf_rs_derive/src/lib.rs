@@ -0,0 +1,292 @@
+//! Derive macros for [xbf_rs](https://docs.rs/xbf_rs).
+//!
+//! These are re-exported by xbf_rs when its `derive` feature is enabled, and should be used
+//! through there rather than by depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Ident, LitStr, Type};
+
+/// Implements `xbf_rs::Xbf` for a struct with named fields.
+///
+/// The struct maps to an `XbfStruct` with the same name as the type and one field per struct
+/// field, in the order they are declared. Every field must itself implement `Xbf`. The metadata
+/// is built once and stored in a static. The derive also implements `From<T>` and `From<&T>` for
+/// `XbfType`, and `TryFrom<XbfType>` for `T`.
+///
+/// A `Vec<u8>` field is a vector of `U8`, as it is everywhere else. Declare the field as
+/// `xbf_rs::XbfBytes` to send it as `Bytes` instead.
+///
+/// # Attributes
+///
+/// - `#[xbf(rename = "name")]` on the struct or a field sets the name used in the metadata. Names
+///   can't be empty, and no two fields that are sent can end up with the same name.
+/// - `#[xbf(skip)]` on a field leaves it out of the metadata entirely. It is set to its
+///   [`Default`] value when converting or deserializing.
+///
+/// # Example
+///
+/// ```rust
+/// use xbf_rs::Xbf;
+/// use xbf_rs::XbfPrimitiveMetadata;
+/// use xbf_rs::XbfStructMetadata;
+///
+/// #[derive(Xbf, Debug, PartialEq)]
+/// #[xbf(rename = "player")]
+/// struct Player {
+///     id: u8,
+///     #[xbf(rename = "display_name")]
+///     name: String,
+///     #[xbf(skip)]
+///     cached_score: Option<u64>,
+/// }
+///
+/// assert_eq!(
+///     Player::xbf_metadata(),
+///     XbfStructMetadata::new(
+///         "player".to_string(),
+///         vec![
+///             ("id".to_string(), XbfPrimitiveMetadata::U8.into()),
+///             ("display_name".to_string(), XbfPrimitiveMetadata::String.into()),
+///         ],
+///     )
+///     .into()
+/// );
+///
+/// let player = Player { id: 7, name: "alice".to_string(), cached_score: None };
+/// let mut writer = vec![];
+/// player.serialize_xbf(&mut writer).unwrap();
+///
+/// assert_eq!(Player::deserialize_xbf(&mut std::io::Cursor::new(writer)).unwrap(), player);
+/// ```
+///
+/// Renaming a field to the name of another is rejected:
+///
+/// ```compile_fail
+/// #[derive(xbf_rs::Xbf)]
+/// struct Player {
+///     id: u8,
+///     #[xbf(rename = "id")]
+///     name: String,
+/// }
+/// ```
+///
+/// As is an empty name:
+///
+/// ```compile_fail
+/// #[derive(xbf_rs::Xbf)]
+/// struct Player {
+///     #[xbf(rename = "")]
+///     id: u8,
+/// }
+/// ```
+#[proc_macro_derive(Xbf, attributes(xbf))]
+pub fn derive_xbf(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The options set with `#[xbf(...)]` on a struct or field.
+#[derive(Default)]
+struct XbfAttributes {
+    rename: Option<String>,
+    skip: bool,
+}
+
+impl XbfAttributes {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut result = Self::default();
+        for attr in attrs.iter().filter(|x| x.path().is_ident("xbf")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let name = meta.value()?.parse::<LitStr>()?;
+                    if name.value().is_empty() {
+                        return Err(syn::Error::new_spanned(name, "xbf names can't be empty"));
+                    }
+                    result.rename = Some(name.value());
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    result.skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown xbf attribute, expected `rename` or `skip`"))
+                }
+            })?;
+        }
+        Ok(result)
+    }
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    name: String,
+    skip: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() || input.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "Xbf cannot be derived for generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input,
+                    "Xbf can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "Xbf can only be derived for structs",
+            ))
+        }
+    };
+
+    let container = XbfAttributes::parse(&input.attrs)?;
+    if container.skip {
+        return Err(syn::Error::new_spanned(
+            &input,
+            "`skip` can only be used on fields",
+        ));
+    }
+    let struct_name = container.rename.unwrap_or_else(|| ident.to_string());
+
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let attrs = XbfAttributes::parse(&field.attrs)?;
+            let ident = field.ident.clone().expect("fields are named");
+            Ok(Field {
+                name: attrs.rename.unwrap_or_else(|| ident.to_string()),
+                ident,
+                ty: field.ty.clone(),
+                skip: attrs.skip,
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let sent: Vec<_> = fields.iter().filter(|x| !x.skip).collect();
+    for (i, field) in sent.iter().enumerate() {
+        if sent[..i].iter().any(|x| x.name == field.name) {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                format!("duplicate xbf field name `{}`", field.name),
+            ));
+        }
+    }
+
+    let metadata_fields = sent.iter().map(|Field { ty, name, .. }| {
+        quote! { (#name.to_string(), <#ty as ::xbf_rs::Xbf>::xbf_metadata()) }
+    });
+    let to_xbf_fields = sent.iter().map(|Field { ident, .. }| {
+        quote! { ::xbf_rs::Xbf::to_xbf_type(&self.#ident) }
+    });
+    let from_xbf_fields = fields.iter().map(|field| {
+        let Field { ident, name, .. } = field;
+        if field.skip {
+            quote! { #ident: ::std::default::Default::default() }
+        } else {
+            quote! { #ident: ::xbf_rs::__private::field(&mut fields, #name)? }
+        }
+    });
+    let serialize_fields = sent.iter().map(|Field { ident, name, .. }| {
+        let segment = format!(".{name}");
+        quote! {
+            ::xbf_rs::Xbf::serialize_xbf_with_config(&self.#ident, writer, config)
+                .map_err(|e| ::xbf_rs::__private::within(e, #segment))?;
+        }
+    });
+    let deserialize_fields = fields.iter().map(|field| {
+        let Field {
+            ident, ty, name, ..
+        } = field;
+        let segment = format!(".{name}");
+        if field.skip {
+            quote! { #ident: ::std::default::Default::default() }
+        } else {
+            quote! {
+                #ident: <#ty as ::xbf_rs::Xbf>::deserialize_xbf_with_context(reader)
+                    .map_err(|e| ::xbf_rs::__private::within(e, #segment))?
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl ::xbf_rs::Xbf for #ident {
+            fn xbf_metadata() -> ::xbf_rs::XbfMetadata {
+                static METADATA: ::std::sync::OnceLock<::xbf_rs::XbfMetadata> =
+                    ::std::sync::OnceLock::new();
+                METADATA
+                    .get_or_init(|| {
+                        ::xbf_rs::XbfStructMetadata::new(
+                            #struct_name.to_string(),
+                            ::std::vec![#(#metadata_fields),*],
+                        )
+                        .into()
+                    })
+                    .clone()
+            }
+
+            fn to_xbf_type(&self) -> ::xbf_rs::XbfType {
+                let ::xbf_rs::XbfMetadata::Struct(metadata) = Self::xbf_metadata() else {
+                    unreachable!("derived metadata is always a struct")
+                };
+                ::xbf_rs::XbfStruct::new_unchecked(metadata, ::std::vec![#(#to_xbf_fields),*])
+                    .into()
+            }
+
+            fn from_xbf_type(value: ::xbf_rs::XbfType) -> ::xbf_rs::Result<Self> {
+                #[allow(unused_mut, unused_variables)]
+                let mut fields =
+                    ::xbf_rs::__private::struct_fields(value, &Self::xbf_metadata())?;
+                Ok(Self { #(#from_xbf_fields),* })
+            }
+
+            fn serialize_xbf_with_config(
+                &self,
+                writer: &mut impl ::std::io::Write,
+                config: &::xbf_rs::XbfConfig,
+            ) -> ::xbf_rs::Result<()> {
+                #(#serialize_fields)*
+                Ok(())
+            }
+
+            fn deserialize_xbf_with_context(
+                reader: &mut ::xbf_rs::__private::ReadContext<impl ::std::io::Read>,
+            ) -> ::xbf_rs::Result<Self> {
+                #[allow(unused_variables)]
+                reader.nested(|reader| Ok(Self { #(#deserialize_fields),* }))
+            }
+        }
+
+        impl ::std::convert::From<&#ident> for ::xbf_rs::XbfType {
+            fn from(value: &#ident) -> Self {
+                ::xbf_rs::Xbf::to_xbf_type(value)
+            }
+        }
+
+        impl ::std::convert::From<#ident> for ::xbf_rs::XbfType {
+            fn from(value: #ident) -> Self {
+                ::xbf_rs::Xbf::to_xbf_type(&value)
+            }
+        }
+
+        impl ::std::convert::TryFrom<::xbf_rs::XbfType> for #ident {
+            type Error = ::xbf_rs::XbfError;
+
+            fn try_from(value: ::xbf_rs::XbfType) -> ::xbf_rs::Result<Self> {
+                <Self as ::xbf_rs::Xbf>::from_xbf_type(value)
+            }
+        }
+    })
+}
@@ -0,0 +1,159 @@
+use std::io::Cursor;
+use xbf_rs::{
+    Xbf, XbfConfig, XbfError, XbfMetadata, XbfPrimitive, XbfPrimitiveMetadata, XbfStruct,
+    XbfStructMetadata, XbfType, XbfVecMetadata,
+};
+
+#[derive(Xbf, Debug, Clone, PartialEq)]
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Xbf, Debug, Clone, PartialEq)]
+#[xbf(rename = "player")]
+struct Player {
+    id: u32,
+    #[xbf(rename = "display_name")]
+    name: String,
+    position: Position,
+    friends: Vec<u32>,
+    title: Option<String>,
+    #[xbf(skip)]
+    cached: u64,
+}
+
+fn player() -> Player {
+    Player {
+        id: 7,
+        name: "alice".to_string(),
+        position: Position { x: 1.0, y: -2.5 },
+        friends: vec![3, 4],
+        title: Some("champion".to_string()),
+        cached: 0,
+    }
+}
+
+#[test]
+fn metadata_follows_the_struct() {
+    let position = XbfStructMetadata::new(
+        "Position".to_string(),
+        vec![
+            ("x".to_string(), XbfPrimitiveMetadata::F32.into()),
+            ("y".to_string(), XbfPrimitiveMetadata::F32.into()),
+        ],
+    );
+
+    let XbfMetadata::Struct(metadata) = Player::xbf_metadata() else {
+        panic!("expected struct metadata");
+    };
+
+    assert_eq!(metadata.name(), "player");
+    let names: Vec<_> = metadata.fields().iter().map(|(x, _)| x.as_str()).collect();
+    assert_eq!(
+        names,
+        ["id", "display_name", "position", "friends", "title"]
+    );
+    assert_eq!(metadata.field("position"), Some(&position.into()));
+    assert_eq!(
+        metadata.field("friends"),
+        Some(&XbfVecMetadata::new(XbfPrimitiveMetadata::U32.into()).into())
+    );
+}
+
+#[test]
+fn direct_serialize_matches_dynamic() {
+    let config = XbfConfig::new().with_length_encoding(xbf_rs::LengthEncoding::Varint);
+    for config in [XbfConfig::default(), config] {
+        let player = player();
+        let mut direct = vec![];
+        player
+            .serialize_xbf_with_config(&mut direct, &config)
+            .unwrap();
+        let mut dynamic = vec![];
+        XbfType::from(&player)
+            .serialize_base_type_with_config(&mut dynamic, &config)
+            .unwrap();
+        assert_eq!(direct, dynamic);
+
+        let deserialized =
+            Player::deserialize_xbf_with_config(&mut Cursor::new(&direct), &config).unwrap();
+        assert_eq!(deserialized, player);
+
+        let dynamic = XbfType::deserialize_base_type_with_config(
+            &Player::xbf_metadata(),
+            &mut Cursor::new(&direct),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(Player::try_from(dynamic).unwrap(), player);
+    }
+}
+
+#[test]
+fn skipped_fields_are_defaulted() {
+    let player = Player {
+        cached: 42,
+        ..player()
+    };
+
+    let converted = Player::try_from(XbfType::from(player.clone())).unwrap();
+
+    assert_eq!(converted.cached, 0);
+    assert_eq!(
+        converted,
+        Player {
+            cached: 0,
+            ..player
+        }
+    );
+}
+
+#[test]
+fn conversion_checks_the_metadata() {
+    let position = XbfType::from(Position { x: 0.0, y: 0.0 });
+    let err = Player::try_from(position).unwrap_err();
+    assert!(matches!(err, XbfError::TypeMismatch(_)));
+
+    let XbfType::Struct(mut player) = XbfType::from(player()) else {
+        panic!("expected a struct");
+    };
+    *player.get_mut("display_name").unwrap() = XbfPrimitive::U8(1).into();
+    let err = Player::try_from(XbfType::from(player)).unwrap_err();
+    assert!(matches!(err.root_cause(), XbfError::TypeMismatch(_)));
+    assert_eq!(err.path(), Some("root.display_name"));
+}
+
+#[test]
+fn errors_record_the_field_path() {
+    let mut data = vec![];
+    player().serialize_xbf(&mut data).unwrap();
+    // the first byte of the name, after the id and the length of the name
+    data[6] = 0xff;
+
+    let err = Player::deserialize_xbf(&mut Cursor::new(data)).unwrap_err();
+
+    assert!(matches!(err.root_cause(), XbfError::InvalidUtf8));
+    assert_eq!(err.path(), Some("root.display_name"));
+}
+
+#[test]
+fn empty_structs_work() {
+    #[derive(Xbf, Debug, PartialEq)]
+    struct Empty {}
+
+    let mut data = vec![];
+    Empty {}.serialize_xbf(&mut data).unwrap();
+
+    assert!(data.is_empty());
+    assert_eq!(
+        Empty::deserialize_xbf(&mut Cursor::new(data)).unwrap(),
+        Empty {}
+    );
+    assert_eq!(
+        XbfType::from(Empty {}),
+        XbfStruct::new(XbfStructMetadata::new("Empty".to_string(), vec![]), vec![])
+            .unwrap()
+            .into()
+    );
+}